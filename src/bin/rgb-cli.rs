@@ -13,6 +13,7 @@
 
 use clap::Clap;
 use log::*;
+use std::convert::TryFrom;
 use std::env;
 
 use rgb_node::cli::{Config, Opts, Runtime};
@@ -21,7 +22,8 @@ use rgb_node::error::BootstrapError;
 fn main() -> Result<(), BootstrapError> {
     // TODO #60: Parse config file as well
     let opts: Opts = Opts::parse();
-    let config: Config = opts.clone().into();
+    let config = Config::try_from(opts.clone())
+        .map_err(|err| BootstrapError::ArgParseError(err.to_string()))?;
 
     if env::var("RUST_LOG").is_err() {
         env::set_var(
@@ -11,6 +11,7 @@
 // along with this software.
 // If not, see <https://opensource.org/licenses/MIT>.
 
+use core::convert::TryFrom;
 use core::fmt::Display;
 use core::str::FromStr;
 use std::path::PathBuf;
@@ -20,6 +21,7 @@ use lnpbp::chain::Chain;
 
 use super::{fungible, stash, Error, Runtime};
 use crate::constants::*;
+use crate::util;
 
 #[derive(Clap, Clone, Debug, Display)]
 #[display(Debug)]
@@ -91,17 +93,19 @@ pub struct Config {
     pub network: Chain,
 }
 
-impl From<Opts> for Config {
-    fn from(opts: Opts) -> Self {
+impl TryFrom<Opts> for Config {
+    type Error = Error;
+
+    fn try_from(opts: Opts) -> Result<Self, Self::Error> {
         let mut me = Self {
             verbose: opts.verbose,
             network: opts.network,
             ..Config::default()
         };
         me.data_dir = me.parse_param(opts.data_dir);
-        me.fungible_endpoint = me.parse_param(opts.fungible_endpoint);
-        me.stash_endpoint = me.parse_param(opts.stash_endpoint);
-        me
+        me.fungible_endpoint = me.parse_endpoint(opts.fungible_endpoint)?;
+        me.stash_endpoint = me.parse_endpoint(opts.stash_endpoint)?;
+        Ok(me)
     }
 }
 
@@ -141,12 +145,52 @@ impl Config {
         T: FromStr,
         T::Err: Display,
     {
-        param
-            .replace("{network}", &self.network.to_string())
-            .replace("{data_dir}", self.data_dir.to_str().unwrap())
+        self.replace_placeholders(&param)
             .parse()
             .unwrap_or_else(|err| {
                 panic!("Error parsing parameter `{}`: {}", param, err)
             })
     }
+
+    /// Parses RPC endpoint parameter with [`parse_endpoint`], substituting
+    /// `{network}` and `{data_dir}` placeholders first
+    pub fn parse_endpoint(
+        &self,
+        param: String,
+    ) -> Result<ZmqSocketAddr, Error> {
+        parse_endpoint(&self.replace_placeholders(&param))
+    }
+
+    fn replace_placeholders(&self, param: &str) -> String {
+        param
+            .replace("{network}", &self.network.to_string())
+            .replace("{data_dir}", self.data_dir.to_str().unwrap())
+    }
+}
+
+/// Parses RPC endpoint with [`crate::util::parse_endpoint`]. Malformed
+/// endpoints are reported with [`Error::InvalidEndpoint`] before any socket
+/// is created.
+pub fn parse_endpoint(endpoint: &str) -> Result<ZmqSocketAddr, Error> {
+    util::parse_endpoint(endpoint)
+        .map_err(|err| Error::InvalidEndpoint(err.to_string()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_endpoint() {
+        assert_eq!(
+            parse_endpoint("tcp://127.0.0.1:61961").unwrap(),
+            ZmqSocketAddr::Tcp("127.0.0.1:61961".parse().unwrap())
+        );
+        match parse_endpoint("udp://127.0.0.1:61961") {
+            Err(Error::InvalidEndpoint(msg)) => {
+                assert!(msg.contains("udp://127.0.0.1:61961"))
+            }
+            other => panic!("udp endpoint must be invalid: {:?}", other),
+        }
+    }
 }
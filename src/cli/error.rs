@@ -12,6 +12,10 @@
 // If not, see <https://opensource.org/licenses/MIT>.
 
 use crate::error::ServiceErrorDomain;
+use crate::rpc::fungible::{
+    BurnError, InflateError, IssueError, TransferError,
+};
+use crate::rpc::reply::ChunkError;
 
 #[derive(Debug, Display, Error, From)]
 #[display(Debug)]
@@ -23,6 +27,16 @@ pub enum Error {
     #[from]
     ServiceError(ServiceErrorDomain),
 
+    #[from(std::io::Error)]
+    Io(amplify::IoError),
+
+    #[from]
+    #[from(zmq::Error)]
+    Transport(internet2::transport::Error),
+
+    #[from]
+    Presentation(internet2::presentation::Error),
+
     #[from]
     YamlError(serde_yaml::Error),
 
@@ -36,9 +50,26 @@ pub enum Error {
     #[from]
     StrictEncoding(strict_encoding::Error),
 
+    #[from]
+    Chunk(ChunkError),
+
     #[from]
     ConsensusEncoding(bitcoin::consensus::encode::Error),
 
+    #[from]
+    InvalidIssue(IssueError),
+
+    #[from]
+    InvalidInflation(InflateError),
+
+    #[from]
+    InvalidBurn(BurnError),
+
+    #[from]
+    Transfer(TransferError),
+
+    InvalidEndpoint(String),
+
     DataInconsistency,
 
     UnsupportedFunctionality,
@@ -23,13 +23,17 @@ use bp::seals::{OutpointHash, OutpointReveal};
 use commit_verify::CommitConceal;
 use microservices::FileFormat;
 use rgb::prelude::*;
+use rgb::validation;
 use rgb20::{Asset, SealCoins};
 use strict_encoding::strict_deserialize;
 
 use super::{Error, OutputFormat, Runtime};
-use crate::rpc::fungible::{AcceptReq, IssueReq, TransferReq};
+use crate::rpc::fungible::{
+    unique_inputs, AcceptReq, BurnReq, InflateReq, IssueReq, TransferReq,
+};
 use crate::rpc::{reply, Reply};
 use crate::util::file::ReadWrite;
+use crate::util::AssetExt;
 
 #[derive(Clap, Clone, Debug, Display)]
 #[display(Debug)]
@@ -60,6 +64,12 @@ pub enum Command {
     /// Creates a new asset
     Issue(IssueReq),
 
+    /// Issues more of an existing asset, spending its inflation right
+    Inflate(InflateCli),
+
+    /// Provably burns assets allocated to the given outpoints
+    Burn(BurnCli),
+
     /// Creates a blinded version of a given bitcoin transaction outpoint
     Blind {
         /// Original outpoint in `txid:vout` format
@@ -101,6 +111,68 @@ pub enum Command {
     },
 }
 
+#[derive(Clap, Clone, PartialEq, Debug, Display)]
+#[display(Debug)]
+pub struct InflateCli {
+    /// Outpoint controlling the inflation right
+    #[clap(short, long)]
+    pub inflation: OutPoint,
+
+    /// Allocations of the newly issued assets; MUST sum up to the issued
+    /// amount
+    #[clap(short, long, min_values = 1)]
+    pub allocate: Vec<SealCoins>,
+
+    /// Outpoint receiving the inflation right for the capacity left after
+    /// the issue. If not given, the right goes to the first output of the
+    /// transaction
+    #[clap(long)]
+    pub inflation_change: Option<OutPoint>,
+
+    /// Amount to issue, in atomic (non-float) units
+    pub amount: AtomicValue,
+
+    /// Which asset to inflate
+    pub asset: ContractId,
+
+    /// Read partially-signed transaction prototype
+    pub prototype: PathBuf,
+
+    /// File to save consignment to
+    pub consignment: PathBuf,
+
+    /// File to save disclosure to
+    pub disclosure: PathBuf,
+
+    /// File to save updated partially-signed bitcoin transaction to
+    pub transaction: PathBuf,
+}
+
+#[derive(Clap, Clone, PartialEq, Debug, Display)]
+#[display(Debug)]
+pub struct BurnCli {
+    /// Outpoints which assets are burned
+    #[clap(short = 'o', long = "outpoint", min_values = 1)]
+    pub outpoints: Vec<OutPoint>,
+
+    /// Outpoint receiving the burn right, which allows to prove subsequent
+    /// burns. If not given, no more burns will be possible
+    #[clap(long)]
+    pub proof_outpoint: Option<OutPoint>,
+
+    /// Which asset to burn
+    pub asset: ContractId,
+
+    /// Read partially-signed transaction prototype
+    pub prototype: PathBuf,
+
+    /// File to save consignment to
+    pub consignment: PathBuf,
+
+    /// File to save updated partially-signed bitcoin transaction to
+    pub transaction: PathBuf,
+}
+
 #[derive(Clap, Clone, PartialEq, Debug, Display)]
 #[display(Debug)]
 pub struct TransferCli {
@@ -119,6 +191,12 @@ pub struct TransferCli {
     /// Amount to pay, in atomic (non-float) units
     pub amount: AtomicValue,
 
+    /// Outpoint receiving the rest of the input assets as a change. If not
+    /// given, the change goes to the first of the additional allocations or
+    /// to the first output of the transaction
+    #[clap(long)]
+    pub change_outpoint: Option<OutPoint>,
+
     /// Which asset to use for the payment
     pub asset: ContractId,
 
@@ -138,6 +216,11 @@ pub struct TransferCli {
 
     /// File to save updated partially-signed bitcoin transaction to
     pub transaction: PathBuf,
+
+    /// Only report changes to the asset allocations which would be produced
+    /// by the transfer, without creating consignment and updating any state
+    #[clap(long)]
+    pub dry_run: bool,
 }
 
 impl Command {
@@ -160,6 +243,8 @@ impl Command {
                 Ok(())
             }
             Command::Issue(issue) => issue.exec(runtime),
+            Command::Inflate(inflate) => inflate.exec(runtime),
+            Command::Burn(burn) => burn.exec(runtime),
             Command::Transfer(transfer) => transfer.exec(runtime),
             Command::Validate { ref consignment } => {
                 self.exec_validate(runtime, consignment.clone())
@@ -204,6 +289,7 @@ impl Command {
                     .map(|a| {
                         map! {
                             "id" => a.id().to_string(),
+                            "fingerprint" => a.fingerprint(),
                             "ticker" => a.ticker().clone(),
                             "name" => a.name().clone()
                         }
@@ -252,8 +338,11 @@ impl Command {
             Reply::Failure(failure) => {
                 eprintln!("Server returned error: {}", failure);
             }
-            Reply::Success => {
-                eprintln!("Asset successfully imported");
+            Reply::Asset(asset) => {
+                eprintln!("Asset {} successfully imported", asset.id());
+            }
+            Reply::AlreadyKnown(asset) => {
+                eprintln!("Asset {} is already known", asset.id());
             }
             _ => {
                 eprintln!(
@@ -309,8 +398,8 @@ impl Command {
             Reply::Failure(failure) => {
                 eprintln!("Server returned error: {}", failure);
             }
-            Reply::ValidationStatus(status) => {
-                eprintln!("Asset transfer validation report:\n{:#?}", status);
+            Reply::ConsignmentReport(report) => {
+                eprintln!("Asset transfer validation report:\n{:#?}", report);
             }
             _ => {
                 eprintln!(
@@ -373,8 +462,15 @@ impl Command {
             Reply::Failure(failure) => {
                 eprintln!("Server returned error: {}", failure);
             }
-            Reply::Success => {
-                eprintln!("Asset transfer successfully accepted.");
+            Reply::ConsignmentReport(report) => {
+                if report.status.validity() == validation::Validity::Invalid {
+                    eprintln!(
+                        "Asset transfer is invalid and was not accepted."
+                    );
+                } else {
+                    eprintln!("Asset transfer successfully accepted.");
+                }
+                eprintln!("{:#?}", report);
             }
             _ => {
                 eprintln!(
@@ -468,9 +564,8 @@ impl IssueReq {
             }
         };
 
-        eprintln!(
-            "Asset successfully issued. Use this information for sharing:"
-        );
+        eprintln!("Asset successfully issued: {}", asset.summary());
+        eprintln!("Use this information for sharing:");
         #[cfg(feature = "serde")]
         eprintln!(
             "Asset information:\n {}\n",
@@ -483,55 +578,114 @@ impl IssueReq {
     }
 }
 
-impl TransferCli {
-    #[allow(unreachable_code)]
+impl InflateCli {
     pub fn exec(self, mut runtime: Runtime) -> Result<(), Error> {
-        info!("Transferring asset ...");
-        debug!("{}", self.clone());
+        info!("Inflating asset ...");
+        debug!("{}", self);
 
-        debug!(
-            "Reading partially-signed transaction from file {:?}",
-            self.prototype
-        );
-        let filepath = format!("{:?}", &self.prototype);
-        let file = fs::File::open(self.prototype)
-            .map_err(|_| Error::InputFileIoError(format!("{:?}", filepath)))?;
-        let mut psbt = PartiallySignedTransaction::consensus_decode(file)
-            .map_err(|err| {
-                Error::InputFileFormatError(
-                    format!("{:?}", filepath),
-                    format!("{}", err),
-                )
-            })?;
+        let psbt = read_witness(&self.prototype)?;
+        let api = InflateReq {
+            contract_id: self.asset,
+            witness: psbt,
+            inflation: self.inflation,
+            amount: self.amount,
+            allocation: self
+                .allocate
+                .into_iter()
+                .map(|seal_coins| {
+                    (seal_coins.seal_definition(), seal_coins.coins)
+                })
+                .collect(),
+            inflation_change: self
+                .inflation_change
+                .map(|outpoint| SealDefinition::TxOutpoint(outpoint.into())),
+        };
+
+        let reply = runtime.inflate(api)?;
+        info!("Reply: {}", reply);
+        match &*reply {
+            Reply::Failure(failure) => {
+                eprintln!("Inflation failed: {}", failure);
+            }
+            Reply::Transfer(transfer) => {
+                transfer.disclosure.write_file(&self.disclosure)?;
+                transfer.consignment.write_file(&self.consignment)?;
+
+                let out_file = fs::File::create(&self.transaction)
+                    .expect("can't create output transaction file");
+                transfer.witness.consensus_encode(out_file).map_err(|err| {
+                    bitcoin::consensus::encode::Error::Io(err)
+                })?;
 
-        for (index, output) in &mut psbt.outputs.iter_mut().enumerate() {
-            if let Some(key) = output.bip32_derivation.keys().next() {
-                let key = key.clone();
-                output.proprietary.insert(
-                    ProprietaryKey {
-                        prefix: b"RGB".to_vec(),
-                        subtype: PSBT_OUT_PUBKEY,
-                        key: vec![],
-                    },
-                    key.key.serialize().to_vec(),
+                eprintln!(
+                    "Inflation succeeded, consignments and disclosure are \
+                     written to {:?} and {:?}, partially signed witness \
+                     transaction to {:?}",
+                    self.consignment, self.disclosure, self.transaction
                 );
-                debug!("Output #{} commitment key will be {}", index, key);
-            } else {
-                warn!(
-                    "No public key information found for output #{}; \
-                    LNPBP1/2 commitment will be impossible.\
-                    In order to allow commitment pls add known keys derivation \
-                    information to PSBT output map",
-                    index
+            }
+            _ => (),
+        }
+
+        Ok(())
+    }
+}
+
+impl BurnCli {
+    pub fn exec(self, mut runtime: Runtime) -> Result<(), Error> {
+        info!("Burning asset ...");
+        debug!("{}", self);
+
+        let psbt = read_witness(&self.prototype)?;
+        let api = BurnReq {
+            contract_id: self.asset,
+            witness: psbt,
+            outpoints: self.outpoints.into_iter().collect(),
+            proof_seal: self
+                .proof_outpoint
+                .map(|outpoint| SealDefinition::TxOutpoint(outpoint.into())),
+        };
+
+        let reply = runtime.burn(api)?;
+        info!("Reply: {}", reply);
+        match &*reply {
+            Reply::Failure(failure) => {
+                eprintln!("Burn failed: {}", failure);
+            }
+            Reply::Transfer(transfer) => {
+                transfer.consignment.write_file(&self.consignment)?;
+
+                let out_file = fs::File::create(&self.transaction)
+                    .expect("can't create output transaction file");
+                transfer.witness.consensus_encode(out_file).map_err(|err| {
+                    bitcoin::consensus::encode::Error::Io(err)
+                })?;
+
+                eprintln!(
+                    "Burn succeeded, consignment is written to {:?}, \
+                     partially signed witness transaction to {:?}",
+                    self.consignment, self.transaction
                 );
             }
+            _ => (),
         }
-        trace!("{:?}", psbt);
+
+        Ok(())
+    }
+}
+
+impl TransferCli {
+    #[allow(unreachable_code)]
+    pub fn exec(self, mut runtime: Runtime) -> Result<(), Error> {
+        info!("Transferring asset ...");
+        debug!("{}", self);
+
+        let psbt = read_witness(&self.prototype)?;
 
         let api = TransferReq {
             witness: psbt,
             contract_id: self.asset,
-            inputs: self.inputs.into_iter().collect(),
+            inputs: unique_inputs(self.inputs)?,
             change: self
                 .allocate
                 .into_iter()
@@ -540,8 +694,35 @@ impl TransferCli {
                 })
                 .collect(),
             payment: bmap! { SealEndpoint::TxOutpoint(self.receiver) => self.amount },
+            change_outpoint: self.change_outpoint,
+            idempotency_key: None,
         };
 
+        if self.dry_run {
+            let reply = runtime.simulate_transfer(api)?;
+            info!("Reply: {}", reply);
+            match &*reply {
+                Reply::Failure(failure) => {
+                    eprintln!("Transfer dry run failed: {}", failure);
+                }
+                Reply::TransferPreview(preview) => {
+                    eprintln!(
+                        "Transfer dry run succeeded: {} paid, {} allocated as \
+                         change",
+                        preview.payment, preview.change
+                    );
+                    for allocation in &preview.spent {
+                        println!("- {}", allocation);
+                    }
+                    for allocation in &preview.allocations {
+                        println!("+ {}", allocation);
+                    }
+                }
+                _ => (),
+            }
+            return Ok(());
+        }
+
         let reply = runtime.transfer(api)?;
         info!("Reply: {}", reply);
         match &*reply {
@@ -558,6 +739,9 @@ impl TransferCli {
                     bitcoin::consensus::encode::Error::Io(err)
                 })?;
 
+                if let Some(outpoint) = transfer.change_outpoint {
+                    eprintln!("Change is allocated to {}", outpoint);
+                }
                 eprintln!(
                     "Transfer succeeded, consignments and disclosure are written \
                      to {:?} and {:?}, partially signed witness transaction to {:?}",
@@ -572,3 +756,50 @@ impl TransferCli {
         Ok(())
     }
 }
+
+/// Reads partially-signed witness transaction prototype from the file,
+/// adding to its outputs information on the keys used for LNPBP1/2
+/// commitments
+fn read_witness(
+    prototype: &PathBuf,
+) -> Result<PartiallySignedTransaction, Error> {
+    debug!(
+        "Reading partially-signed transaction from file {:?}",
+        prototype
+    );
+    let filepath = format!("{:?}", &prototype);
+    let file = fs::File::open(prototype)
+        .map_err(|_| Error::InputFileIoError(format!("{:?}", filepath)))?;
+    let mut psbt =
+        PartiallySignedTransaction::consensus_decode(file).map_err(|err| {
+            Error::InputFileFormatError(
+                format!("{:?}", filepath),
+                format!("{}", err),
+            )
+        })?;
+
+    for (index, output) in &mut psbt.outputs.iter_mut().enumerate() {
+        if let Some(key) = output.bip32_derivation.keys().next() {
+            let key = *key;
+            output.proprietary.insert(
+                ProprietaryKey {
+                    prefix: b"RGB".to_vec(),
+                    subtype: PSBT_OUT_PUBKEY,
+                    key: vec![],
+                },
+                key.key.serialize().to_vec(),
+            );
+            debug!("Output #{} commitment key will be {}", index, key);
+        } else {
+            warn!(
+                "No public key information found for output #{}; \
+                LNPBP1/2 commitment will be impossible.\
+                In order to allow commitment pls add known keys derivation \
+                information to PSBT output map",
+                index
+            );
+        }
+    }
+    trace!("{:?}", psbt);
+    Ok(psbt)
+}
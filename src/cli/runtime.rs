@@ -17,14 +17,18 @@ use bitcoin::OutPoint;
 use internet2::zmqsocket::ZmqType;
 use internet2::{
     session, transport, CreateUnmarshaller, PlainTranscoder, Session,
-    TypedEnum, Unmarshall, Unmarshaller,
+    TypedEnum, Unmarshall, Unmarshaller, ZmqSocketAddr,
 };
 use rgb::{Consignment, ContractId, Disclosure, Genesis, SchemaId};
 
 use super::{Config, Error};
 use crate::cli::OutputFormat;
-use crate::error::{BootstrapError, ServiceErrorDomain};
-use crate::rpc::fungible::{self, AcceptReq, IssueReq, TransferReq};
+use crate::error::BootstrapError;
+use crate::rpc::fungible::{
+    self, AcceptReq, BurnReq, ConsignmentChunkReq, InflateReq, IssueReq,
+    TransferReq,
+};
+use crate::rpc::reply::{ChunkedTransfer, ConsignmentChunk};
 use crate::rpc::stash;
 use crate::rpc::Reply;
 use microservices::FileFormat;
@@ -38,18 +42,8 @@ pub struct Runtime {
 
 impl Runtime {
     pub fn init(config: Config) -> Result<Self, BootstrapError> {
-        let fungible_rpc = session::Raw::with_zmq_unencrypted(
-            ZmqType::Req,
-            &config.fungible_endpoint,
-            None,
-            None,
-        )?;
-        let stash_rpc = session::Raw::with_zmq_unencrypted(
-            ZmqType::Req,
-            &config.stash_endpoint,
-            None,
-            None,
-        )?;
+        let fungible_rpc = connect(&config.fungible_endpoint)?;
+        let stash_rpc = connect(&config.stash_endpoint)?;
         Ok(Self {
             stash_rpc,
             fungible_rpc,
@@ -57,41 +51,85 @@ impl Runtime {
         })
     }
 
+    /// Closes RPC sessions to the daemons, discarding all unsent messages, so
+    /// the process may exit immediately even if a daemon does not respond.
+    /// The ZMQ context is shared by all sessions of the process and is not
+    /// terminated.
+    pub fn close(self) -> Result<(), Error> {
+        for session in vec![self.stash_rpc, self.fungible_rpc] {
+            session
+                .as_socket()
+                .set_linger(0)
+                .map_err(transport::Error::from)?;
+            // Dropping the session closes its socket
+            drop(session);
+        }
+        Ok(())
+    }
+
     fn stash_command(
         &mut self,
         command: stash::Request,
-    ) -> Result<Arc<Reply>, ServiceErrorDomain> {
-        let data = command.serialize();
-        self.stash_rpc.send_raw_message(&data)?;
-        let raw = self.stash_rpc.recv_raw_message()?;
-        let reply = self.unmarshaller.unmarshall(&raw)?;
-        Ok(reply)
+    ) -> Result<Arc<Reply>, Error> {
+        request(
+            &mut self.stash_rpc,
+            &self.unmarshaller,
+            &command.serialize(),
+        )
     }
 
     fn fungible_command(
         &mut self,
         command: fungible::Request,
-    ) -> Result<Arc<Reply>, ServiceErrorDomain> {
-        let data = command.serialize();
-        self.fungible_rpc.send_raw_message(&data)?;
-        let raw = self.fungible_rpc.recv_raw_message()?;
-        let reply = self.unmarshaller.unmarshall(&raw)?;
-        Ok(reply)
+    ) -> Result<Arc<Reply>, Error> {
+        let reply = request(
+            &mut self.fungible_rpc,
+            &self.unmarshaller,
+            &command.serialize(),
+        )?;
+        match &*reply {
+            Reply::ChunkedTransfer(transfer) => {
+                self.collect_chunks(transfer.clone())
+            }
+            _ => Ok(reply),
+        }
+    }
+
+    /// Requests all chunks of the chunked transfer consignment, completing
+    /// the transfer with the consignment reassembled from them. Replies other
+    /// than the chunks, like failures, are returned as they are.
+    fn collect_chunks(
+        &mut self,
+        transfer: ChunkedTransfer,
+    ) -> Result<Arc<Reply>, Error> {
+        let mut chunks = Vec::with_capacity(transfer.chunks as usize);
+        for seq in 0..transfer.chunks {
+            let reply =
+                self.fungible_command(fungible::Request::ConsignmentChunk(
+                    ConsignmentChunkReq { seq },
+                ))?;
+            match &*reply {
+                Reply::ConsignmentChunk(chunk) => chunks.push(chunk.clone()),
+                _ => return Ok(reply),
+            }
+        }
+        let consignment = ConsignmentChunk::reassemble(chunks)?;
+        Ok(Arc::new(Reply::Transfer(transfer.complete(consignment))))
     }
 
     #[inline]
     pub fn list_schemata(&mut self) -> Result<Arc<Reply>, Error> {
-        Ok(self.stash_command(stash::Request::ListSchemata())?)
+        self.stash_command(stash::Request::ListSchemata())
     }
 
     #[inline]
     pub fn list_geneses(&mut self) -> Result<Arc<Reply>, Error> {
-        Ok(self.stash_command(stash::Request::ListGeneses())?)
+        self.stash_command(stash::Request::ListGeneses())
     }
 
     #[inline]
     pub fn schema(&mut self, schema_id: SchemaId) -> Result<Arc<Reply>, Error> {
-        Ok(self.stash_command(stash::Request::ReadSchema(schema_id))?)
+        self.stash_command(stash::Request::ReadSchema(schema_id))
     }
 
     #[inline]
@@ -99,7 +137,7 @@ impl Runtime {
         &mut self,
         contract_id: ContractId,
     ) -> Result<Arc<Reply>, Error> {
-        Ok(self.stash_command(stash::Request::ReadGenesis(contract_id))?)
+        self.stash_command(stash::Request::ReadGenesis(contract_id))
     }
 
     #[inline]
@@ -114,12 +152,12 @@ impl Runtime {
             OutputFormat::StrictEncode => FileFormat::StrictEncode,
             _ => unimplemented!("The provided output format is not supported for this operation")
         };
-        Ok(self.fungible_command(fungible::Request::Sync(data_format))?)
+        self.fungible_command(fungible::Request::Sync(data_format))
     }
 
     #[inline]
     pub fn import(&mut self, genesis: Genesis) -> Result<Arc<Reply>, Error> {
-        Ok(self.fungible_command(fungible::Request::ImportAsset(genesis))?)
+        self.fungible_command(fungible::Request::ImportAsset(genesis))
     }
 
     #[inline]
@@ -127,20 +165,51 @@ impl Runtime {
         &mut self,
         asset_id: ContractId,
     ) -> Result<Arc<Reply>, Error> {
-        Ok(self.fungible_command(fungible::Request::ExportAsset(asset_id))?)
+        self.fungible_command(fungible::Request::ExportAsset(asset_id))
     }
 
-    #[inline]
     pub fn issue(&mut self, issue: IssueReq) -> Result<Arc<Reply>, Error> {
-        Ok(self.fungible_command(fungible::Request::Issue(issue))?)
+        issue.validate()?;
+        self.fungible_command(fungible::Request::Issue(issue))
+    }
+
+    pub fn inflate(
+        &mut self,
+        inflate: InflateReq,
+    ) -> Result<Arc<Reply>, Error> {
+        inflate.validate()?;
+        self.fungible_command(fungible::Request::Inflate(inflate))
+    }
+
+    pub fn burn(&mut self, burn: BurnReq) -> Result<Arc<Reply>, Error> {
+        burn.validate()?;
+        if let Reply::AssetAllocations(allocations) = &*self.fungible_command(
+            fungible::Request::Allocations(burn.contract_id),
+        )? {
+            burn.validate_allocations(allocations)?;
+        }
+        self.fungible_command(fungible::Request::Burn(burn))
     }
 
-    #[inline]
     pub fn transfer(
         &mut self,
         transfer: TransferReq,
     ) -> Result<Arc<Reply>, Error> {
-        Ok(self.fungible_command(fungible::Request::Transfer(transfer))?)
+        transfer.validate()?;
+        if let Reply::AssetAllocations(allocations) = &*self.fungible_command(
+            fungible::Request::Allocations(transfer.contract_id),
+        )? {
+            transfer.validate_inputs(allocations)?;
+        }
+        self.fungible_command(fungible::Request::Transfer(transfer))
+    }
+
+    pub fn simulate_transfer(
+        &mut self,
+        transfer: TransferReq,
+    ) -> Result<Arc<Reply>, Error> {
+        transfer.validate()?;
+        self.fungible_command(fungible::Request::TransferDryRun(transfer))
     }
 
     #[inline]
@@ -148,12 +217,12 @@ impl Runtime {
         &mut self,
         consignment: Consignment,
     ) -> Result<Arc<Reply>, Error> {
-        Ok(self.fungible_command(fungible::Request::Validate(consignment))?)
+        self.fungible_command(fungible::Request::Validate(consignment))
     }
 
     #[inline]
     pub fn accept(&mut self, accept: AcceptReq) -> Result<Arc<Reply>, Error> {
-        Ok(self.fungible_command(fungible::Request::Accept(accept))?)
+        self.fungible_command(fungible::Request::Accept(accept))
     }
 
     #[inline]
@@ -161,11 +230,99 @@ impl Runtime {
         &mut self,
         disclosure: Disclosure,
     ) -> Result<Arc<Reply>, Error> {
-        Ok(self.fungible_command(fungible::Request::Enclose(disclosure))?)
+        self.fungible_command(fungible::Request::Enclose(disclosure))
     }
 
     #[inline]
     pub fn forget(&mut self, outpoint: OutPoint) -> Result<Arc<Reply>, Error> {
-        Ok(self.fungible_command(fungible::Request::Forget(outpoint))?)
+        self.fungible_command(fungible::Request::Forget(outpoint))
+    }
+}
+
+/// Opens RPC session to the daemon endpoint. Unsent messages are discarded
+/// when the session is dropped, so a non-responding daemon can't block the
+/// process exit.
+fn connect(
+    endpoint: &ZmqSocketAddr,
+) -> Result<
+    session::Raw<PlainTranscoder, transport::zmqsocket::Connection>,
+    transport::Error,
+> {
+    let session =
+        session::Raw::with_zmq_unencrypted(ZmqType::Req, endpoint, None, None)?;
+    session.as_socket().set_linger(0)?;
+    Ok(session)
+}
+
+/// Sends raw request over the RPC session and decodes the reply. Transport
+/// and decoding failures are reported with the corresponding [`Error`]
+/// variants.
+fn request(
+    session: &mut session::Raw<
+        PlainTranscoder,
+        transport::zmqsocket::Connection,
+    >,
+    unmarshaller: &Unmarshaller<Reply>,
+    data: &[u8],
+) -> Result<Arc<Reply>, Error> {
+    session.send_raw_message(data)?;
+    let raw = session.recv_raw_message()?;
+    Ok(unmarshaller.unmarshall(&raw)?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_transport_errors() {
+        let endpoint = ZmqSocketAddr::Inproc(s!("rgb-cli-send-failure"));
+        let _server = session::Raw::with_zmq_unencrypted(
+            ZmqType::Rep,
+            &endpoint,
+            None,
+            None,
+        )
+        .unwrap();
+        let mut client = session::Raw::with_zmq_unencrypted(
+            ZmqType::Req,
+            &endpoint,
+            None,
+            None,
+        )
+        .unwrap();
+        let data = fungible::Request::Ping.serialize();
+        client.send_raw_message(&data).unwrap();
+
+        // REQ socket can't send the next request before receiving the reply
+        let err = request(&mut client, &Reply::create_unmarshaller(), &data)
+            .unwrap_err();
+        assert!(matches!(err, Error::Transport(transport::Error::Zmq(_))));
+
+        assert!(matches!(
+            Error::from(zmq::Error::EHOSTUNREACH),
+            Error::Transport(transport::Error::ServiceOffline)
+        ));
+    }
+
+    #[test]
+    fn test_close_unresponsive() {
+        // Nobody serves the endpoints, so the requests stay unsent
+        let mut runtime = Runtime::init(Config {
+            fungible_endpoint: ZmqSocketAddr::Inproc(s!(
+                "rgb-cli-no-fungibled"
+            )),
+            stash_endpoint: ZmqSocketAddr::Inproc(s!("rgb-cli-no-stashd")),
+            ..Config::default()
+        })
+        .unwrap();
+        let data = fungible::Request::Ping.serialize();
+        runtime.fungible_rpc.send_raw_message(&data).unwrap();
+        runtime.stash_rpc.send_raw_message(&data).unwrap();
+
+        let start = Instant::now();
+        runtime.close().unwrap();
+        assert!(start.elapsed() < Duration::from_secs(1));
     }
 }
@@ -28,5 +28,9 @@ pub const STASHD_RPC_ENDPOINT: &'static str =
 pub const FUNGIBLED_CACHE: &'static str = "{data_dir}/{network}/cache/fungible";
 pub const FUNGIBLED_RPC_ENDPOINT: &'static str =
     "lnpz:{data_dir}/{network}/fungibled.rpc";
+pub const FUNGIBLED_IDEMPOTENT_REPLIES: usize = 1000;
+pub const FUNGIBLED_IDEMPOTENT_REPLY_TTL_SECS: i64 = 7 * 24 * 60 * 60;
+
+pub const RGB_REPLY_TIMEOUT_SECS: u64 = 60;
 
 pub const DEFAULT_ELECTRUM_ENDPOINT: &'static str = "pandora.network:60601";
@@ -38,6 +38,9 @@ pub enum BootstrapError {
 
     StorageError,
 
+    /// None of the default locations contains configuration file
+    NoConfigFound,
+
     #[cfg(feature = "fungibles")]
     #[from(crate::fungibled::FileCacheError)]
     #[cfg_attr(feature = "sql", from(crate::fungibled::SqlCacheError))]
@@ -76,7 +79,7 @@ pub enum RoutedError {
     RequestSpecific(ServiceError),
 }
 
-#[derive(Clone, PartialEq, Eq, Debug, Display, Error, From)]
+#[derive(Clone, PartialEq, Eq, Debug, Display, From)]
 #[display(Debug)]
 #[non_exhaustive]
 pub enum ServiceErrorDomain {
@@ -122,17 +125,127 @@ pub enum ServiceErrorDomain {
 
     Schema(String),
 
+    /// Asset data do not match RGB20 schema; unlike [`Self::Schema`] keeps
+    /// the structured schema error as the error source
+    #[cfg(feature = "fungibles")]
+    #[from]
+    SchemaViolation(rgb20::schema::Error),
+
     Anchor(String),
 
+    UnknownAsset(String),
+
+    InsufficientFunds(String),
+
+    ChainMismatch(String),
+
+    /// Asset can't be forgotten since it has known non-zero balance
+    AssetHasBalance(String),
+
+    /// Asset is imported for monitoring only, so the node has no allocations
+    /// of it to spend
+    WatchOnly(String),
+
     #[from]
-    #[cfg_attr(
-        feature = "fungibles",
-        from(rgb20::Error),
-        from(rgb20::TransferError)
-    )]
     Internal(String),
 }
 
+#[cfg(feature = "fungibles")]
+impl From<rgb20::Error> for ServiceErrorDomain {
+    fn from(err: rgb20::Error) -> Self {
+        match err {
+            rgb20::Error::Schema(err) => {
+                ServiceErrorDomain::SchemaViolation(err)
+            }
+            err => ServiceErrorDomain::Schema(err.to_string()),
+        }
+    }
+}
+
+impl std::error::Error for ServiceErrorDomain {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ServiceErrorDomain::Io(err) => Some(err),
+            ServiceErrorDomain::LnpRpc(err) => Some(err),
+            ServiceErrorDomain::LnpTransport(err) => Some(err),
+            #[cfg(feature = "fungibles")]
+            ServiceErrorDomain::SchemaViolation(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "fungibles")]
+impl From<crate::util::GenesisError> for ServiceErrorDomain {
+    fn from(err: crate::util::GenesisError) -> Self {
+        match err {
+            crate::util::GenesisError::Asset(err) => err.into(),
+            err => ServiceErrorDomain::Schema(err.to_string()),
+        }
+    }
+}
+
+#[cfg(feature = "fungibles")]
+impl From<crate::util::SupplyError> for ServiceErrorDomain {
+    fn from(err: crate::util::SupplyError) -> Self {
+        ServiceErrorDomain::Schema(err.to_string())
+    }
+}
+
+#[cfg(feature = "fungibles")]
+impl From<rgb20::TransferError> for ServiceErrorDomain {
+    fn from(err: rgb20::TransferError) -> Self {
+        ServiceErrorDomain::InsufficientFunds(err.to_string())
+    }
+}
+
+#[cfg(all(feature = "_rpc", feature = "fungibles"))]
+impl From<crate::rpc::fungible::InflateError> for ServiceErrorDomain {
+    fn from(err: crate::rpc::fungible::InflateError) -> Self {
+        use crate::rpc::fungible::InflateError;
+        match err {
+            InflateError::UnknownInflation(_)
+            | InflateError::ExceedsCapacity { .. } => {
+                ServiceErrorDomain::InsufficientFunds(err.to_string())
+            }
+            _ => ServiceErrorDomain::Schema(err.to_string()),
+        }
+    }
+}
+
+#[cfg(all(feature = "_rpc", feature = "fungibles"))]
+impl From<crate::rpc::fungible::BurnError> for ServiceErrorDomain {
+    fn from(err: crate::rpc::fungible::BurnError) -> Self {
+        use crate::rpc::fungible::BurnError;
+        match err {
+            BurnError::UnknownAllocation(_) => {
+                ServiceErrorDomain::InsufficientFunds(err.to_string())
+            }
+            _ => ServiceErrorDomain::Schema(err.to_string()),
+        }
+    }
+}
+
+#[cfg(all(feature = "_rpc", feature = "fungibles"))]
+impl From<crate::rpc::fungible::TransferError> for ServiceErrorDomain {
+    fn from(err: crate::rpc::fungible::TransferError) -> Self {
+        use crate::rpc::fungible::TransferError;
+        match err {
+            TransferError::InsufficientInputs { .. } => {
+                ServiceErrorDomain::InsufficientFunds(err.to_string())
+            }
+            _ => ServiceErrorDomain::Schema(err.to_string()),
+        }
+    }
+}
+
+#[cfg(all(feature = "_rpc", feature = "fungibles"))]
+impl From<crate::rpc::fungible::RenominateError> for ServiceErrorDomain {
+    fn from(err: crate::rpc::fungible::RenominateError) -> Self {
+        ServiceErrorDomain::Schema(err.to_string())
+    }
+}
+
 #[derive(Clone, PartialEq, Eq, Debug, Display)]
 #[display(Debug)]
 #[non_exhaustive]
@@ -202,3 +315,31 @@ pub struct ServiceErrorRepresentation {
     pub description: String,
     pub info: HashMap<String, String>,
 }
+
+#[cfg(all(test, feature = "fungibles"))]
+mod test {
+    use super::*;
+    use std::error::Error;
+
+    #[test]
+    fn test_schema_error_source() {
+        let err = ServiceErrorDomain::from(rgb20::Error::Schema(
+            rgb20::schema::Error::NotAllFieldsPresent,
+        ));
+        assert_eq!(
+            err,
+            ServiceErrorDomain::SchemaViolation(
+                rgb20::schema::Error::NotAllFieldsPresent
+            )
+        );
+        assert_eq!(
+            err.source().and_then(
+                |source| source.downcast_ref::<rgb20::schema::Error>()
+            ),
+            Some(&rgb20::schema::Error::NotAllFieldsPresent)
+        );
+
+        let err = ServiceErrorDomain::Schema(s!("schema"));
+        assert!(err.source().is_none());
+    }
+}
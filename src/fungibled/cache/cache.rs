@@ -28,6 +28,30 @@ pub trait Cache {
     fn add_asset(&mut self, asset: Asset) -> Result<bool, Self::Error>;
     fn remove_asset(&mut self, id: ContractId) -> Result<bool, Self::Error>;
 
+    /// Adds allocation to the known asset with
+    /// [`crate::util::AssetExt::insert_allocation`], keeping the outpoint
+    /// index up to date. Returns whether the allocation was added, i.e. it
+    /// was neither known nor conflicting with a known allocation.
+    fn add_allocation(
+        &mut self,
+        id: ContractId,
+        outpoint: bitcoin::OutPoint,
+        node_id: NodeId,
+        index: u16,
+        value: value::Revealed,
+    ) -> Result<bool, Self::Error>;
+
+    /// Removes allocation from the known asset, keeping the outpoint index up
+    /// to date. Returns whether the allocation was known.
+    fn remove_allocation(
+        &mut self,
+        id: ContractId,
+        outpoint: bitcoin::OutPoint,
+        node_id: NodeId,
+        index: u16,
+        value: value::Revealed,
+    ) -> Result<bool, Self::Error>;
+
     /// Returns the map of Utxo-Allocation_amount for a given asset
     fn asset_allocations(
         &self,
@@ -39,6 +63,14 @@ pub trait Cache {
         &self,
         outpoint: bitcoin::OutPoint,
     ) -> Result<BTreeMap<ContractId, Vec<AtomicValue>>, CacheError>;
+
+    /// Returns ids of the assets having allocations or inflation rights
+    /// assigned to the given outpoint, i.e. the assets affected by the
+    /// spending of the outpoint
+    fn assets_affected_by_spend(
+        &self,
+        outpoint: bitcoin::OutPoint,
+    ) -> Result<Vec<ContractId>, Self::Error>;
 }
 
 #[derive(Clone, PartialEq, Eq, Debug, Display, Error)]
@@ -95,6 +127,16 @@ impl From<FileCacheError> for CacheError {
             FileCacheError::SerdeToml => Self::DataIntegrityError(format!(
                 "TOML serialization/deserialization error"
             )),
+            #[cfg(feature = "serde")]
+            FileCacheError::AssetJson(e) => {
+                Self::DataIntegrityError(format!("{:?}", e))
+            }
+            FileCacheError::UnsupportedVersion(version) => {
+                Self::DataIntegrityError(format!(
+                    "Unsupported version {} of the cache data",
+                    version
+                ))
+            }
             FileCacheError::NotFound => {
                 Self::DataIntegrityError("Data file is not found".to_string())
             }
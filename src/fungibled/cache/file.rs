@@ -11,26 +11,40 @@
 // along with this software.
 // If not, see <https://opensource.org/licenses/MIT>.
 
+#[cfg(feature = "serde")]
+use serde::Serialize;
 #[cfg(feature = "serde")]
 use serde_json;
 use std::collections::BTreeMap;
-#[cfg(any(
-    feature = "serde_yaml",
-    feature = "serde_json",
-    feature = "toml"
-))]
-use std::io::{Read, Write};
+#[cfg(feature = "serde")]
+use std::io::Read;
+use std::io::Write;
 use std::path::PathBuf;
 use std::{fs, io};
 
+use chrono::NaiveDateTime;
 use microservices::FileFormat;
 use rgb::prelude::*;
+use rgb::SealEndpoint;
 use rgb20::Asset;
-use strict_encoding::{strict_serialize, StrictDecode, StrictEncode};
+use strict_encoding::{
+    strict_deserialize, strict_serialize, StrictDecode, StrictEncode,
+};
 
-use super::Cache;
+use super::{
+    Cache, IdempotentReply, OutpointIndex, OwnedRight, PendingSpending,
+};
+use crate::constants::{
+    FUNGIBLED_IDEMPOTENT_REPLIES, FUNGIBLED_IDEMPOTENT_REPLY_TTL_SECS,
+};
 use crate::fungibled::cache::CacheError;
+use crate::rpc::reply::PendingTransfer;
 use crate::util::file::*;
+#[cfg(feature = "serde")]
+use crate::util::AssetJsonError;
+use crate::util::{
+    AllocationInsertion, AssetExt, MagicNumber, ASSET_JSON_VERSION,
+};
 
 #[derive(Debug, Display, Error, From)]
 #[display(Debug)]
@@ -61,6 +75,13 @@ pub enum FileCacheError {
     #[from(toml::ser::Error)]
     SerdeToml,
 
+    #[cfg(feature = "serde")]
+    #[from]
+    AssetJson(AssetJsonError),
+
+    /// Cache file has unsupported version of the data format
+    UnsupportedVersion(u16),
+
     NotFound,
 }
 
@@ -83,13 +104,87 @@ impl FileCacheConfig {
             .join("assets")
             .with_extension(self.data_format.extension())
     }
+
+    #[inline]
+    pub fn labels_filename(&self) -> PathBuf {
+        self.assets_dir()
+            .join("labels")
+            .with_extension(self.data_format.extension())
+    }
+
+    #[inline]
+    pub fn watched_filename(&self) -> PathBuf {
+        self.assets_dir()
+            .join("watched")
+            .with_extension(self.data_format.extension())
+    }
+
+    #[inline]
+    pub fn rights_filename(&self) -> PathBuf {
+        self.assets_dir()
+            .join("rights")
+            .with_extension(self.data_format.extension())
+    }
+
+    #[inline]
+    pub fn spendings_filename(&self) -> PathBuf {
+        self.assets_dir()
+            .join("spendings")
+            .with_extension(self.data_format.extension())
+    }
+
+    #[inline]
+    pub fn replies_filename(&self) -> PathBuf {
+        self.assets_dir()
+            .join("replies")
+            .with_extension(self.data_format.extension())
+    }
+
+    #[inline]
+    pub fn transfers_filename(&self) -> PathBuf {
+        self.assets_dir()
+            .join("transfers")
+            .with_extension(self.data_format.extension())
+    }
 }
 
 /// Keeps all source/binary RGB contract data, stash etc
 #[derive(Debug)]
 pub struct FileCache {
     config: FileCacheConfig,
+
+    /// Assets indexed by their contract id, which is the unique primary key
+    /// of the asset data. The map serves as the index itself, so asset
+    /// lookups do not require scanning all known assets.
     assets: BTreeMap<ContractId, Asset>,
+
+    /// Reverse index of the asset outpoints
+    outpoint_index: OutpointIndex,
+
+    /// Private user labels of the assets. Labels are not a part of the asset
+    /// data, so they are kept in a separate file.
+    labels: BTreeMap<ContractId, String>,
+
+    /// Watch-only assets, imported for monitoring only, with the time of the
+    /// import. Like labels, they are kept in a separate file.
+    watched: BTreeMap<ContractId, NaiveDateTime>,
+
+    /// Rights of the assets which were changed by the confirmed state
+    /// transitions composed by the node. Assets missing here have the rights
+    /// assigned by their genesis.
+    rights: BTreeMap<ContractId, Vec<OwnedRight>>,
+
+    /// State transitions composed by the node which witness transactions are
+    /// not confirmed yet
+    spendings: Vec<PendingSpending>,
+
+    /// Replies to the requests which had idempotency keys, ordered by the
+    /// time the requests were performed
+    replies: Vec<IdempotentReply>,
+
+    /// Payments of the transfers created by the node which are not known to
+    /// be accepted by the receivers yet
+    pending_transfers: Vec<PendingTransfer>,
 }
 
 impl FileCache {
@@ -116,6 +211,13 @@ impl FileCache {
         let mut me = Self {
             config,
             assets: bmap![],
+            outpoint_index: default!(),
+            labels: bmap![],
+            watched: bmap![],
+            rights: bmap![],
+            spendings: vec![],
+            replies: vec![],
+            pending_transfers: vec![],
         };
         let filename = me.config.assets_filename();
         if filename.exists() {
@@ -130,43 +232,255 @@ impl FileCache {
 
     fn load(&mut self) -> Result<(), FileCacheError> {
         debug!("Reading assets information ...");
-        let filename = self.config.assets_filename();
-        let mut f = file(filename, FileMode::Read)?;
-        self.assets = match self.config.data_format {
-            #[cfg(feature = "serde_yaml")]
-            FileFormat::Yaml => serde_yaml::from_reader(&f)?,
-            #[cfg(feature = "serde_json")]
-            FileFormat::Json => serde_json::from_reader(&f)?,
-            #[cfg(feature = "toml")]
-            FileFormat::Toml => {
-                let mut data = String::new();
-                f.read_to_string(&mut data)?;
-                toml::from_str(&data)?
-            }
-            FileFormat::StrictEncode => StrictDecode::strict_decode(&mut f)?,
-            _ => unimplemented!(),
-        };
+        let data_format = self.config.data_format;
+        self.assets = load_assets(self.config.assets_filename(), data_format)?;
+        self.outpoint_index = OutpointIndex::with(self.assets.values());
+
+        let filename = self.config.labels_filename();
+        if filename.exists() {
+            self.labels = load_data(filename, data_format)?;
+        }
+        let filename = self.config.watched_filename();
+        if filename.exists() {
+            self.watched = load_data(filename, data_format)?;
+        }
+        let filename = self.config.rights_filename();
+        if filename.exists() {
+            self.rights = load_data(filename, data_format)?;
+        }
+        let filename = self.config.spendings_filename();
+        if filename.exists() {
+            self.spendings = load_data(filename, data_format)?;
+        }
+        let filename = self.config.replies_filename();
+        if filename.exists() {
+            self.replies = load_data(filename, data_format)?;
+        }
+        let filename = self.config.transfers_filename();
+        if filename.exists() {
+            self.pending_transfers = load_data(filename, data_format)?;
+        }
         Ok(())
     }
 
+    fn save_labels(&self) -> Result<(), FileCacheError> {
+        trace!("Saving asset labels ...");
+        save_data(
+            &self.labels,
+            self.config.labels_filename(),
+            self.config.data_format,
+        )
+    }
+
+    /// Returns private user label of the asset, if any
+    #[inline]
+    pub fn label(&self, id: ContractId) -> Option<&String> {
+        self.labels.get(&id)
+    }
+
+    /// Sets private user label of the asset, or removes it if `None` is
+    /// given. Returns whether the asset had a label before.
+    pub fn set_label(
+        &mut self,
+        id: ContractId,
+        label: Option<String>,
+    ) -> Result<bool, FileCacheError> {
+        let existed = match label {
+            Some(label) => self.labels.insert(id, label),
+            None => self.labels.remove(&id),
+        }
+        .is_some();
+        self.save_labels()?;
+        Ok(existed)
+    }
+
+    fn save_watched(&self) -> Result<(), FileCacheError> {
+        trace!("Saving watch-only assets ...");
+        save_data(
+            &self.watched,
+            self.config.watched_filename(),
+            self.config.data_format,
+        )
+    }
+
+    /// Returns time when the watch-only asset was imported, or `None` if the
+    /// asset is not watch-only
+    #[inline]
+    pub fn watched_since(&self, id: ContractId) -> Option<NaiveDateTime> {
+        self.watched.get(&id).copied()
+    }
+
+    /// Marks the asset as watch-only since the given time, or unmarks it if
+    /// `None` is given. Returns whether the asset was watch-only before.
+    pub fn set_watched(
+        &mut self,
+        id: ContractId,
+        since: Option<NaiveDateTime>,
+    ) -> Result<bool, FileCacheError> {
+        let existed = match since {
+            Some(since) => self.watched.insert(id, since),
+            None => self.watched.remove(&id),
+        }
+        .is_some();
+        self.save_watched()?;
+        Ok(existed)
+    }
+
+    fn save_rights(&self) -> Result<(), FileCacheError> {
+        trace!("Saving asset rights ...");
+        save_data(
+            &self.rights,
+            self.config.rights_filename(),
+            self.config.data_format,
+        )
+    }
+
+    /// Returns asset rights changed by the confirmed state transitions, or
+    /// `None` if the asset has only the rights assigned by its genesis
+    #[inline]
+    pub fn rights(&self, id: ContractId) -> Option<&Vec<OwnedRight>> {
+        self.rights.get(&id)
+    }
+
+    /// Replaces known asset rights
+    pub fn set_rights(
+        &mut self,
+        id: ContractId,
+        rights: Vec<OwnedRight>,
+    ) -> Result<(), FileCacheError> {
+        self.rights.insert(id, rights);
+        self.save_rights()
+    }
+
+    fn save_spendings(&self) -> Result<(), FileCacheError> {
+        trace!("Saving pending spendings ...");
+        save_data(
+            &self.spendings,
+            self.config.spendings_filename(),
+            self.config.data_format,
+        )
+    }
+
+    /// Adds state transition composed by the node to the pending ones. Since
+    /// a right can be spent only once, a pending transition spending the same
+    /// right is replaced.
+    pub fn add_spending(
+        &mut self,
+        spending: PendingSpending,
+    ) -> Result<(), FileCacheError> {
+        self.spendings.retain(|other| other.spent != spending.spent);
+        self.spendings.push(spending);
+        self.save_spendings()
+    }
+
+    /// Removes and returns pending state transitions which witness
+    /// transactions spend the outpoint
+    pub fn take_spendings(
+        &mut self,
+        outpoint: bitcoin::OutPoint,
+    ) -> Result<Vec<PendingSpending>, FileCacheError> {
+        let (confirmed, pending) = self
+            .spendings
+            .drain(..)
+            .partition(|spending| spending.inputs.contains(&outpoint));
+        self.spendings = pending;
+        if !confirmed.is_empty() {
+            self.save_spendings()?;
+        }
+        Ok(confirmed)
+    }
+
+    fn save_replies(&self) -> Result<(), FileCacheError> {
+        trace!("Saving idempotent replies ...");
+        save_data(
+            &self.replies,
+            self.config.replies_filename(),
+            self.config.data_format,
+        )
+    }
+
+    /// Returns stored reply to the request of the given type with the given
+    /// idempotency key, if any
+    pub fn idempotent_reply(
+        &self,
+        request_type: u16,
+        key: [u8; 32],
+    ) -> Option<&IdempotentReply> {
+        self.replies.iter().find(|reply| {
+            reply.request_type == request_type && reply.key == key
+        })
+    }
+
+    /// Stores reply to the request with an idempotency key. Replies older
+    /// than [`FUNGIBLED_IDEMPOTENT_REPLY_TTL_SECS`] are dropped, and only the
+    /// latest [`FUNGIBLED_IDEMPOTENT_REPLIES`] replies are kept.
+    pub fn add_idempotent_reply(
+        &mut self,
+        reply: IdempotentReply,
+    ) -> Result<(), FileCacheError> {
+        let expiry = reply.created_at
+            - chrono::Duration::seconds(FUNGIBLED_IDEMPOTENT_REPLY_TTL_SECS);
+        self.replies.retain(|other| {
+            other.created_at > expiry
+                && (other.request_type, other.key)
+                    != (reply.request_type, reply.key)
+        });
+        self.replies.push(reply);
+        if self.replies.len() > FUNGIBLED_IDEMPOTENT_REPLIES {
+            let excess = self.replies.len() - FUNGIBLED_IDEMPOTENT_REPLIES;
+            self.replies.drain(..excess);
+        }
+        self.save_replies()
+    }
+
+    fn save_pending_transfers(&self) -> Result<(), FileCacheError> {
+        trace!("Saving pending transfers ...");
+        save_data(
+            &self.pending_transfers,
+            self.config.transfers_filename(),
+            self.config.data_format,
+        )
+    }
+
+    /// Returns payments of the transfers created by the node which are not
+    /// known to be accepted by the receivers yet
+    #[inline]
+    pub fn pending_transfers(&self) -> &Vec<PendingTransfer> {
+        &self.pending_transfers
+    }
+
+    /// Adds payments of the transfer created by the node to the pending ones
+    pub fn add_pending_transfers(
+        &mut self,
+        transfers: impl IntoIterator<Item = PendingTransfer>,
+    ) -> Result<(), FileCacheError> {
+        self.pending_transfers.extend(transfers);
+        self.save_pending_transfers()
+    }
+
+    /// Removes pending transfer paying to the seal, returning whether there
+    /// was such transfer
+    pub fn accept_pending_transfer(
+        &mut self,
+        seal: &SealEndpoint,
+    ) -> Result<bool, FileCacheError> {
+        let count = self.pending_transfers.len();
+        self.pending_transfers
+            .retain(|transfer| &transfer.seal != seal);
+        if self.pending_transfers.len() == count {
+            return Ok(false);
+        }
+        self.save_pending_transfers()?;
+        Ok(true)
+    }
+
     pub fn save(&self) -> Result<(), FileCacheError> {
         trace!("Saving assets information ...");
-        let filename = self.config.assets_filename();
-        let _ = fs::remove_file(&filename);
-        let mut f = file(filename, FileMode::Create)?;
-        match self.config.data_format {
-            #[cfg(feature = "serde_yaml")]
-            FileFormat::Yaml => serde_yaml::to_writer(&f, &self.assets)?,
-            #[cfg(feature = "serde_json")]
-            FileFormat::Json => serde_json::to_writer(&f, &self.assets)?,
-            #[cfg(feature = "toml")]
-            FileFormat::Toml => f.write_all(&toml::to_vec(&self.assets)?)?,
-            FileFormat::StrictEncode => {
-                self.assets.strict_encode(&mut f)?;
-            }
-            _ => unimplemented!(),
-        }
-        Ok(())
+        save_data(
+            &self.assets,
+            self.config.assets_filename(),
+            self.config.data_format,
+        )
     }
 
     pub fn export(
@@ -188,6 +502,171 @@ impl FileCache {
     }
 }
 
+/// Data which can be kept by [`FileCache`] in a file of any of the supported
+/// data formats
+#[cfg(feature = "serde")]
+trait FileData:
+    StrictEncode + StrictDecode + Serialize + serde::de::DeserializeOwned
+{
+}
+
+#[cfg(feature = "serde")]
+impl<T> FileData for T where
+    T: StrictEncode + StrictDecode + Serialize + serde::de::DeserializeOwned
+{
+}
+
+/// Data which can be kept by [`FileCache`] in a file of any of the supported
+/// data formats
+#[cfg(not(feature = "serde"))]
+trait FileData: StrictEncode + StrictDecode {}
+
+#[cfg(not(feature = "serde"))]
+impl<T> FileData for T where T: StrictEncode + StrictDecode {}
+
+/// Top-level object of the cache files in the text data formats, allowing
+/// migrations of the cached data. Data in binary (strict encoded) cache files
+/// are prefixed with [`MagicNumber::Cache`] and the version instead.
+#[cfg(feature = "serde")]
+#[derive(Serialize)]
+#[serde(crate = "serde_crate")]
+struct CacheEnvelope<'data, T> {
+    version: u16,
+    data: &'data T,
+}
+
+/// Reads cache file data in the text data format as a JSON value, together
+/// with the version of the data format. Files written before the cache files
+/// were versioned have no envelope and are reported as version 1.
+#[cfg(feature = "serde")]
+fn load_value(
+    filename: PathBuf,
+    data_format: FileFormat,
+) -> Result<(u16, serde_json::Value), FileCacheError> {
+    let mut f = file(filename, FileMode::Read)?;
+    let value: serde_json::Value = match data_format {
+        #[cfg(feature = "serde_yaml")]
+        FileFormat::Yaml => serde_yaml::from_reader(&f)?,
+        #[cfg(feature = "serde_json")]
+        FileFormat::Json => serde_json::from_reader(&f)?,
+        #[cfg(feature = "toml")]
+        FileFormat::Toml => {
+            let mut data = String::new();
+            f.read_to_string(&mut data)?;
+            toml::from_str(&data)?
+        }
+        _ => unimplemented!(),
+    };
+    let version = match value.get("version") {
+        Some(version) if value.get("data").is_some() => {
+            serde_json::from_value(version.clone())?
+        }
+        _ => return Ok((1, value)),
+    };
+    if version > ASSET_JSON_VERSION {
+        return Err(FileCacheError::UnsupportedVersion(version));
+    }
+    let data = value.get("data").cloned().unwrap_or_default();
+    Ok((version, data))
+}
+
+/// Reads strict encoded cache file data. Files written before the cache files
+/// were versioned have no magic number and version prefix.
+fn load_strict<T>(filename: PathBuf) -> Result<T, FileCacheError>
+where
+    T: StrictDecode,
+{
+    let data = fs::read(filename)?;
+    if !data.starts_with(&MagicNumber::Cache.to_u32().to_be_bytes()) {
+        return Ok(strict_deserialize(&data)?);
+    }
+    let version = u16::strict_decode(&data[4..])?;
+    if version > ASSET_JSON_VERSION {
+        return Err(FileCacheError::UnsupportedVersion(version));
+    }
+    Ok(strict_deserialize(&data[6..])?)
+}
+
+fn load_data<T>(
+    filename: PathBuf,
+    data_format: FileFormat,
+) -> Result<T, FileCacheError>
+where
+    T: FileData,
+{
+    match data_format {
+        FileFormat::StrictEncode => load_strict(filename),
+        #[cfg(feature = "serde")]
+        _ => {
+            let (_, data) = load_value(filename, data_format)?;
+            Ok(serde_json::from_value(data)?)
+        }
+        #[cfg(not(feature = "serde"))]
+        _ => unimplemented!(),
+    }
+}
+
+/// Reads cached assets, upgrading asset data of the earlier versions with
+/// [`AssetExt::migrate`]
+fn load_assets(
+    filename: PathBuf,
+    data_format: FileFormat,
+) -> Result<BTreeMap<ContractId, Asset>, FileCacheError> {
+    match data_format {
+        FileFormat::StrictEncode => load_strict(filename),
+        #[cfg(feature = "serde")]
+        _ => {
+            let (version, data) = load_value(filename, data_format)?;
+            let assets: BTreeMap<ContractId, serde_json::Value> =
+                serde_json::from_value(data)?;
+            assets
+                .into_iter()
+                .map(|(id, asset)| {
+                    let asset = Asset::migrate(serde_json::json!({
+                        "version": version,
+                        "asset": asset
+                    }))?;
+                    Ok((id, asset))
+                })
+                .collect()
+        }
+        #[cfg(not(feature = "serde"))]
+        _ => unimplemented!(),
+    }
+}
+
+fn save_data<T>(
+    data: &T,
+    filename: PathBuf,
+    data_format: FileFormat,
+) -> Result<(), FileCacheError>
+where
+    T: FileData,
+{
+    let _ = fs::remove_file(&filename);
+    let mut f = file(filename, FileMode::Create)?;
+    #[cfg(feature = "serde")]
+    let envelope = CacheEnvelope {
+        version: ASSET_JSON_VERSION,
+        data,
+    };
+    match data_format {
+        #[cfg(feature = "serde_yaml")]
+        FileFormat::Yaml => serde_yaml::to_writer(&f, &envelope)?,
+        #[cfg(feature = "serde_json")]
+        FileFormat::Json => serde_json::to_writer(&f, &envelope)?,
+        #[cfg(feature = "toml")]
+        FileFormat::Toml => f.write_all(&toml::to_vec(&envelope)?)?,
+        FileFormat::StrictEncode => {
+            f.write_all(&MagicNumber::Cache.to_u32().to_be_bytes())?;
+            ASSET_JSON_VERSION.strict_encode(&mut f)?;
+            data.strict_encode(&mut f)?;
+        }
+        _ => unimplemented!(),
+    }
+    Ok(())
+}
+
 impl Cache for FileCache {
     type Error = CacheError;
 
@@ -208,6 +687,7 @@ impl Cache for FileCache {
     }
 
     fn add_asset(&mut self, asset: Asset) -> Result<bool, CacheError> {
+        self.outpoint_index.insert(&asset);
         let exists = self.assets.insert(*asset.id(), asset).is_some();
         self.save()?;
         Ok(exists)
@@ -216,10 +696,77 @@ impl Cache for FileCache {
     #[inline]
     fn remove_asset(&mut self, id: ContractId) -> Result<bool, CacheError> {
         let existed = self.assets.remove(&id).is_some();
+        self.outpoint_index.remove(id);
         self.save()?;
+        if self.labels.remove(&id).is_some() {
+            self.save_labels()?;
+        }
+        if self.watched.remove(&id).is_some() {
+            self.save_watched()?;
+        }
+        if self.rights.remove(&id).is_some() {
+            self.save_rights()?;
+        }
+        let count = self.spendings.len();
+        self.spendings.retain(|spending| spending.contract_id != id);
+        if self.spendings.len() != count {
+            self.save_spendings()?;
+        }
+        let count = self.pending_transfers.len();
+        self.pending_transfers
+            .retain(|transfer| transfer.contract_id != id);
+        if self.pending_transfers.len() != count {
+            self.save_pending_transfers()?;
+        }
         Ok(existed)
     }
 
+    fn add_allocation(
+        &mut self,
+        id: ContractId,
+        outpoint: bitcoin::OutPoint,
+        node_id: NodeId,
+        index: u16,
+        value: value::Revealed,
+    ) -> Result<bool, CacheError> {
+        let asset =
+            self.assets
+                .get_mut(&id)
+                .ok_or(CacheError::DataIntegrityError(
+                    "Asset is not known".to_string(),
+                ))?;
+        if asset.insert_allocation(outpoint, node_id, index, value)
+            != AllocationInsertion::Added
+        {
+            return Ok(false);
+        }
+        self.outpoint_index.add_allocation(id, outpoint);
+        self.save()?;
+        Ok(true)
+    }
+
+    fn remove_allocation(
+        &mut self,
+        id: ContractId,
+        outpoint: bitcoin::OutPoint,
+        node_id: NodeId,
+        index: u16,
+        value: value::Revealed,
+    ) -> Result<bool, CacheError> {
+        let asset =
+            self.assets
+                .get_mut(&id)
+                .ok_or(CacheError::DataIntegrityError(
+                    "Asset is not known".to_string(),
+                ))?;
+        if !asset.remove_allocation(outpoint, node_id, index, value) {
+            return Ok(false);
+        }
+        self.outpoint_index.remove_allocation(id, outpoint);
+        self.save()?;
+        Ok(true)
+    }
+
     fn asset_allocations(
         &self,
         contract_id: ContractId,
@@ -254,17 +801,363 @@ impl Cache for FileCache {
 
         Ok(result)
     }
+
+    #[inline]
+    fn assets_affected_by_spend(
+        &self,
+        outpoint: bitcoin::OutPoint,
+    ) -> Result<Vec<ContractId>, CacheError> {
+        Ok(self.outpoint_index.assets_affected_by_spend(outpoint))
+    }
 }
 
-#[cfg(all(test, feature = "sql"))]
+#[cfg(test)]
 mod test {
+    #[cfg(feature = "sql")]
     use super::super::sql::{SqlCache, SqlCacheConfig};
     use super::*;
+    #[cfg(feature = "sql")]
     use amplify::hex::FromHex;
     use std::env;
 
+    fn issue(ticker: &str, vout: u32) -> Asset {
+        rgb20::issue(
+            lnpbp::chain::Chain::Testnet3,
+            ticker.to_owned(),
+            s!("Test asset"),
+            None,
+            0,
+            vec![(bitcoin::OutPoint::new(Default::default(), vout), 100)],
+            bmap! {},
+            None,
+            None,
+        )
+        .unwrap()
+        .0
+    }
+
+    /// Creates empty cache in a temporary directory unique for the test
+    fn temp_cache(name: &str) -> (FileCacheConfig, FileCache) {
+        let config = FileCacheConfig {
+            data_dir: env::temp_dir().join(format!(
+                "rgb-node-test-filecache-{}-{}",
+                name,
+                std::process::id()
+            )),
+            data_format: FileFormat::StrictEncode,
+        };
+        let _ = fs::remove_dir_all(&config.data_dir);
+        let cache = FileCache::new(config.clone()).unwrap();
+        (config, cache)
+    }
+
+    #[test]
+    fn test_filecache_index() {
+        let (config, mut cache) = temp_cache("index");
+        let first = issue("FST", 0);
+        let second = issue("SND", 1);
+
+        assert!(!cache.add_asset(first.clone()).unwrap());
+        assert!(!cache.add_asset(second.clone()).unwrap());
+        assert_eq!(cache.assets().unwrap().len(), 2);
+        assert_eq!(cache.asset(*second.id()).unwrap(), &second);
+
+        // Re-adding the asset replaces it under the same key
+        assert!(cache.add_asset(first.clone()).unwrap());
+        assert_eq!(cache.assets().unwrap().len(), 2);
+
+        assert!(cache.remove_asset(*first.id()).unwrap());
+        assert!(!cache.remove_asset(*first.id()).unwrap());
+        assert!(!cache.has_asset(*first.id()).unwrap());
+        assert!(cache.asset(*first.id()).is_err());
+        assert_eq!(cache.asset(*second.id()).unwrap(), &second);
+
+        // Allocations added and removed one by one are indexed
+        let outpoint = bitcoin::OutPoint::new(Default::default(), 5);
+        let value = value::Revealed {
+            value: 10,
+            blinding: rgb::secp256k1zkp::key::ONE_KEY.into(),
+        };
+        let node_id = NodeId::default();
+        let id = *second.id();
+        assert!(cache
+            .add_allocation(id, outpoint, node_id, 0, value)
+            .unwrap());
+        assert!(!cache
+            .add_allocation(id, outpoint, node_id, 0, value)
+            .unwrap());
+        assert_eq!(cache.assets_affected_by_spend(outpoint).unwrap(), vec![id]);
+        assert!(cache
+            .remove_allocation(id, outpoint, node_id, 0, value)
+            .unwrap());
+        assert!(cache.assets_affected_by_spend(outpoint).unwrap().is_empty());
+
+        // The index is restored from the saved data
+        let cache = FileCache::new(config.clone()).unwrap();
+        assert_eq!(cache.assets().unwrap(), vec![&second]);
+
+        fs::remove_dir_all(config.data_dir).unwrap();
+    }
+
+    #[test]
+    fn test_filecache_labels() {
+        let (config, mut cache) = temp_cache("labels");
+        let first = issue("FST", 0);
+        let second = issue("SND", 1);
+        cache.add_asset(first.clone()).unwrap();
+        cache.add_asset(second.clone()).unwrap();
+
+        assert_eq!(cache.label(*first.id()), None);
+        assert!(!cache
+            .set_label(*first.id(), Some(s!("company treasury")))
+            .unwrap());
+        assert!(!cache.set_label(*second.id(), Some(s!("savings"))).unwrap());
+        assert_eq!(cache.label(*first.id()), Some(&s!("company treasury")));
+        // Labels do not change the asset data
+        assert_eq!(cache.asset(*first.id()).unwrap(), &first);
+
+        // Labels are restored from the saved data
+        let mut cache = FileCache::new(config.clone()).unwrap();
+        assert_eq!(cache.label(*first.id()), Some(&s!("company treasury")));
+        assert!(cache.set_label(*second.id(), None).unwrap());
+        assert_eq!(cache.label(*second.id()), None);
+
+        // Forgotten assets lose their labels
+        cache.remove_asset(*first.id()).unwrap();
+        let cache = FileCache::new(config.clone()).unwrap();
+        assert_eq!(cache.label(*first.id()), None);
+
+        fs::remove_dir_all(config.data_dir).unwrap();
+    }
+
+    #[test]
+    fn test_filecache_watched() {
+        let (config, mut cache) = temp_cache("watched");
+        let first = issue("FST", 0);
+        let second = issue("SND", 1);
+        cache.add_asset(first.clone()).unwrap();
+        cache.add_asset(second.clone()).unwrap();
+
+        let since = NaiveDateTime::from_timestamp(1_600_000_000, 0);
+        assert_eq!(cache.watched_since(*first.id()), None);
+        assert!(!cache.set_watched(*first.id(), Some(since)).unwrap());
+        assert!(!cache.set_watched(*second.id(), Some(since)).unwrap());
+
+        // Watch-only marks are restored from the saved data
+        let mut cache = FileCache::new(config.clone()).unwrap();
+        assert_eq!(cache.watched_since(*first.id()), Some(since));
+        assert!(cache.set_watched(*second.id(), None).unwrap());
+        assert_eq!(cache.watched_since(*second.id()), None);
+
+        // Forgotten assets lose their marks
+        cache.remove_asset(*first.id()).unwrap();
+        let cache = FileCache::new(config.clone()).unwrap();
+        assert_eq!(cache.watched_since(*first.id()), None);
+
+        fs::remove_dir_all(config.data_dir).unwrap();
+    }
+
+    #[test]
+    fn test_filecache_spendings() {
+        let (config, mut cache) = temp_cache("spendings");
+        let asset = issue("FST", 0);
+        cache.add_asset(asset.clone()).unwrap();
+        let outpoint = |vout| bitcoin::OutPoint::new(Default::default(), vout);
+        let right = |vout| OwnedRight {
+            right_type: 0,
+            node_id: NodeId::default(),
+            index: 0,
+            outpoint: outpoint(vout),
+        };
+        let spending = PendingSpending {
+            contract_id: *asset.id(),
+            inputs: bset![outpoint(0), outpoint(1)],
+            spent: right(1),
+            assigned: vec![right(2)],
+            inflation: None,
+            burned: bset![outpoint(0)],
+            ticker: None,
+            name: Some(s!("Renamed asset")),
+        };
+        cache.add_spending(spending.clone()).unwrap();
+        cache.set_rights(*asset.id(), vec![right(1)]).unwrap();
+
+        // Pending spendings and rights are restored from the saved data
+        let mut cache = FileCache::new(config.clone()).unwrap();
+        assert_eq!(cache.rights(*asset.id()), Some(&vec![right(1)]));
+        assert!(cache.take_spendings(outpoint(2)).unwrap().is_empty());
+        assert_eq!(cache.take_spendings(outpoint(0)).unwrap(), vec![spending]);
+        assert!(cache.take_spendings(outpoint(1)).unwrap().is_empty());
+
+        // Forgotten assets lose their rights
+        cache.remove_asset(*asset.id()).unwrap();
+        assert_eq!(cache.rights(*asset.id()), None);
+
+        fs::remove_dir_all(config.data_dir).unwrap();
+    }
+
+    #[test]
+    fn test_filecache_replies() {
+        let (config, mut cache) = temp_cache("replies");
+        let since = NaiveDateTime::from_timestamp(1_600_000_000, 0);
+        let reply = |request_type, key, secs| IdempotentReply {
+            request_type,
+            key: [key; 32],
+            request_hash: [0u8; 32],
+            reply: vec![key],
+            created_at: since + chrono::Duration::seconds(secs),
+        };
+        cache.add_idempotent_reply(reply(1, 1, 0)).unwrap();
+        cache.add_idempotent_reply(reply(2, 1, 1)).unwrap();
+
+        // Replies are restored from the saved data and keyed by the request
+        // type together with the idempotency key
+        let mut cache = FileCache::new(config.clone()).unwrap();
+        assert_eq!(cache.idempotent_reply(1, [1u8; 32]), Some(&reply(1, 1, 0)));
+        assert_eq!(cache.idempotent_reply(2, [1u8; 32]), Some(&reply(2, 1, 1)));
+        assert_eq!(cache.idempotent_reply(1, [2u8; 32]), None);
+
+        // Expired replies are dropped
+        cache
+            .add_idempotent_reply(reply(
+                1,
+                2,
+                FUNGIBLED_IDEMPOTENT_REPLY_TTL_SECS,
+            ))
+            .unwrap();
+        assert_eq!(cache.idempotent_reply(1, [1u8; 32]), None);
+        assert!(cache.idempotent_reply(2, [1u8; 32]).is_some());
+
+        // Only the latest replies are kept
+        for request_type in 0..FUNGIBLED_IDEMPOTENT_REPLIES {
+            cache
+                .add_idempotent_reply(reply(request_type as u16 + 3, 3, 2))
+                .unwrap();
+        }
+        assert_eq!(cache.idempotent_reply(2, [1u8; 32]), None);
+        assert!(cache.idempotent_reply(3, [3u8; 32]).is_some());
+
+        fs::remove_dir_all(config.data_dir).unwrap();
+    }
+
+    #[test]
+    fn test_filecache_versions() {
+        let (config, _) = temp_cache("versions");
+        let asset = issue("TST", 0);
+        let assets = bmap! { *asset.id() => asset.clone() };
+
+        // Cache files written before the versioning was introduced
+        fs::write(config.assets_filename(), strict_serialize(&assets).unwrap())
+            .unwrap();
+        let cache = FileCache::new(config.clone()).unwrap();
+        assert_eq!(cache.assets().unwrap(), vec![&asset]);
+
+        cache.save().unwrap();
+        let data = fs::read(config.assets_filename()).unwrap();
+        assert_eq!(data[..4], MagicNumber::Cache.to_u32().to_be_bytes());
+        assert_eq!(data[4..6], ASSET_JSON_VERSION.to_le_bytes());
+        let cache = FileCache::new(config.clone()).unwrap();
+        assert_eq!(cache.assets().unwrap(), vec![&asset]);
+
+        let mut data = data;
+        data[4..6].copy_from_slice(&(ASSET_JSON_VERSION + 1).to_le_bytes());
+        fs::write(config.assets_filename(), data).unwrap();
+        assert!(matches!(
+            FileCache::new(config.clone()),
+            Err(FileCacheError::UnsupportedVersion(version))
+                if version == ASSET_JSON_VERSION + 1
+        ));
+
+        fs::remove_dir_all(config.data_dir).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_filecache_migration() {
+        let config = FileCacheConfig {
+            data_dir: env::temp_dir().join(format!(
+                "rgb-node-test-filecache-migration-{}",
+                std::process::id()
+            )),
+            data_format: FileFormat::Json,
+        };
+        let _ = fs::remove_dir_all(&config.data_dir);
+        fs::create_dir_all(config.assets_dir()).unwrap();
+
+        // Version 1 asset data, written without the envelope, lacking a
+        // later-added field
+        let asset = issue("TST", 0);
+        let mut raw = serde_json::to_value(&asset).unwrap();
+        let fields = raw.as_object_mut().unwrap();
+        assert!(fields.remove("knownInflation").is_some());
+        fs::write(
+            config.assets_filename(),
+            serde_json::to_vec(&serde_json::json!({
+                asset.id().to_string(): raw
+            }))
+            .unwrap(),
+        )
+        .unwrap();
+
+        let cache = FileCache::new(config.clone()).unwrap();
+        let migrated = cache.asset(*asset.id()).unwrap();
+        assert!(migrated.known_inflation().is_empty());
+        assert_eq!(migrated.known_allocations(), asset.known_allocations());
+
+        // Upgraded data are saved in the current version
+        cache.save().unwrap();
+        let saved: serde_json::Value = serde_json::from_slice(
+            &fs::read(config.assets_filename()).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(saved["version"], ASSET_JSON_VERSION);
+        let cache = FileCache::new(config.clone()).unwrap();
+        assert!(cache
+            .asset(*asset.id())
+            .unwrap()
+            .known_inflation()
+            .is_empty());
+
+        fs::remove_dir_all(config.data_dir).unwrap();
+    }
+
+    #[test]
+    fn test_filecache_pending_transfers() {
+        let (config, mut cache) = temp_cache("transfers");
+        let transfer = |vout, amount| PendingTransfer {
+            contract_id: ContractId::default(),
+            amount,
+            seal: SealEndpoint::WitnessVout { vout, blinding: 1 },
+            created_at: NaiveDateTime::from_timestamp(1_600_000_000, 0),
+        };
+        cache
+            .add_pending_transfers(vec![transfer(1, 400), transfer(2, 100)])
+            .unwrap();
+
+        // Pending transfers survive reloading of the cache
+        let mut cache = FileCache::new(config.clone()).unwrap();
+        assert_eq!(
+            cache.pending_transfers(),
+            &vec![transfer(1, 400), transfer(2, 100)]
+        );
+
+        let seal = transfer(1, 400).seal;
+        assert!(cache.accept_pending_transfer(&seal).unwrap());
+        assert!(!cache.accept_pending_transfer(&seal).unwrap());
+        let mut cache = FileCache::new(config.clone()).unwrap();
+        assert_eq!(cache.pending_transfers(), &vec![transfer(2, 100)]);
+
+        // Transfers of the forgotten asset are dropped
+        cache.remove_asset(ContractId::default()).unwrap();
+        let cache = FileCache::new(config.clone()).unwrap();
+        assert!(cache.pending_transfers().is_empty());
+
+        fs::remove_dir_all(config.data_dir).unwrap();
+    }
+
     #[test]
     #[ignore]
+    #[cfg(feature = "sql")]
     fn test_filecache_mappings() {
         // -------------------------------------------------
         // Setup sqlite database connection
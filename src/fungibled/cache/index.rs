@@ -0,0 +1,219 @@
+// RGB standard library
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use bitcoin::OutPoint;
+use rgb::ContractId;
+use rgb20::Asset;
+
+/// Reverse index of the outpoints to the assets having allocations or
+/// inflation rights assigned to them. Allows to find assets affected by the
+/// spending of an outpoint without scanning all of the known asset data.
+///
+/// The index is not updated by [`Asset::add_allocation`] and
+/// [`Asset::remove_allocation`] directly: caches must either re-index the
+/// asset with [`OutpointIndex::insert`] each time the updated asset data are
+/// stored, or report single allocation changes with
+/// [`OutpointIndex::add_allocation`] and [`OutpointIndex::remove_allocation`].
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub(crate) struct OutpointIndex {
+    /// Assets referencing each of the indexed outpoints
+    assets: BTreeMap<OutPoint, BTreeSet<ContractId>>,
+
+    /// Outpoints referenced by each of the indexed assets, with the number
+    /// of allocations and inflation rights assigned to the outpoint
+    outpoints: BTreeMap<ContractId, BTreeMap<OutPoint, usize>>,
+}
+
+impl OutpointIndex {
+    /// Builds index for all of the provided assets
+    pub fn with<'a>(assets: impl IntoIterator<Item = &'a Asset>) -> Self {
+        let mut index = Self::default();
+        for asset in assets {
+            index.insert(asset);
+        }
+        index
+    }
+
+    /// Indexes outpoints of the asset allocations and inflation rights,
+    /// replacing outpoints indexed for the previous version of the asset data
+    pub fn insert(&mut self, asset: &Asset) {
+        let id = *asset.id();
+        self.remove(id);
+        let outpoints = asset
+            .known_allocations()
+            .iter()
+            .map(|allocation| *allocation.outpoint())
+            .chain(asset.known_inflation().keys().copied());
+        for outpoint in outpoints {
+            self.add_allocation(id, outpoint);
+        }
+    }
+
+    /// Removes all outpoints indexed for the asset
+    pub fn remove(&mut self, id: ContractId) {
+        for outpoint in self.outpoints.remove(&id).unwrap_or_default().keys() {
+            self.unindex(id, *outpoint);
+        }
+    }
+
+    /// Indexes allocation of the asset added to the outpoint
+    pub fn add_allocation(&mut self, id: ContractId, outpoint: OutPoint) {
+        *self
+            .outpoints
+            .entry(id)
+            .or_default()
+            .entry(outpoint)
+            .or_default() += 1;
+        self.assets.entry(outpoint).or_default().insert(id);
+    }
+
+    /// Removes allocation of the asset from the outpoint; the outpoint is
+    /// unindexed for the asset once it has no more allocations there
+    pub fn remove_allocation(&mut self, id: ContractId, outpoint: OutPoint) {
+        let outpoints = match self.outpoints.get_mut(&id) {
+            Some(outpoints) => outpoints,
+            None => return,
+        };
+        match outpoints.get_mut(&outpoint) {
+            Some(count) if *count > 1 => {
+                *count -= 1;
+                return;
+            }
+            Some(_) => {
+                outpoints.remove(&outpoint);
+            }
+            None => return,
+        }
+        if outpoints.is_empty() {
+            self.outpoints.remove(&id);
+        }
+        self.unindex(id, outpoint);
+    }
+
+    /// Returns ids of the assets affected by the spending of the outpoint
+    pub fn assets_affected_by_spend(
+        &self,
+        outpoint: OutPoint,
+    ) -> Vec<ContractId> {
+        self.assets
+            .get(&outpoint)
+            .map(|ids| ids.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    fn unindex(&mut self, id: ContractId, outpoint: OutPoint) {
+        if let Some(ids) = self.assets.get_mut(&outpoint) {
+            ids.remove(&id);
+            if ids.is_empty() {
+                self.assets.remove(&outpoint);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bitcoin::hashes::hex::FromHex;
+    use bitcoin::Txid;
+    use lnpbp::chain::Chain;
+    use rgb::AtomicValue;
+
+    fn outpoint(vout: u32) -> OutPoint {
+        OutPoint::new(
+            Txid::from_hex(
+                "d47df6cf7a0eff79d3afeab7614404e43a0fa4498ff081918a2e75d7366cd730",
+            )
+            .unwrap(),
+            vout,
+        )
+    }
+
+    fn issue(
+        ticker: &str,
+        allocation: Vec<(OutPoint, AtomicValue)>,
+        inflation: BTreeMap<OutPoint, AtomicValue>,
+    ) -> Asset {
+        rgb20::issue(
+            Chain::Testnet3,
+            ticker.to_owned(),
+            s!("Test asset"),
+            None,
+            0,
+            allocation,
+            inflation,
+            None,
+            None,
+        )
+        .unwrap()
+        .0
+    }
+
+    #[test]
+    fn test_shared_outpoint() {
+        let first = issue("FST", vec![(outpoint(0), 100)], bmap! {});
+        let second = issue(
+            "SND",
+            vec![(outpoint(1), 100)],
+            bmap! { outpoint(0) => 1000 },
+        );
+        let third = issue("TRD", vec![(outpoint(2), 100)], bmap! {});
+        let mut index = OutpointIndex::with(vec![&first, &second, &third]);
+
+        let mut affected = index.assets_affected_by_spend(outpoint(0));
+        affected.sort();
+        let mut expected = vec![*first.id(), *second.id()];
+        expected.sort();
+        assert_eq!(affected, expected);
+        assert_eq!(
+            index.assets_affected_by_spend(outpoint(2)),
+            vec![*third.id()]
+        );
+        assert!(index.assets_affected_by_spend(outpoint(3)).is_empty());
+
+        // Re-indexing the asset does not duplicate entries
+        index.insert(&first);
+        assert_eq!(index.assets_affected_by_spend(outpoint(0)).len(), 2);
+
+        index.remove(*second.id());
+        assert_eq!(
+            index.assets_affected_by_spend(outpoint(0)),
+            vec![*first.id()]
+        );
+        assert!(index.assets_affected_by_spend(outpoint(1)).is_empty());
+    }
+
+    #[test]
+    fn test_allocation_hooks() {
+        let asset = issue("FST", vec![(outpoint(0), 100)], bmap! {});
+        let id = *asset.id();
+        let mut index = OutpointIndex::with(vec![&asset]);
+
+        index.add_allocation(id, outpoint(1));
+        index.add_allocation(id, outpoint(1));
+        assert_eq!(index.assets_affected_by_spend(outpoint(1)), vec![id]);
+
+        // Outpoint is indexed while some of the allocations are left on it
+        index.remove_allocation(id, outpoint(1));
+        assert_eq!(index.assets_affected_by_spend(outpoint(1)), vec![id]);
+        index.remove_allocation(id, outpoint(1));
+        assert!(index.assets_affected_by_spend(outpoint(1)).is_empty());
+        index.remove_allocation(id, outpoint(1));
+
+        index.remove_allocation(id, outpoint(0));
+        assert!(index.assets_affected_by_spend(outpoint(0)).is_empty());
+        assert_eq!(index, OutpointIndex::default());
+    }
+}
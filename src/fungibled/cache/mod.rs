@@ -13,10 +13,16 @@
 
 mod cache;
 mod file;
+mod index;
+mod reply;
+mod spending;
 #[cfg(feature = "sql")]
 mod sql;
 
 pub use cache::{Cache, CacheError};
 pub use file::{FileCache, FileCacheConfig, FileCacheError};
+pub(crate) use index::OutpointIndex;
+pub use reply::IdempotentReply;
+pub use spending::{OwnedRight, PendingSpending};
 #[cfg(feature = "sql")]
 pub use sql::{SqlCache, SqlCacheConfig, SqlCacheError};
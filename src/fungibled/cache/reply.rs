@@ -0,0 +1,44 @@
+// RGB standard library
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use chrono::NaiveDateTime;
+
+/// Reply to the request which had an idempotency key, returned when the
+/// request of the same type with the same key is repeated
+#[derive(Clone, PartialEq, Eq, Debug, Display, StrictEncode, StrictDecode)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+#[display("reply to request {request_type:#06x} at {created_at}")]
+pub struct IdempotentReply {
+    /// RPC API type of the request
+    pub request_type: u16,
+
+    /// Idempotency key provided by the client
+    pub key: [u8; 32],
+
+    /// SHA256 hash of the serialized request, used for detecting reuse of
+    /// the key for a different request
+    pub request_hash: [u8; 32],
+
+    /// Serialized reply to the request
+    pub reply: Vec<u8>,
+
+    /// Time when the request was performed
+    pub created_at: NaiveDateTime,
+}
@@ -0,0 +1,81 @@
+// RGB standard library
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+use bitcoin::OutPoint;
+use rgb::schema::OwnedRightType;
+use rgb::{AtomicValue, ContractId, NodeId};
+
+/// Asset right (inflation, burn or renomination right) assigned by the genesis
+/// or a state transition to a single-use seal
+#[derive(
+    Clone, Copy, PartialEq, Eq, Hash, Debug, Display, StrictEncode, StrictDecode,
+)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+#[display("{right_type}@{node_id}#{index}>{outpoint}")]
+pub struct OwnedRight {
+    /// Type of the right as defined by RGB20 schema
+    pub right_type: OwnedRightType,
+
+    /// Genesis or state transition assigning the right
+    pub node_id: NodeId,
+
+    /// Index of the right assignment within the node
+    pub index: u16,
+
+    /// Outpoint controlling the right
+    pub outpoint: OutPoint,
+}
+
+/// State transition spending some of the asset rights, composed by the node.
+/// The transition is applied to the asset data only once its witness
+/// transaction is confirmed, i.e. some of its inputs are reported as spent.
+#[derive(Clone, PartialEq, Eq, Debug, Display, StrictEncode, StrictDecode)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+#[display("{contract_id}: spending {spent}")]
+pub struct PendingSpending {
+    /// Asset contract id
+    pub contract_id: ContractId,
+
+    /// Outpoints spent by the witness transaction
+    pub inputs: BTreeSet<OutPoint>,
+
+    /// Right spent by the transition
+    pub spent: OwnedRight,
+
+    /// Rights assigned by the transition
+    pub assigned: Vec<OwnedRight>,
+
+    /// Capacity of the inflation right assigned by the transition, if any
+    pub inflation: Option<AtomicValue>,
+
+    /// Outpoints which assets are burned by the transition
+    pub burned: BTreeSet<OutPoint>,
+
+    /// New asset ticker set by the transition, if any
+    pub ticker: Option<String>,
+
+    /// New asset name set by the transition, if any
+    pub name: Option<String>,
+}
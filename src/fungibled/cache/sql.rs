@@ -31,7 +31,9 @@ use cache_schema::sql_inflation::dsl::sql_inflation as sql_inflation_table;
 use cache_schema::sql_issues::dsl::sql_issues as sql_issue_table;
 
 use super::cache::{Cache, CacheError};
+use super::OutpointIndex;
 use crate::fungibled::sql::models::*;
+use crate::util::{AllocationInsertion, AssetExt};
 
 #[derive(Debug, Display, Error, From)]
 #[display(inner)]
@@ -110,6 +112,7 @@ impl SqlCacheConfig {
 pub struct SqlCache {
     connection: SqliteConnection,
     assets: HashMap<ContractId, Asset>,
+    outpoint_index: OutpointIndex,
 }
 
 impl fmt::Display for SqlCache {
@@ -155,6 +158,7 @@ impl SqlCache {
             let mut sql_cache = Self {
                 connection,
                 assets: map![],
+                outpoint_index: default!(),
             };
 
             sql_cache.load()?;
@@ -177,6 +181,7 @@ impl SqlCache {
             let sql_cache = Self {
                 connection,
                 assets: map![],
+                outpoint_index: default!(),
             };
 
             Ok(sql_cache)
@@ -196,6 +201,7 @@ impl SqlCache {
             );
         }
 
+        self.outpoint_index = OutpointIndex::with(asset_map.values());
         self.assets = asset_map;
 
         Ok(())
@@ -282,6 +288,7 @@ impl Cache for SqlCache {
     }
 
     fn add_asset(&mut self, asset: Asset) -> Result<bool, CacheError> {
+        self.outpoint_index.insert(&asset);
         let exists = self.assets.insert(*asset.id(), asset).is_some();
         self.save()?;
         Ok(exists)
@@ -290,10 +297,57 @@ impl Cache for SqlCache {
     #[inline]
     fn remove_asset(&mut self, id: ContractId) -> Result<bool, CacheError> {
         let existed = self.assets.remove(&id).is_some();
+        self.outpoint_index.remove(id);
         self.save()?;
         Ok(existed)
     }
 
+    fn add_allocation(
+        &mut self,
+        id: ContractId,
+        outpoint: bitcoin::OutPoint,
+        node_id: NodeId,
+        index: u16,
+        value: value::Revealed,
+    ) -> Result<bool, CacheError> {
+        let asset =
+            self.assets
+                .get_mut(&id)
+                .ok_or(CacheError::DataIntegrityError(
+                    "Asset is not known".to_string(),
+                ))?;
+        if asset.insert_allocation(outpoint, node_id, index, value)
+            != AllocationInsertion::Added
+        {
+            return Ok(false);
+        }
+        self.outpoint_index.add_allocation(id, outpoint);
+        self.save()?;
+        Ok(true)
+    }
+
+    fn remove_allocation(
+        &mut self,
+        id: ContractId,
+        outpoint: bitcoin::OutPoint,
+        node_id: NodeId,
+        index: u16,
+        value: value::Revealed,
+    ) -> Result<bool, CacheError> {
+        let asset =
+            self.assets
+                .get_mut(&id)
+                .ok_or(CacheError::DataIntegrityError(
+                    "Asset is not known".to_string(),
+                ))?;
+        if !asset.remove_allocation(outpoint, node_id, index, value) {
+            return Ok(false);
+        }
+        self.outpoint_index.remove_allocation(id, outpoint);
+        self.save()?;
+        Ok(true)
+    }
+
     // TODO #157: Move this method to RGB20
     // Issue
     fn asset_allocations(
@@ -365,6 +419,14 @@ impl Cache for SqlCache {
 
         Ok(result)
     }
+
+    #[inline]
+    fn assets_affected_by_spend(
+        &self,
+        outpoint: bitcoin::OutPoint,
+    ) -> Result<Vec<ContractId>, CacheError> {
+        Ok(self.outpoint_index.assets_affected_by_spend(outpoint))
+    }
 }
 
 #[cfg(test)]
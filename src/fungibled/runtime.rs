@@ -12,12 +12,14 @@
 // If not, see <https://opensource.org/licenses/MIT>.
 
 use core::borrow::Borrow;
-use core::convert::TryFrom;
 use std::collections::{BTreeMap, BTreeSet};
+use std::io;
 use std::path::PathBuf;
 
+use bitcoin::hashes::{sha256, Hash};
 use bitcoin::{OutPoint, Txid};
 use bp::seals::OutpointReveal;
+use chrono::{NaiveDateTime, Utc};
 use commit_verify::CommitConceal;
 use internet2::zmqsocket::ZmqType;
 use internet2::TypedEnum;
@@ -27,14 +29,20 @@ use internet2::{
 };
 use microservices::node::TryService;
 use microservices::FileFormat;
+use rgb::schema::{HistoryProofFormat, OwnedRightType};
 use rgb::{
-    AtomicValue, Consignment, ContractId, Disclosure, Genesis, Node,
+    data, secp256k1zkp, validation, value, Assignments, AtomicValue,
+    Consignment, ContractId, Disclosure, Genesis, Node, NodeId, OwnedState,
     SealDefinition, SealEndpoint, Transition,
 };
-use rgb20::schema::OwnedRightsType;
-use rgb20::{schema, Asset, OutpointCoins};
+use rgb20::schema::{FieldType, OwnedRightsType, TransitionType};
+use rgb20::{schema, Allocation, Asset, OutpointCoins};
+use strict_encoding::StrictEncode;
 
-use super::cache::{Cache, FileCache, FileCacheConfig};
+use super::cache::{
+    Cache, FileCache, FileCacheConfig, IdempotentReply, OwnedRight,
+    PendingSpending,
+};
 use super::Config;
 use crate::error::{
     ApiErrorType, BootstrapError, RuntimeError, ServiceError,
@@ -42,14 +50,33 @@ use crate::error::{
 };
 use crate::rpc::{
     self,
-    fungible::{AcceptReq, IssueReq, Request, TransferReq},
+    fungible::{
+        negotiate_wire_format, validate_batch, AcceptReq, BurnError, BurnReq,
+        ForgetAssetReq, InflateError, InflateReq, IssueReq, ListAssetsReq,
+        RenominateError, RenominateReq, Request, SetLabelReq, TransferError,
+        TransferReq,
+    },
     reply,
     stash::AcceptRequest,
     stash::TransferRequest,
-    Reply,
+    wire, Reply,
+};
+use crate::util::{
+    pending_allocations, AllocationInsertion, AssetExt, GenesisError, SealSpec,
+    ToBech32Data, RGB20_SCHEMA_ID,
 };
-use crate::util::ToBech32Data;
 
+/// Fungible assets daemon runtime.
+///
+/// Requests are received from a single ZMQ REP socket and served one at a
+/// time: the socket does not accept the next request before the reply to the
+/// previous one is sent, so requests of multiple clients are queued by ZMQ.
+/// Request handlers take `&mut self`, thus the asset cache and its reverse
+/// outpoint index are never accessed concurrently and require no locking; a
+/// handler re-indexes the asset each time it stores updated asset data,
+/// before the next request is read. Any future multi-threaded request
+/// serving must guard the cache and the index with a single lock, so they
+/// can't be updated in a different order by different threads.
 pub struct Runtime {
     /// Original configuration object
     config: Config,
@@ -71,6 +98,10 @@ pub struct Runtime {
 
     /// Unmarshaller instance used for parsing RPC request
     reply_unmarshaller: Unmarshaller<Reply>,
+
+    /// Chunks of the consignment of the last transfer returned as
+    /// [`Reply::ChunkedTransfer`], kept until the next chunked transfer
+    consignment_chunks: Vec<reply::ConsignmentChunk>,
 }
 
 impl Runtime {
@@ -113,6 +144,7 @@ impl Runtime {
             cacher,
             unmarshaller: Request::create_unmarshaller(),
             reply_unmarshaller: Reply::create_unmarshaller(),
+            consignment_chunks: vec![],
         })
     }
 }
@@ -145,9 +177,18 @@ impl Runtime {
     fn run(&mut self) -> Result<(), RuntimeError> {
         trace!("Awaiting for ZMQ RPC requests...");
         let raw = self.fungible_rpc_server.recv_raw_message()?;
-        let reply = self.rpc_process(raw).unwrap_or_else(|err| err);
+        // Replies are sent in the format of the request, which is the one
+        // negotiated by the client
+        let format = wire::detect_format(&raw);
+        let reply = self.rpc_process(raw, format).unwrap_or_else(|err| err);
+        let (reply, chunks) =
+            chunk_transfer(reply, reply::ConsignmentChunk::MAX_SIZE);
+        if !chunks.is_empty() {
+            debug!("Consignment is split into {} chunks", chunks.len());
+            self.consignment_chunks = chunks;
+        }
         trace!("Preparing ZMQ RPC reply: {:?}", reply);
-        let data = reply.serialize();
+        let data = wire::encode(&reply, format);
         trace!(
             "Sending {} bytes back to the client over ZMQ RPC",
             data.len()
@@ -156,46 +197,115 @@ impl Runtime {
         Ok(())
     }
 
-    fn rpc_process(&mut self, raw: Vec<u8>) -> Result<Reply, Reply> {
+    fn rpc_process(
+        &mut self,
+        raw: Vec<u8>,
+        format: FileFormat,
+    ) -> Result<Reply, Reply> {
         trace!(
             "Got {} bytes over ZMQ RPC: {:?}",
             raw.len(),
             raw.to_bech32data()
         );
-        let message = &*self.unmarshaller.unmarshall(&raw).map_err(|err| {
-            error!("Error unmarshalling the data: {}", err);
-            ServiceError::from_rpc(
-                ServiceErrorSource::Contract(s!("fungible")),
-                err,
-            )
-        })?;
+        let message = &*wire::decode(&self.unmarshaller, &raw, format)
+            .map_err(|err| {
+                error!("Error unmarshalling the data: {}", err);
+                ServiceError::from_rpc(
+                    ServiceErrorSource::Contract(s!("fungible")),
+                    err,
+                )
+            })?;
         debug!("Received ZMQ RPC request: {:?}", message);
         Ok(match message {
+            Request::Ping => Ok(Reply::Pong),
+            Request::Info => self.rpc_info(),
+            Request::Hello(formats) => self.rpc_hello(formats),
             Request::Issue(issue) => self.rpc_issue(issue),
+            Request::Inflate(inflate) => self.rpc_inflate(inflate),
+            Request::Burn(burn) => self.rpc_burn(burn),
+            Request::Renominate(renominate) => self.rpc_renominate(renominate),
             Request::Transfer(transfer) => self.rpc_transfer(transfer),
+            Request::BatchTransfer(transfers) => {
+                self.rpc_batch_transfer(transfers)
+            }
+            Request::TransferDryRun(transfer) => {
+                self.rpc_transfer_dry_run(transfer)
+            }
+            Request::PreviewTransfer(transfer) => {
+                self.rpc_preview_transfer(transfer)
+            }
             Request::Validate(consignment) => self.rpc_validate(consignment),
             Request::Accept(accept) => self.rpc_accept(accept),
             Request::Enclose(disclosure) => self.rpc_enclose(disclosure),
             Request::Forget(outpoint) => self.rpc_forget(outpoint),
+            Request::ForgetAsset(forget) => self.rpc_forget_asset(forget),
             Request::ImportAsset(genesis) => self.rpc_import_asset(genesis),
             Request::ExportAsset(asset_id) => self.rpc_export_asset(asset_id),
             Request::Sync(data_format) => self.rpc_sync(*data_format),
+            Request::ListAssets(list) => self.rpc_list_assets(list),
             Request::Assets(outpoint) => self.rpc_outpoint_assets(*outpoint),
             Request::Allocations(contract_id) => {
                 self.rpc_asset_allocations(*contract_id)
             }
+            Request::ListPendingTransfers => self.rpc_pending_transfers(),
+            Request::SetLabel(set_label) => self.rpc_set_label(set_label),
+            Request::Label(label) => self.rpc_label(label.contract_id),
+            Request::ClearWatchOnly(clear) => {
+                self.rpc_clear_watch_only(clear.contract_id)
+            }
+            Request::TransferAccepted(accepted) => {
+                self.rpc_transfer_accepted(&accepted.seal)
+            }
+            Request::ConsignmentChunk(chunk) => {
+                self.rpc_consignment_chunk(chunk.seq)
+            }
         }
         .map_err(|err| ServiceError::contract(err, "fungible"))?)
     }
 
+    fn rpc_info(&mut self) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got INFO");
+        Ok(Reply::Info(reply::ServerInfo {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            schema_ids: vec![*RGB20_SCHEMA_ID],
+            chain: self.config.network.clone(),
+        }))
+    }
+
+    fn rpc_hello(
+        &mut self,
+        formats: &[FileFormat],
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got HELLO");
+        let format = negotiate_wire_format(formats).ok_or_else(|| {
+            ServiceErrorDomain::Api(ApiErrorType::MalformedArgument {
+                request: s!("hello"),
+                argument: s!("formats"),
+            })
+        })?;
+        Ok(Reply::WireFormat(reply::WireFormat { format }))
+    }
+
     fn rpc_issue(
         &mut self,
         issue: &IssueReq,
     ) -> Result<Reply, ServiceErrorDomain> {
         debug!("Got ISSUE {}", issue);
 
+        self.idempotent(
+            "issue",
+            Request::Issue(issue.clone()),
+            issue.idempotency_key,
+            |runtime| runtime.compose_issue(issue),
+        )
+    }
+
+    fn compose_issue(
+        &mut self,
+        issue: &IssueReq,
+    ) -> Result<Reply, ServiceErrorDomain> {
         let issue = issue.clone();
-        let (asset, genesis) = rgb20::issue(
+        let (_, genesis) = rgb20::issue(
             self.config.network.clone(),
             issue.ticker,
             issue.name,
@@ -220,6 +330,7 @@ impl Runtime {
             issue.renomination,
             issue.epoch,
         )?;
+        let asset = Asset::from_genesis(&genesis)?;
 
         self.import_asset(asset.clone(), genesis)?;
 
@@ -228,130 +339,285 @@ impl Runtime {
         Ok(Reply::Asset(asset))
     }
 
-    fn rpc_transfer(
+    fn rpc_inflate(
         &mut self,
-        transfer: &TransferReq,
+        inflate: &InflateReq,
     ) -> Result<Reply, ServiceErrorDomain> {
-        debug!("Got TRANSFER {}", transfer);
+        debug!("Got INFLATE {}", inflate);
 
-        // TODO #66: Check inputs that they really exist and have sufficient
-        //       amount of asset for the transfer operation
+        let asset = self.asset(inflate.contract_id)?.clone();
+        let rights = self.asset_rights(inflate.contract_id)?;
+        let (transition, inflation_right, inflation_change) =
+            inflation_transition(&asset, &rights, inflate)?;
+        debug!("State transition: {}", transition);
+        let spending = inflation_spending(
+            &asset,
+            &transition,
+            inflation_right,
+            inflation_change,
+            inflate,
+        );
 
-        trace!("Looking for asset information");
-        debug!("Transferring asset {}", transfer.contract_id);
+        let inputs = bset![inflate.inflation];
+        let other_transitions = self.blank_transitions(
+            &bset![inflate.contract_id],
+            &inputs,
+            &inflate.allocation,
+        )?;
 
-        trace!("Preparing state transition");
-        // Filtering inputs which do not have this assets: we will need them
-        // later, but not for constructing the main RGB20 transfer transition
-        let asset = self.cacher.asset(transfer.contract_id)?;
-        let inputs = transfer
-            .inputs
-            .iter()
-            .filter(|outpoint| !asset.allocations(**outpoint).is_empty())
-            .cloned()
+        trace!("Requesting consignment from stash daemon");
+        let endpoints = inflate
+            .allocation
+            .keys()
+            .copied()
+            .chain(inflation_change)
+            .map(SealEndpoint::from)
             .collect();
-        let transition = rgb20::transfer(
-            asset,
+        let mut reply = self.consign(TransferRequest {
+            contract_id: inflate.contract_id,
             inputs,
-            transfer.payment.clone(),
-            transfer.change.clone(),
-        )?;
-        debug!("State transition: {}", transition);
+            transition,
+            other_transitions,
+            endpoints,
+            psbt: inflate.witness.clone(),
+        })?;
 
-        trace!("Collecting other assets on the spent outpoints and preparing blank state transitions");
-        let mut other_outpoint_assets: BTreeMap<
-            ContractId,
-            BTreeSet<(OutPoint, AtomicValue)>,
-        > = bmap! {};
-        for outpoint in &transfer.inputs {
-            for (other_contract_id, amounts) in
-                self.cacher.outpoint_assets(*outpoint)?
-            {
-                let sum = amounts.into_iter().sum();
-                // Ignoring native asset, current contract and zero balances
-                if other_contract_id == transfer.contract_id || sum == 0 {
-                    continue;
-                }
-                other_outpoint_assets
-                    .entry(other_contract_id)
-                    .or_insert(empty!())
-                    .insert((*outpoint, sum));
-            }
+        // All of the new allocations belong to us, so concealing everything
+        if let Reply::Transfer(reply::Transfer {
+            ref mut consignment,
+            ..
+        }) = reply
+        {
+            consignment.finalize(&bset![], inflate.contract_id);
+            // Remaining inflation right becomes known only once the witness
+            // transaction is confirmed
+            self.cacher.add_spending(spending)?;
         }
-        debug!(
-            "Total {} other assets are found on the spent outpoints",
-            other_outpoint_assets.len()
-        );
-        trace!("{:?}", other_outpoint_assets);
-        let change_seal = if other_outpoint_assets.len() > 0 {
-            transfer.change.keys().find(|_| true).ok_or(
-                ServiceErrorDomain::Internal(s!(
-                    "Other assets are present on the provided inputs, but no change address given"
-                ))
-            )?.clone()
-        } else {
-            SealDefinition::WitnessVout {
-                vout: 0,
-                blinding: 0,
-            } // Not used
-        };
-        let mut other_transitions = bmap! {};
-        for (other_contract, outpoints) in other_outpoint_assets {
-            other_transitions.insert(
-                other_contract,
-                rgb20::transfer(
-                    self.cacher.asset(other_contract)?,
-                    outpoints.iter().map(|(outpoint, _)| *outpoint).collect(),
-                    empty!(),
-                    outpoints
-                        .iter()
-                        .map(|(_, amount)| (change_seal, *amount))
-                        .collect(),
-                )?,
-            );
+
+        Ok(reply)
+    }
+
+    fn rpc_burn(
+        &mut self,
+        burn: &BurnReq,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got BURN {}", burn);
+
+        burn.validate()?;
+        let asset = self.asset(burn.contract_id)?.clone();
+        let rights = self.asset_rights(burn.contract_id)?;
+        let (transition, burn_right) = burn_transition(&asset, &rights, burn)?;
+        debug!("State transition: {}", transition);
+        let spending = burn_spending(&transition, burn_right, burn);
+
+        let inputs = bset![burn_right.outpoint];
+        let other_transitions = self.blank_transitions(
+            &bset![burn.contract_id],
+            &inputs,
+            &bmap! {},
+        )?;
+
+        trace!("Requesting consignment from stash daemon");
+        let endpoints = burn
+            .proof_seal
+            .into_iter()
+            .map(SealEndpoint::from)
+            .collect();
+        let mut reply = self.consign(TransferRequest {
+            contract_id: burn.contract_id,
+            inputs,
+            transition,
+            other_transitions,
+            endpoints,
+            psbt: burn.witness.clone(),
+        })?;
+
+        if let Reply::Transfer(reply::Transfer {
+            ref mut consignment,
+            ..
+        }) = reply
+        {
+            consignment.finalize(&bset![], burn.contract_id);
+            // Assets are burned only once the witness transaction is confirmed
+            self.cacher.add_spending(spending)?;
         }
 
+        Ok(reply)
+    }
+
+    fn rpc_renominate(
+        &mut self,
+        renominate: &RenominateReq,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got RENOMINATE {}", renominate);
+
+        renominate.validate()?;
+        let rights = self.asset_rights(renominate.contract_id)?;
+        let (transition, renomination_right) =
+            renomination_transition(&rights, renominate)?;
+        debug!("State transition: {}", transition);
+        let spending =
+            renomination_spending(&transition, renomination_right, renominate);
+
+        let inputs = bset![renomination_right.outpoint];
+        let other_transitions = self.blank_transitions(
+            &bset![renominate.contract_id],
+            &inputs,
+            &bmap! {},
+        )?;
+
         trace!("Requesting consignment from stash daemon");
-        let endpoints = transfer
-            .change
-            .keys()
-            .copied()
+        let endpoints = renominate
+            .renomination_seal
+            .into_iter()
             .map(SealEndpoint::from)
-            .chain(transfer.payment.keys().copied())
             .collect();
         let mut reply = self.consign(TransferRequest {
-            contract_id: transfer.contract_id,
-            inputs: transfer.inputs.clone(),
+            contract_id: renominate.contract_id,
+            inputs,
             transition,
             other_transitions,
             endpoints,
-            psbt: transfer.witness.clone(),
+            psbt: renominate.witness.clone(),
         })?;
 
-        // Concealing internal data
         if let Reply::Transfer(reply::Transfer {
             ref mut consignment,
             ..
         }) = reply
         {
-            let receivers = transfer.payment.keys().collect::<BTreeSet<_>>();
-            let expose = consignment
-                .endpoints
-                .iter()
-                .filter_map(|(_, endpoint)| {
-                    if receivers.contains(endpoint) {
-                        Some(*endpoint)
-                    } else {
-                        None
-                    }
-                })
-                .collect();
-            consignment.finalize(&expose, transfer.contract_id);
+            consignment.finalize(&bset![], renominate.contract_id);
+            // Asset is renamed only once the witness transaction is confirmed
+            self.cacher.add_spending(spending)?;
         }
 
         Ok(reply)
     }
 
+    fn rpc_transfer(
+        &mut self,
+        transfer: &TransferReq,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got TRANSFER {}", transfer);
+
+        // TODO #66: Check inputs that they really exist and have sufficient
+        //       amount of asset for the transfer operation
+
+        self.idempotent(
+            "transfer",
+            Request::Transfer(transfer.clone()),
+            transfer.idempotency_key,
+            |runtime| runtime.compose_transfer(&[transfer.clone()]),
+        )
+    }
+
+    fn rpc_batch_transfer(
+        &mut self,
+        transfers: &[TransferReq],
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got BATCH_TRANSFER of {} assets", transfers.len());
+        validate_batch(transfers)?;
+        // All transfers of a valid batch share the same idempotency key
+        self.idempotent(
+            "batch_transfer",
+            Request::BatchTransfer(transfers.to_vec()),
+            transfers[0].idempotency_key,
+            |runtime| runtime.compose_transfer(transfers),
+        )
+    }
+
+    /// Performs the request unless a request of the same type with the same
+    /// idempotency key was already performed, in which case the stored reply
+    /// is returned
+    fn idempotent(
+        &mut self,
+        name: &str,
+        request: Request,
+        key: Option<[u8; 32]>,
+        perform: impl FnOnce(&mut Self) -> Result<Reply, ServiceErrorDomain>,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        let key = match key {
+            Some(key) => key,
+            None => return perform(self),
+        };
+        if let Some(reply) = repeated_reply(
+            &self.cacher,
+            &self.reply_unmarshaller,
+            name,
+            &request,
+            key,
+        )? {
+            return Ok(reply);
+        }
+        let reply = perform(self)?;
+        remember_reply(&mut self.cacher, &request, key, &reply)?;
+        Ok(reply)
+    }
+
+    fn rpc_pending_transfers(&mut self) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got PENDING_TRANSFERS");
+        Ok(Reply::PendingTransfers(
+            self.cacher.pending_transfers().clone(),
+        ))
+    }
+
+    fn rpc_transfer_accepted(
+        &mut self,
+        seal: &SealEndpoint,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got TRANSFER_ACCEPTED");
+        if self.cacher.accept_pending_transfer(seal)? {
+            Ok(Reply::Success)
+        } else {
+            Ok(Reply::Nothing)
+        }
+    }
+
+    fn rpc_transfer_dry_run(
+        &mut self,
+        transfer: &TransferReq,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got TRANSFER DRY RUN {}", transfer);
+
+        let asset = self.asset(transfer.contract_id)?;
+        check_spendable(
+            asset,
+            self.cacher.watched_since(transfer.contract_id),
+        )?;
+        let (transfer, _) = allocate_change(asset, transfer);
+        let transition = self.transfer_transition(&transfer)?;
+        let preview = transfer_preview(asset, &transfer, &transition);
+        debug!(
+            "Transfer would spend {} and create {} known allocations",
+            preview.spent.len(),
+            preview.allocations.len()
+        );
+
+        Ok(Reply::TransferPreview(preview))
+    }
+
+    fn rpc_preview_transfer(
+        &mut self,
+        transfer: &TransferReq,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got PREVIEW TRANSFER {}", transfer);
+
+        let asset = self.asset(transfer.contract_id)?;
+        check_spendable(
+            asset,
+            self.cacher.watched_since(transfer.contract_id),
+        )?;
+        let (transfer, _) = allocate_change(asset, transfer);
+        let transition = self.transfer_transition(&transfer)?;
+        let delta = balance_delta(asset, &transfer, &transition);
+        debug!(
+            "Transfer would change balance of {} outpoint(s)",
+            delta.outpoints.len()
+        );
+
+        Ok(Reply::BalanceDelta(delta))
+    }
+
     fn rpc_validate(
         &mut self,
         consignment: &Consignment,
@@ -384,6 +650,54 @@ impl Runtime {
         Ok(self.forget(outpoint.clone())?)
     }
 
+    fn rpc_forget_asset(
+        &mut self,
+        forget: &ForgetAssetReq,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got FORGET_ASSET {}", forget);
+        check_forgettable(self.asset(forget.contract_id)?, forget.force)?;
+        self.cacher.remove_asset(forget.contract_id)?;
+        info!("Asset {} is forgotten", forget.contract_id);
+        Ok(Reply::Success)
+    }
+
+    fn rpc_set_label(
+        &mut self,
+        set_label: &SetLabelReq,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got SET_LABEL {}", set_label);
+        self.asset(set_label.contract_id)?;
+        self.cacher
+            .set_label(set_label.contract_id, set_label.label.clone())?;
+        Ok(Reply::Success)
+    }
+
+    fn rpc_label(
+        &mut self,
+        contract_id: ContractId,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got LABEL {}", contract_id);
+        self.asset(contract_id)?;
+        Ok(Reply::Label(self.cacher.label(contract_id).cloned()))
+    }
+
+    fn rpc_consignment_chunk(
+        &mut self,
+        seq: u32,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got CONSIGNMENT_CHUNK #{}", seq);
+        self.consignment_chunks
+            .get(seq as usize)
+            .cloned()
+            .map(Reply::ConsignmentChunk)
+            .ok_or_else(|| {
+                ServiceErrorDomain::Api(ApiErrorType::MalformedArgument {
+                    request: s!("consignment_chunk"),
+                    argument: s!("seq"),
+                })
+            })
+    }
+
     fn rpc_sync(
         &mut self,
         data_format: FileFormat,
@@ -393,6 +707,15 @@ impl Runtime {
         Ok(Reply::Sync(reply::SyncFormat(data_format, data)))
     }
 
+    fn rpc_list_assets(
+        &mut self,
+        list: &ListAssetsReq,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got LIST_ASSETS {}", list);
+        let assets = self.cacher.assets()?;
+        Ok(Reply::AssetPage(asset_page(assets, list)))
+    }
+
     fn rpc_outpoint_assets(
         &mut self,
         outpoint: OutPoint,
@@ -416,9 +739,43 @@ impl Runtime {
         genesis: &Genesis,
     ) -> Result<Reply, ServiceErrorDomain> {
         debug!("Got IMPORT_ASSET");
-        let asset = Asset::try_from(genesis.clone())?;
+        self.check_chain(genesis)?;
+        let known = self.known_asset(genesis.contract_id())?;
+        let (asset, already_known) = imported_asset(known, genesis)?;
+        let pending = pending_allocations(genesis);
+        if !pending.is_empty() {
+            info!(
+                "Genesis has {} allocation(s) with concealed seal or amount; \
+                they will become known once revealed",
+                pending.len()
+            );
+        }
         self.import_asset(asset.clone(), genesis.clone())?;
-        Ok(Reply::Asset(asset))
+        if already_known {
+            info!("Asset {} is already known", asset.id());
+            Ok(Reply::AlreadyKnown(asset))
+        } else {
+            if imported_watch_only(&asset) {
+                info!(
+                    "Asset {} has no known allocations and is imported as \
+                    watch-only",
+                    asset.id()
+                );
+                self.cacher
+                    .set_watched(*asset.id(), Some(Utc::now().naive_utc()))?;
+            }
+            Ok(Reply::Asset(asset))
+        }
+    }
+
+    fn rpc_clear_watch_only(
+        &mut self,
+        contract_id: ContractId,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got CLEAR_WATCH_ONLY {}", contract_id);
+        self.asset(contract_id)?;
+        self.cacher.set_watched(contract_id, None)?;
+        Ok(Reply::Success)
     }
 
     fn rpc_export_asset(
@@ -454,19 +811,202 @@ impl Runtime {
         &mut self,
         asset_id: ContractId,
     ) -> Result<Genesis, ServiceErrorDomain> {
+        if !self.cacher.has_asset(asset_id)? {
+            Err(ServiceErrorDomain::UnknownAsset(asset_id.to_string()))?
+        }
         match self.stash_req_rep(rpc::stash::Request::ReadGenesis(asset_id))? {
             Reply::Genesis(genesis) => Ok(genesis.clone()),
             _ => Err(ServiceErrorDomain::Api(ApiErrorType::UnexpectedReply)),
         }
     }
 
-    fn consign(
+    /// Composes state transitions for each of the transfers (which must not
+    /// be empty) and commits them to a single witness transaction. The
+    /// consignment, change outpoint and concealment in the reply are produced
+    /// for the first transfer; transitions of the rest, which must not have
+    /// payments (see [`validate_batch`]), are anchored alongside it and are
+    /// present in the disclosure.
+    fn compose_transfer(
         &mut self,
-        transfer_req: TransferRequest,
+        transfers: &[TransferReq],
     ) -> Result<Reply, ServiceErrorDomain> {
-        let reply =
-            self.stash_req_rep(rpc::stash::Request::Transfer(transfer_req))?;
-        if let Reply::Transfer(_) = reply {
+        trace!("Looking for asset information");
+        let inputs = transfers
+            .iter()
+            .flat_map(|transfer| transfer.inputs.iter().copied())
+            .collect::<BTreeSet<_>>();
+        let mut composed = Vec::with_capacity(transfers.len());
+        let mut asset_change_seal = None;
+        for transfer in transfers {
+            debug!("Transferring asset {}", transfer.contract_id);
+            let asset = self.asset(transfer.contract_id)?;
+            check_spendable(
+                asset,
+                self.cacher.watched_since(transfer.contract_id),
+            )?;
+            if asset.chain() != &self.config.network {
+                Err(ServiceErrorDomain::ChainMismatch(format!(
+                    "asset {} is issued on {} while the node uses {}",
+                    transfer.contract_id,
+                    asset.chain(),
+                    self.config.network
+                )))?
+            }
+            check_batch_inputs(asset, transfer, &inputs)?;
+            let (transfer, change_seal) = allocate_change(asset, transfer);
+            let transition = self.transfer_transition(&transfer)?;
+            if composed.is_empty() {
+                asset_change_seal = change_seal;
+            }
+            composed.push((transfer, transition));
+        }
+
+        let contract_ids = composed
+            .iter()
+            .map(|(transfer, _)| transfer.contract_id)
+            .collect();
+        let other_transitions = self.blank_transitions(
+            &contract_ids,
+            &inputs,
+            &composed[0].0.change,
+        )?;
+
+        trace!("Requesting consignment from stash daemon");
+        let mut reply =
+            self.consign(batch_request(&composed, other_transitions))?;
+        let transfer = &composed[0].0;
+
+        // Concealing internal data
+        if let Reply::Transfer(reply::Transfer {
+            ref mut consignment,
+            ref mut change_outpoint,
+            ref witness,
+            ..
+        }) = reply
+        {
+            let witness_txid = witness.global.unsigned_tx.txid();
+            *change_outpoint = asset_change_seal
+                .map(|seal| seal.outpoint_reveal(witness_txid).into());
+
+            let receivers = transfer.payment.keys().collect::<BTreeSet<_>>();
+            let expose = consignment
+                .endpoints
+                .iter()
+                .filter_map(|(_, endpoint)| {
+                    if receivers.contains(endpoint) {
+                        Some(*endpoint)
+                    } else {
+                        None
+                    }
+                })
+                .collect();
+            consignment.finalize(&expose, transfer.contract_id);
+
+            let now = Utc::now().naive_utc();
+            for (transfer, _) in &composed {
+                self.cacher
+                    .add_pending_transfers(pending_transfers(transfer, now))?;
+            }
+        }
+
+        Ok(reply)
+    }
+
+    fn transfer_transition(
+        &self,
+        transfer: &TransferReq,
+    ) -> Result<Transition, ServiceErrorDomain> {
+        trace!("Preparing state transition");
+        // Filtering inputs which do not have this assets: we will need them
+        // later, but not for constructing the main RGB20 transfer transition
+        let asset = self.asset(transfer.contract_id)?;
+        let inputs = transfer
+            .inputs
+            .iter()
+            .filter(|outpoint| !asset.allocations(**outpoint).is_empty())
+            .cloned()
+            .collect();
+        let transition = rgb20::transfer(
+            asset,
+            inputs,
+            transfer.payment.clone(),
+            transfer.change.clone(),
+        )?;
+        debug!("State transition: {}", transition);
+        Ok(transition)
+    }
+
+    /// Prepares blank state transitions moving assets of other contracts,
+    /// which are present on the spent outpoints, to the first change seal
+    fn blank_transitions(
+        &self,
+        contract_ids: &BTreeSet<ContractId>,
+        inputs: &BTreeSet<OutPoint>,
+        change: &BTreeMap<SealDefinition, AtomicValue>,
+    ) -> Result<BTreeMap<ContractId, Transition>, ServiceErrorDomain> {
+        trace!("Collecting other assets on the spent outpoints and preparing blank state transitions");
+        let mut other_outpoint_assets: BTreeMap<
+            ContractId,
+            BTreeSet<(OutPoint, AtomicValue)>,
+        > = bmap! {};
+        for outpoint in inputs {
+            for (other_contract_id, amounts) in
+                self.cacher.outpoint_assets(*outpoint)?
+            {
+                let sum = amounts.into_iter().sum();
+                // Ignoring native asset, transferred contracts and zero
+                // balances
+                if contract_ids.contains(&other_contract_id) || sum == 0 {
+                    continue;
+                }
+                other_outpoint_assets
+                    .entry(other_contract_id)
+                    .or_insert(empty!())
+                    .insert((*outpoint, sum));
+            }
+        }
+        debug!(
+            "Total {} other assets are found on the spent outpoints",
+            other_outpoint_assets.len()
+        );
+        trace!("{:?}", other_outpoint_assets);
+        let change_seal = if !other_outpoint_assets.is_empty() {
+            *change.keys().find(|_| true).ok_or(
+                ServiceErrorDomain::Internal(s!(
+                    "Other assets are present on the provided inputs, but no change address given"
+                ))
+            )?
+        } else {
+            SealDefinition::WitnessVout {
+                vout: 0,
+                blinding: 0,
+            } // Not used
+        };
+        let mut other_transitions = bmap! {};
+        for (other_contract, outpoints) in other_outpoint_assets {
+            other_transitions.insert(
+                other_contract,
+                rgb20::transfer(
+                    self.cacher.asset(other_contract)?,
+                    outpoints.iter().map(|(outpoint, _)| *outpoint).collect(),
+                    empty!(),
+                    outpoints
+                        .iter()
+                        .map(|(_, amount)| (change_seal, *amount))
+                        .collect(),
+                )?,
+            );
+        }
+        Ok(other_transitions)
+    }
+
+    fn consign(
+        &mut self,
+        transfer_req: TransferRequest,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        let reply =
+            self.stash_req_rep(rpc::stash::Request::Transfer(transfer_req))?;
+        if let Reply::Transfer(_) = reply {
             Ok(reply)
         } else {
             Err(ServiceErrorDomain::Api(ApiErrorType::UnexpectedReply))
@@ -477,45 +1017,60 @@ impl Runtime {
         &mut self,
         consignment: Consignment,
     ) -> Result<Reply, ServiceErrorDomain> {
-        let reply =
-            self.stash_req_rep(rpc::stash::Request::Validate(consignment))?;
-
-        match reply {
-            Reply::ValidationStatus(_) => Ok(reply),
-            _ => Err(ServiceErrorDomain::Api(ApiErrorType::UnexpectedReply)),
-        }
+        let status = self.validation_status(consignment.clone())?;
+        let known_asset =
+            self.known_asset(consignment.genesis.contract_id())?;
+        let (report, _) = consignment_report(
+            known_asset.as_ref(),
+            &consignment,
+            &[],
+            status,
+        )?;
+        Ok(Reply::ConsignmentReport(report))
     }
 
     fn accept(
         &mut self,
         accept: AcceptReq,
     ) -> Result<Reply, ServiceErrorDomain> {
+        self.check_chain(&accept.consignment.genesis)?;
+        let status = self.validation_status(accept.consignment.clone())?;
+        let known_asset =
+            self.known_asset(accept.consignment.genesis.contract_id())?;
+        let (report, asset) = consignment_report(
+            known_asset.as_ref(),
+            &accept.consignment,
+            &accept.reveal_outpoints,
+            status,
+        )?;
+        if report.status.validity() == validation::Validity::Invalid {
+            // Invalid consignments are never accepted, so the report does not
+            // list any state changes
+            return Ok(Reply::ConsignmentReport(reply::ConsignmentReport {
+                resolved_seals: vec![],
+                gained_allocations: bmap! {},
+                ..report
+            }));
+        }
+        asset.verify_allocations_within_supply()?;
+
         let reply =
             self.stash_req_rep(rpc::stash::Request::Accept(AcceptRequest {
-                consignment: accept.consignment.clone(),
-                reveal_outpoints: accept.reveal_outpoints.clone(),
+                consignment: accept.consignment,
+                reveal_outpoints: accept.reveal_outpoints,
             }))?;
         if let Reply::Success = reply {
-            let asset_id = accept.consignment.genesis.contract_id();
-            let asset = if self.cacher.has_asset(asset_id)? {
-                self.cacher.asset(asset_id)?.clone()
-            } else {
-                Asset::try_from(accept.consignment.genesis)?
-            };
             // NB: Previously we were adding endpoint-only data; but I think
             // this filtering is not necessary
             // TODO: This part is moved to RGB Core library, so replace it with
             //       consignment processing API from that library
-            self.update_asset(
-                asset,
-                accept
-                    .consignment
-                    .state_transitions
-                    .iter()
-                    .map(|(anchor, transition)| (transition, anchor.txid)),
-                &accept.reveal_outpoints,
-            )?;
-            Ok(reply)
+            self.cacher.add_asset(asset)?;
+            if !report.resolved_seals.is_empty() {
+                // Watch-only asset becomes spendable once the node receives
+                // some of its allocations
+                self.cacher.set_watched(report.contract_id, None)?;
+            }
+            Ok(Reply::ConsignmentReport(report))
         } else if let Reply::Failure(_) = &reply {
             Ok(reply)
         } else {
@@ -523,6 +1078,49 @@ impl Runtime {
         }
     }
 
+    fn validation_status(
+        &mut self,
+        consignment: Consignment,
+    ) -> Result<validation::Status, ServiceErrorDomain> {
+        match self.stash_req_rep(rpc::stash::Request::Validate(consignment))? {
+            Reply::ValidationStatus(status) => Ok(status),
+            _ => Err(ServiceErrorDomain::Api(ApiErrorType::UnexpectedReply)),
+        }
+    }
+
+    fn asset(
+        &self,
+        asset_id: ContractId,
+    ) -> Result<&Asset, ServiceErrorDomain> {
+        if !self.cacher.has_asset(asset_id)? {
+            Err(ServiceErrorDomain::UnknownAsset(asset_id.to_string()))?
+        }
+        Ok(self.cacher.asset(asset_id)?)
+    }
+
+    fn check_chain(&self, genesis: &Genesis) -> Result<(), ServiceErrorDomain> {
+        if genesis.chain() != &self.config.network {
+            Err(ServiceErrorDomain::ChainMismatch(format!(
+                "asset {} is issued on {} while the node uses {}",
+                genesis.contract_id(),
+                genesis.chain(),
+                self.config.network
+            )))?
+        }
+        Ok(())
+    }
+
+    fn known_asset(
+        &self,
+        asset_id: ContractId,
+    ) -> Result<Option<Asset>, ServiceErrorDomain> {
+        Ok(if self.cacher.has_asset(asset_id)? {
+            Some(self.cacher.asset(asset_id)?.clone())
+        } else {
+            None
+        })
+    }
+
     fn enclose(
         &mut self,
         disclosure: Disclosure,
@@ -565,21 +1163,20 @@ impl Runtime {
         outpoint: OutPoint,
     ) -> Result<Reply, ServiceErrorDomain> {
         let mut removal_list = Vec::<_>::new();
+        for spending in self.cacher.take_spendings(outpoint)? {
+            for allocation in self.confirm_spending(&spending)? {
+                removal_list.push((*allocation.node_id(), *allocation.index()));
+            }
+        }
         let assets = self
             .cacher
-            .assets()?
+            .assets_affected_by_spend(outpoint)?
             .into_iter()
-            .map(Clone::clone)
-            .collect::<Vec<_>>();
+            .map(|id| self.cacher.asset(id).map(Clone::clone))
+            .collect::<Result<Vec<_>, _>>()?;
         for asset in assets {
             let mut asset = asset.clone();
-            for allocation in asset.clone().allocations(outpoint) {
-                asset.remove_allocation(
-                    outpoint,
-                    *allocation.node_id(),
-                    *allocation.index(),
-                    allocation.revealed_amount().clone(),
-                );
+            for allocation in asset.on_outpoint_spent(&outpoint) {
                 removal_list.push((*allocation.node_id(), *allocation.index()));
             }
             self.cacher.add_asset(asset)?;
@@ -597,57 +1194,43 @@ impl Runtime {
         }
     }
 
+    /// Applies state transition composed by the node, which witness
+    /// transaction is confirmed, to the asset data and the asset rights.
+    /// Returns allocations removed by the transition.
+    fn confirm_spending(
+        &mut self,
+        spending: &PendingSpending,
+    ) -> Result<Vec<Allocation>, ServiceErrorDomain> {
+        debug!("Witness of {} is confirmed", spending);
+        let mut asset = self.asset(spending.contract_id)?.clone();
+        let mut rights = self.asset_rights(spending.contract_id)?;
+        let removed = apply_spending(&mut asset, &mut rights, spending);
+        self.cacher.set_rights(spending.contract_id, rights)?;
+        self.cacher.add_asset(asset)?;
+        Ok(removed)
+    }
+
+    /// Returns current asset rights: the rights assigned by the genesis,
+    /// updated by the confirmed state transitions composed by the node
+    fn asset_rights(
+        &mut self,
+        contract_id: ContractId,
+    ) -> Result<Vec<OwnedRight>, ServiceErrorDomain> {
+        match self.cacher.rights(contract_id) {
+            Some(rights) => Ok(rights.clone()),
+            None => Ok(genesis_rights(&self.export_asset(contract_id)?)),
+        }
+    }
+
     fn update_asset<'a>(
         &mut self,
         mut asset: Asset,
         data: impl IntoIterator<Item = (&'a Transition, Txid)>,
         reveal_outpoints: &'a Vec<OutpointReveal>,
     ) -> Result<(), ServiceErrorDomain> {
-        for (transition, txid) in data.into_iter() {
-            let assignments = if let Some(assignments) =
-                transition.owned_rights_by_type(*OwnedRightsType::Assets)
-            {
-                assignments
-            } else {
-                continue;
-            };
-
-            // TODO: Move all of the logic to RGB20 Lib by implementing
-            //       allocations parsing from consignment, and before that
-            //       revealing known consignment information with separate
-            //       routine
-            for (index, state) in
-                assignments.to_discrete_state().into_iter().enumerate()
-            {
-                let seal_confidential = state.seal_definition_confidential();
-                let seal_revealed = if let Some(seal_revealed) =
-                    state.seal_definition().or_else(|| {
-                        reveal_outpoints
-                            .iter()
-                            .find(|reveal| {
-                                reveal.commit_conceal() == seal_confidential
-                            })
-                            .copied()
-                            .map(SealDefinition::from)
-                    }) {
-                    seal_revealed
-                } else {
-                    continue;
-                };
-
-                if let Some(state_data) = state.assigned_state() {
-                    asset.add_allocation(
-                        seal_revealed.outpoint_reveal(txid).into(),
-                        transition.node_id(),
-                        index as u16,
-                        *state_data,
-                    );
-                }
-            }
-        }
-
+        apply_transitions(&mut asset, data, reveal_outpoints);
+        asset.verify_allocations_within_supply()?;
         self.cacher.add_asset(asset)?;
-
         Ok(())
     }
 
@@ -672,9 +1255,2176 @@ impl Runtime {
     }
 }
 
-pub fn main_with_config(config: Config) -> Result<(), BootstrapError> {
-    let runtime = Runtime::init(config)?;
-    runtime.run_or_panic("Fungible contract runtime");
+/// Adds allocations created by the provided state transitions to the asset.
+/// Allocations assigned to concealed seals are added only if the seal can be
+/// revealed with `reveal_outpoints` data.
+fn apply_transitions<'a>(
+    asset: &mut Asset,
+    data: impl IntoIterator<Item = (&'a Transition, Txid)>,
+    reveal_outpoints: &'a [OutpointReveal],
+) {
+    for (transition, txid) in data.into_iter() {
+        let assignments = if let Some(assignments) =
+            transition.owned_rights_by_type(*OwnedRightsType::Assets)
+        {
+            assignments
+        } else {
+            continue;
+        };
 
-    unreachable!()
+        // TODO: Move all of the logic to RGB20 Lib by implementing
+        //       allocations parsing from consignment, and before that
+        //       revealing known consignment information with separate
+        //       routine
+        for (index, state) in
+            assignments.to_discrete_state().into_iter().enumerate()
+        {
+            let seal_confidential = state.seal_definition_confidential();
+            let seal_revealed = if let Some(seal_revealed) =
+                state.seal_definition().or_else(|| {
+                    reveal_outpoints
+                        .iter()
+                        .find(|reveal| {
+                            reveal.commit_conceal() == seal_confidential
+                        })
+                        .copied()
+                        .map(SealDefinition::from)
+                }) {
+                seal_revealed
+            } else {
+                continue;
+            };
+
+            if let Some(state_data) = state.assigned_state() {
+                if let AllocationInsertion::Conflict(known) = asset
+                    .insert_allocation(
+                        seal_revealed.outpoint_reveal(txid).into(),
+                        transition.node_id(),
+                        index as u16,
+                        *state_data,
+                    )
+                {
+                    warn!(
+                        "Allocation {}#{} conflicts with known allocation {}; \
+                        it is skipped",
+                        transition.node_id(),
+                        index,
+                        known
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Assigns the part of the input allocations which is not spent by the payment
+/// and explicitly provided change allocations to a change seal. The seal is
+/// defined by the requested change outpoint; if none was requested, the first
+/// explicit change seal is used, or a new seal on the first witness
+/// transaction output is created. Returns the completed transfer request
+/// together with the seal receiving the remaining assets, if any.
+fn allocate_change(
+    asset: &Asset,
+    transfer: &TransferReq,
+) -> (TransferReq, Option<SealDefinition>) {
+    let mut transfer = transfer.clone();
+    let available: AtomicValue = transfer
+        .inputs
+        .iter()
+        .flat_map(|outpoint| asset.allocations(*outpoint))
+        .map(|allocation| allocation.value())
+        .sum();
+    let remainder = match available.checked_sub(transfer.requested_amount()) {
+        Some(0) | None => return (transfer, None),
+        Some(remainder) => remainder,
+    };
+
+    let seal = match (transfer.change_outpoint, transfer.change.keys().next()) {
+        (Some(outpoint), _) => SealSpec::from(outpoint).seal_definition(),
+        (None, Some(seal)) => *seal,
+        (None, None) => SealSpec::with_vout(0).seal_definition(),
+    };
+    debug!("Allocating change of {} to {}", remainder, seal);
+    *transfer.change.entry(seal).or_insert(0) += remainder;
+    (transfer, Some(seal))
+}
+
+/// Checks that all allocations of the transferred asset on the inputs of the
+/// whole batch are spent by the transfer of this asset, since otherwise the
+/// witness transaction would leave them behind
+fn check_batch_inputs(
+    asset: &Asset,
+    transfer: &TransferReq,
+    inputs: &BTreeSet<OutPoint>,
+) -> Result<(), TransferError> {
+    match inputs.iter().find(|outpoint| {
+        !transfer.inputs.contains(outpoint)
+            && !asset.allocations(**outpoint).is_empty()
+    }) {
+        Some(outpoint) => Err(TransferError::ConflictingInput(*outpoint)),
+        None => Ok(()),
+    }
+}
+
+/// Merges state transitions of the batched transfers (which must not be
+/// empty) into a single request to the stash daemon, committing all of them
+/// to the witness transaction of the first transfer. Only the first transfer
+/// gets consigned; transitions of the rest, which may only allocate change,
+/// are anchored together with the blank transitions.
+fn batch_request(
+    transfers: &[(TransferReq, Transition)],
+    mut other_transitions: BTreeMap<ContractId, Transition>,
+) -> TransferRequest {
+    let (primary, transition) = &transfers[0];
+    for (transfer, transition) in &transfers[1..] {
+        other_transitions.insert(transfer.contract_id, transition.clone());
+    }
+    let endpoints = primary
+        .change
+        .keys()
+        .copied()
+        .map(SealEndpoint::from)
+        .chain(primary.payment.keys().copied())
+        .collect();
+    TransferRequest {
+        contract_id: primary.contract_id,
+        inputs: transfers
+            .iter()
+            .flat_map(|(transfer, _)| transfer.inputs.iter().copied())
+            .collect(),
+        transition: transition.clone(),
+        other_transitions,
+        endpoints,
+        psbt: primary.witness.clone(),
+    }
+}
+
+/// Constructs secondary issue state transition spending the inflation right
+/// controlled by the requested outpoint, which is looked up in the current
+/// asset rights. The capacity left after the issue is assigned to the
+/// requested inflation change seal, or to a new seal on the first witness
+/// transaction output. Returns the transition together with the spent
+/// inflation right and the seal receiving the remaining inflation right, if
+/// any.
+fn inflation_transition(
+    asset: &Asset,
+    rights: &[OwnedRight],
+    inflate: &InflateReq,
+) -> Result<(Transition, OwnedRight, Option<SealDefinition>), ServiceErrorDomain>
+{
+    let capacity = inflate.validate_capacity(asset.known_inflation())?;
+    let inflation_right = rights
+        .iter()
+        .find(|right| {
+            right.right_type == *OwnedRightsType::Inflation
+                && right.outpoint == inflate.inflation
+        })
+        .copied()
+        .ok_or(InflateError::UnknownInflation(inflate.inflation))?;
+
+    let mut owned_rights = bmap! {
+        *OwnedRightsType::Assets => Assignments::zero_balanced(
+            vec![value::Revealed {
+                value: inflate.amount,
+                blinding: secp256k1zkp::key::ONE_KEY.into(),
+            }],
+            inflate.allocation.clone(),
+            bmap! {},
+        )
+    };
+    let remaining = capacity - inflate.amount;
+    let inflation_change = if remaining > 0 {
+        let seal = inflate
+            .inflation_change
+            .unwrap_or_else(|| SealSpec::with_vout(0).seal_definition());
+        debug!("Assigning remaining inflation of {} to {}", remaining, seal);
+        owned_rights.insert(
+            *OwnedRightsType::Inflation,
+            Assignments::CustomData(vec![OwnedState::Revealed {
+                seal_definition: seal,
+                assigned_state: data::Revealed::U64(remaining),
+            }]),
+        );
+        Some(seal)
+    } else {
+        None
+    };
+
+    let metadata = bmap! {
+        *FieldType::IssuedSupply => bset![data::Revealed::U64(inflate.amount)]
+    };
+    let parent = bmap! {
+        inflation_right.node_id => bmap! {
+            *OwnedRightsType::Inflation => vec![inflation_right.index]
+        }
+    };
+    let transition = Transition::with(
+        *TransitionType::Issue,
+        metadata.into(),
+        parent.into(),
+        owned_rights.into(),
+        bset![].into(),
+    );
+    Ok((transition, inflation_right, inflation_change))
+}
+
+/// Lists asset rights assigned by the genesis to revealed outpoint seals
+fn genesis_rights(genesis: &Genesis) -> Vec<OwnedRight> {
+    let seals = |right_type| match genesis.owned_rights_by_type(right_type) {
+        Some(Assignments::Declarative(states)) => {
+            states.iter().map(|state| state.seal_definition()).collect()
+        }
+        Some(Assignments::CustomData(states)) => {
+            states.iter().map(|state| state.seal_definition()).collect()
+        }
+        _ => vec![],
+    };
+    [
+        *OwnedRightsType::Inflation,
+        *OwnedRightsType::BurnReplace,
+        *OwnedRightsType::Renomination,
+    ]
+    .iter()
+    .flat_map(|right_type| {
+        seals(*right_type).into_iter().enumerate().filter_map(
+            move |(index, seal)| match seal {
+                Some(SealDefinition::TxOutpoint(outpoint_reveal)) => {
+                    Some(OwnedRight {
+                        right_type: *right_type,
+                        node_id: genesis.node_id(),
+                        index: index as u16,
+                        outpoint: outpoint_reveal.into(),
+                    })
+                }
+                _ => None,
+            },
+        )
+    })
+    .collect()
+}
+
+/// Lists rights of the given type assigned by the state transition to the
+/// seals, which outpoints are resolved against the witness transaction
+fn assigned_rights(
+    transition: &Transition,
+    right_type: OwnedRightType,
+    seals: impl IntoIterator<Item = SealDefinition>,
+    witness_txid: Txid,
+) -> Vec<OwnedRight> {
+    seals
+        .into_iter()
+        .enumerate()
+        .map(|(index, seal)| OwnedRight {
+            right_type,
+            node_id: transition.node_id(),
+            index: index as u16,
+            outpoint: seal.outpoint_reveal(witness_txid).into(),
+        })
+        .collect()
+}
+
+/// Applies confirmed state transition composed by the node to the asset data
+/// and the asset rights: the spent right is replaced with the rights assigned
+/// by the transition, assigned inflation right becomes known inflation,
+/// burned assets are removed, decreasing the known circulating supply, and
+/// the asset gets the new ticker and name. Returns allocations removed by
+/// the transition.
+fn apply_spending(
+    asset: &mut Asset,
+    rights: &mut Vec<OwnedRight>,
+    spending: &PendingSpending,
+) -> Vec<Allocation> {
+    rights.retain(|right| *right != spending.spent);
+    rights.extend(spending.assigned.iter().copied());
+
+    let removed = spending
+        .burned
+        .iter()
+        .flat_map(|outpoint| asset.allocations(*outpoint))
+        .collect();
+    if !spending.burned.is_empty() {
+        let (burned_asset, burned) = asset.burn(&spending.burned);
+        info!("Burned {} of asset {}", burned, spending.contract_id);
+        *asset = burned_asset;
+    }
+
+    let mut known_inflation = asset.known_inflation().clone();
+    if let Some(capacity) = spending.inflation {
+        for right in &spending.assigned {
+            if right.right_type == *OwnedRightsType::Inflation {
+                known_inflation.insert(right.outpoint, capacity);
+            }
+        }
+    }
+    *asset = Asset::with(
+        asset.genesis().clone(),
+        *asset.id(),
+        spending
+            .ticker
+            .clone()
+            .unwrap_or_else(|| asset.ticker().clone()),
+        spending
+            .name
+            .clone()
+            .unwrap_or_else(|| asset.name().clone()),
+        asset.description().clone(),
+        *asset.supply(),
+        asset.chain().clone(),
+        *asset.decimal_precision(),
+        *asset.date(),
+        asset.known_issues().clone(),
+        known_inflation,
+        asset.known_allocations().clone(),
+    );
+    if spending.ticker.is_some() || spending.name.is_some() {
+        info!("Renamed asset {} to {}", spending.contract_id, asset);
+    }
+    removed
+}
+
+/// Constructs burn state transition removing assets allocated to the burned
+/// outpoints. The transition spends the current burn right of the asset,
+/// which is re-assigned to the requested proof seal, if any. Returns the
+/// transition together with the spent burn right.
+fn burn_transition(
+    asset: &Asset,
+    rights: &[OwnedRight],
+    burn: &BurnReq,
+) -> Result<(Transition, OwnedRight), ServiceErrorDomain> {
+    let burned =
+        burn.validate_allocations(&asset.known_allocations().iter().fold(
+            BTreeMap::<OutPoint, Vec<AtomicValue>>::new(),
+            |mut map, allocation| {
+                map.entry(*allocation.outpoint())
+                    .or_default()
+                    .push(allocation.value());
+                map
+            },
+        ))?;
+
+    let burn_right = rights
+        .iter()
+        .find(|right| right.right_type == *OwnedRightsType::BurnReplace)
+        .copied()
+        .ok_or(BurnError::NoBurnRight)?;
+
+    let mut metadata = bmap! {
+        *FieldType::BurnedSupply => bset![data::Revealed::U64(burned)],
+        *FieldType::HistoryProofFormat =>
+            bset![data::Revealed::U8(*HistoryProofFormat::ProofAbsent)]
+    };
+    metadata.insert(
+        *FieldType::BurnUtxo,
+        burn.outpoints
+            .iter()
+            .copied()
+            .map(data::Revealed::TxOutPoint)
+            .collect(),
+    );
+    let owned_rights = burn
+        .proof_seal
+        .map(|seal| {
+            bmap! {
+                *OwnedRightsType::BurnReplace =>
+                    Assignments::Declarative(vec![OwnedState::Revealed {
+                        seal_definition: seal,
+                        assigned_state: data::Void,
+                    }])
+            }
+        })
+        .unwrap_or_default();
+    let parent = bmap! {
+        burn_right.node_id => bmap! {
+            *OwnedRightsType::BurnReplace => vec![burn_right.index]
+        }
+    };
+    let transition = Transition::with(
+        *TransitionType::Burn,
+        metadata.into(),
+        parent.into(),
+        owned_rights.into(),
+        bset![].into(),
+    );
+    Ok((transition, burn_right))
+}
+
+/// Describes composed burn state transition, which is applied to the asset
+/// once the witness transaction is confirmed
+fn burn_spending(
+    transition: &Transition,
+    burn_right: OwnedRight,
+    burn: &BurnReq,
+) -> PendingSpending {
+    PendingSpending {
+        contract_id: burn.contract_id,
+        inputs: burn
+            .outpoints
+            .iter()
+            .copied()
+            .chain(Some(burn_right.outpoint))
+            .collect(),
+        spent: burn_right,
+        assigned: assigned_rights(
+            transition,
+            *OwnedRightsType::BurnReplace,
+            burn.proof_seal,
+            burn.witness.global.unsigned_tx.txid(),
+        ),
+        inflation: None,
+        burned: burn.outpoints.clone(),
+        ticker: None,
+        name: None,
+    }
+}
+
+/// Describes composed secondary issue state transition, which is applied to
+/// the asset once the witness transaction is confirmed. The remaining
+/// inflation capacity, if any, is assigned to the inflation change seal.
+fn inflation_spending(
+    asset: &Asset,
+    transition: &Transition,
+    inflation_right: OwnedRight,
+    inflation_change: Option<SealDefinition>,
+    inflate: &InflateReq,
+) -> PendingSpending {
+    let capacity = asset
+        .known_inflation()
+        .get(&inflation_right.outpoint)
+        .copied()
+        .unwrap_or_default();
+    PendingSpending {
+        contract_id: inflate.contract_id,
+        inputs: bset![inflation_right.outpoint],
+        spent: inflation_right,
+        assigned: assigned_rights(
+            transition,
+            *OwnedRightsType::Inflation,
+            inflation_change,
+            inflate.witness.global.unsigned_tx.txid(),
+        ),
+        inflation: inflation_change
+            .map(|_| capacity.saturating_sub(inflate.amount)),
+        burned: bset![],
+        ticker: None,
+        name: None,
+    }
+}
+
+/// Describes composed renomination state transition, which is applied to the
+/// asset once the witness transaction is confirmed
+fn renomination_spending(
+    transition: &Transition,
+    renomination_right: OwnedRight,
+    renominate: &RenominateReq,
+) -> PendingSpending {
+    PendingSpending {
+        contract_id: renominate.contract_id,
+        inputs: bset![renomination_right.outpoint],
+        spent: renomination_right,
+        assigned: assigned_rights(
+            transition,
+            *OwnedRightsType::Renomination,
+            renominate.renomination_seal,
+            renominate.witness.global.unsigned_tx.txid(),
+        ),
+        inflation: None,
+        burned: bset![],
+        ticker: renominate.ticker.clone(),
+        name: renominate.name.clone(),
+    }
+}
+
+/// Produces asset data for the imported genesis, returning also whether the
+/// asset was already known. Re-import of the known asset is idempotent: it
+/// must not lose allocations acquired through transfers, nor duplicate the
+/// known issues.
+fn imported_asset(
+    known: Option<Asset>,
+    genesis: &Genesis,
+) -> Result<(Asset, bool), ServiceErrorDomain> {
+    Ok(match known {
+        Some(mut asset) => {
+            asset.refresh_from_genesis(genesis)?;
+            (asset, true)
+        }
+        None => (Asset::from_genesis(genesis)?, false),
+    })
+}
+
+/// Checks whether the asset newly imported from its genesis can only be
+/// monitored: the genesis does not reveal any allocations the node may spend
+fn imported_watch_only(asset: &Asset) -> bool {
+    asset.known_allocations().is_empty()
+}
+
+/// Lists payments of the transfer as pending transfers created at the given
+/// time
+fn pending_transfers(
+    transfer: &TransferReq,
+    created_at: NaiveDateTime,
+) -> Vec<reply::PendingTransfer> {
+    transfer
+        .payment
+        .iter()
+        .map(|(seal, amount)| reply::PendingTransfer {
+            contract_id: transfer.contract_id,
+            amount: *amount,
+            seal: *seal,
+            created_at,
+        })
+        .collect()
+}
+
+/// Replaces the transfer reply which consignment is strict-encoded into more
+/// than `chunk_size` bytes with [`Reply::ChunkedTransfer`], returning the
+/// consignment chunks to be requested by the client. Other replies are
+/// returned as they are, with no chunks.
+fn chunk_transfer(
+    reply: Reply,
+    chunk_size: usize,
+) -> (Reply, Vec<reply::ConsignmentChunk>) {
+    match reply {
+        Reply::Transfer(transfer)
+            if transfer
+                .consignment
+                .strict_encode(io::sink())
+                .expect("Sink encoder does not fail")
+                > chunk_size =>
+        {
+            let (transfer, chunks) = transfer.into_chunks(chunk_size);
+            (Reply::ChunkedTransfer(transfer), chunks)
+        }
+        reply => (reply, vec![]),
+    }
+}
+
+/// Hash of the serialized request, distinguishing different requests sent
+/// with the same idempotency key
+fn request_hash(request: &Request) -> [u8; 32] {
+    sha256::Hash::hash(&request.serialize()).into_inner()
+}
+
+/// Looks up the reply to an already performed request of the same type with
+/// the same idempotency key. Fails if the key was used for a different
+/// request.
+fn repeated_reply(
+    cacher: &FileCache,
+    unmarshaller: &Unmarshaller<Reply>,
+    name: &str,
+    request: &Request,
+    key: [u8; 32],
+) -> Result<Option<Reply>, ServiceErrorDomain> {
+    let stored = match cacher.idempotent_reply(*request.get_type(), key) {
+        Some(stored) => stored,
+        None => return Ok(None),
+    };
+    if stored.request_hash != request_hash(request) {
+        error!("Idempotency key is reused for a different {} request", name);
+        return Err(ServiceErrorDomain::Api(ApiErrorType::MalformedArgument {
+            request: name.to_owned(),
+            argument: s!("idempotency_key"),
+        }));
+    }
+    debug!("Request with the same idempotency key was already performed");
+    Ok(Some((*unmarshaller.unmarshall(&stored.reply)?).clone()))
+}
+
+/// Stores reply to the request for returning it when the request with the
+/// same idempotency key is repeated. Failures are not stored, so the request
+/// is performed again on retry.
+fn remember_reply(
+    cacher: &mut FileCache,
+    request: &Request,
+    key: [u8; 32],
+    reply: &Reply,
+) -> Result<(), ServiceErrorDomain> {
+    if matches!(reply, Reply::Failure(_)) {
+        return Ok(());
+    }
+    cacher.add_idempotent_reply(IdempotentReply {
+        request_type: *request.get_type(),
+        key,
+        request_hash: request_hash(request),
+        reply: reply.serialize(),
+        created_at: Utc::now().naive_utc(),
+    })?;
+    Ok(())
+}
+
+/// Checks that the asset may be forgotten: assets with non-zero known balance
+/// are forgotten only if forced
+fn check_forgettable(
+    asset: &Asset,
+    force: bool,
+) -> Result<(), ServiceErrorDomain> {
+    let balance = asset.known_atomic_value();
+    if balance > 0 && !force {
+        return Err(ServiceErrorDomain::AssetHasBalance(format!(
+            "asset {} has balance of {} atomic units",
+            asset.id(),
+            balance
+        )));
+    }
+    Ok(())
+}
+
+/// Checks that the asset may be spent: watch-only assets, imported for
+/// monitoring only, have no allocations owned by the node
+fn check_spendable(
+    asset: &Asset,
+    watched_since: Option<NaiveDateTime>,
+) -> Result<(), ServiceErrorDomain> {
+    match watched_since {
+        Some(since) => Err(ServiceErrorDomain::WatchOnly(format!(
+            "asset {} is watch-only since {}",
+            asset.id(),
+            since
+        ))),
+        None => Ok(()),
+    }
+}
+
+fn renomination_transition(
+    rights: &[OwnedRight],
+    renominate: &RenominateReq,
+) -> Result<(Transition, OwnedRight), ServiceErrorDomain> {
+    let renomination_right = rights
+        .iter()
+        .find(|right| right.right_type == *OwnedRightsType::Renomination)
+        .copied()
+        .ok_or(RenominateError::NoRenominationRight)?;
+
+    let mut metadata = bmap! {};
+    if let Some(ticker) = &renominate.ticker {
+        metadata.insert(
+            *FieldType::Ticker,
+            bset![data::Revealed::String(ticker.clone())],
+        );
+    }
+    if let Some(name) = &renominate.name {
+        metadata.insert(
+            *FieldType::Name,
+            bset![data::Revealed::String(name.clone())],
+        );
+    }
+    let owned_rights = renominate
+        .renomination_seal
+        .map(|seal| {
+            bmap! {
+                *OwnedRightsType::Renomination =>
+                    Assignments::Declarative(vec![OwnedState::Revealed {
+                        seal_definition: seal,
+                        assigned_state: data::Void,
+                    }])
+            }
+        })
+        .unwrap_or_default();
+    let parent = bmap! {
+        renomination_right.node_id => bmap! {
+            *OwnedRightsType::Renomination => vec![renomination_right.index]
+        }
+    };
+    let transition = Transition::with(
+        *TransitionType::Renomination,
+        metadata.into(),
+        parent.into(),
+        owned_rights.into(),
+        bset![].into(),
+    );
+    Ok((transition, renomination_right))
+}
+
+/// Selects the requested page of the assets issued on the requested chain (or
+/// all assets, if no chain is given), ordering them by contract id, so the
+/// pages are stable across the requests
+fn asset_page(
+    mut assets: Vec<&Asset>,
+    list: &ListAssetsReq,
+) -> reply::AssetPage {
+    if let Some(chain) = &list.chain {
+        assets.retain(|asset| asset.chain() == chain);
+    }
+    assets.sort_by_key(|asset| *asset.id());
+    reply::AssetPage {
+        total: assets.len() as u32,
+        offset: list.offset,
+        assets: assets
+            .into_iter()
+            .skip(list.offset as usize)
+            .take(list.limit as usize)
+            .cloned()
+            .collect(),
+    }
+}
+
+/// Computes changes to the asset allocations which would be produced by the
+/// transfer state transition, without modifying the asset data
+fn transfer_preview(
+    asset: &Asset,
+    transfer: &TransferReq,
+    transition: &Transition,
+) -> reply::TransferPreview {
+    let spent = transfer
+        .inputs
+        .iter()
+        .flat_map(|outpoint| asset.allocations(*outpoint))
+        .collect();
+
+    let witness_txid = transfer.witness.global.unsigned_tx.txid();
+    let mut updated = asset.clone();
+    apply_transitions(&mut updated, vec![(transition, witness_txid)], &[]);
+    let allocations = updated
+        .known_allocations()
+        .iter()
+        .filter(|allocation| !asset.known_allocations().contains(allocation))
+        .copied()
+        .collect();
+
+    reply::TransferPreview {
+        contract_id: transfer.contract_id,
+        spent,
+        allocations,
+        payment: transfer.payment.values().sum(),
+        change: transfer.change.values().sum(),
+    }
+}
+
+/// Computes per-outpoint balances before and after the transfer state
+/// transition, spending the transfer inputs and adding the new allocations
+/// to a copy of the asset data
+fn balance_delta(
+    asset: &Asset,
+    transfer: &TransferReq,
+    transition: &Transition,
+) -> reply::BalanceDelta {
+    let witness_txid = transfer.witness.global.unsigned_tx.txid();
+    let mut updated = asset.clone();
+    for outpoint in &transfer.inputs {
+        updated.on_outpoint_spent(outpoint);
+    }
+    apply_transitions(&mut updated, vec![(transition, witness_txid)], &[]);
+
+    let balances = |asset: &Asset| {
+        let mut balances = BTreeMap::<OutPoint, AtomicValue>::new();
+        for allocation in asset.known_allocations() {
+            let balance = balances.entry(*allocation.outpoint()).or_default();
+            *balance = balance.saturating_add(allocation.value());
+        }
+        balances
+    };
+    let before = balances(asset);
+    let after = balances(&updated);
+    let outpoints = before
+        .keys()
+        .chain(after.keys())
+        .filter_map(|outpoint| {
+            let change = reply::BalanceChange {
+                before: before.get(outpoint).copied().unwrap_or_default(),
+                after: after.get(outpoint).copied().unwrap_or_default(),
+            };
+            if change.before == change.after {
+                None
+            } else {
+                Some((*outpoint, change))
+            }
+        })
+        .collect();
+
+    reply::BalanceDelta {
+        contract_id: transfer.contract_id,
+        outpoints,
+    }
+}
+
+/// Constructs report on the consignment validation, applying consignment
+/// state transitions to the known asset data (or asset data extracted from
+/// the consignment genesis, if the asset is not known yet). Returns the report
+/// together with the updated asset data.
+fn consignment_report(
+    known_asset: Option<&Asset>,
+    consignment: &Consignment,
+    reveal_outpoints: &[OutpointReveal],
+    status: validation::Status,
+) -> Result<(reply::ConsignmentReport, Asset), GenesisError> {
+    let contract_id = consignment.genesis.contract_id();
+    let mut asset = match known_asset {
+        Some(asset) => asset.clone(),
+        None => Asset::from_genesis(&consignment.genesis)?,
+    };
+    apply_transitions(
+        &mut asset,
+        consignment
+            .state_transitions
+            .iter()
+            .map(|(anchor, transition)| (transition, anchor.txid)),
+        reveal_outpoints,
+    );
+
+    let resolved_seals = reveal_outpoints
+        .iter()
+        .filter(|reveal| {
+            consignment.endpoints.iter().any(|(_, endpoint)| {
+                endpoint.commit_conceal() == reveal.commit_conceal()
+            })
+        })
+        .map(|reveal| OutPoint::from(*reveal))
+        .collect();
+
+    let validated_transitions = validated_transitions(consignment, &status);
+
+    let mut gained_allocations = BTreeMap::<OutPoint, Vec<AtomicValue>>::new();
+    for allocation in asset.known_allocations() {
+        let is_known = known_asset
+            .map(|known| known.known_allocations().contains(allocation))
+            .unwrap_or_default();
+        if !is_known {
+            gained_allocations
+                .entry(*allocation.outpoint())
+                .or_insert(default!())
+                .push(allocation.revealed_amount().value);
+        }
+    }
+
+    Ok((
+        reply::ConsignmentReport {
+            contract_id,
+            status,
+            validated_transitions,
+            resolved_seals,
+            gained_allocations,
+        },
+        asset,
+    ))
+}
+
+/// Lists consignment state transitions which are not concerned by any of the
+/// validation failures; returns nothing if some of the failures can't be
+/// attributed to specific nodes
+fn validated_transitions(
+    consignment: &Consignment,
+    status: &validation::Status,
+) -> Vec<NodeId> {
+    let mut failed = BTreeSet::new();
+    for failure in &status.failures {
+        if let validation::Failure::WitnessTransactionMissed(txid) = failure {
+            failed.extend(
+                consignment
+                    .state_transitions
+                    .iter()
+                    .filter(|(anchor, _)| anchor.txid == *txid)
+                    .map(|(_, transition)| transition.node_id()),
+            );
+        } else if let Some(node_id) = failed_node(failure) {
+            failed.insert(node_id);
+        } else {
+            return vec![];
+        }
+    }
+    consignment
+        .state_transitions
+        .iter()
+        .map(|(_, transition)| transition.node_id())
+        .filter(|node_id| !failed.contains(node_id))
+        .collect()
+}
+
+/// Returns id of the node concerned by the validation failure, if any
+fn failed_node(failure: &validation::Failure) -> Option<NodeId> {
+    use validation::Failure::*;
+    match failure {
+        SchemaUnknownExtensionType(node_id, _)
+        | SchemaUnknownTransitionType(node_id, _)
+        | SchemaUnknownFieldType(node_id, _)
+        | SchemaUnknownOwnedRightType(node_id, _)
+        | SchemaUnknownPublicRightType(node_id, _)
+        | SchemaDeniedScriptExtension(node_id)
+        | SchemaMetaOccurrencesError(node_id, ..)
+        | SchemaParentOwnedRightOccurrencesError(node_id, ..)
+        | SchemaOwnedRightOccurrencesError(node_id, ..)
+        | TransitionAbsent(node_id)
+        | TransitionNotAnchored(node_id)
+        | TransitionNotInAnchor(node_id, _)
+        | TransitionParentWrongSealType { node_id, .. }
+        | TransitionParentWrongSeal { node_id, .. }
+        | TransitionParentConfidentialSeal { node_id, .. }
+        | TransitionParentIsNotWitnessInput { node_id, .. }
+        | ExtensionAbsent(node_id)
+        | ExtensionParentWrongValenciesType { node_id, .. }
+        | WitnessNoCommitment(node_id, ..)
+        | EndpointTransitionNotFound(node_id)
+        | InvalidBulletproofs(node_id, ..)
+        | ScriptFailure(node_id, _) => Some(*node_id),
+        _ => None,
+    }
+}
+
+pub fn main_with_config(config: Config) -> Result<(), BootstrapError> {
+    let runtime = Runtime::init(config)?;
+    runtime.run_or_panic("Fungible contract runtime");
+
+    unreachable!()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bitcoin::hashes::hex::FromHex;
+    use bitcoin::util::psbt::PartiallySignedTransaction;
+    use lnpbp::chain::Chain;
+
+    fn outpoint(vout: u32) -> OutPoint {
+        OutPoint::new(
+            Txid::from_hex(
+                "db2f3035e05795d72e2744dc0e88b2f72acbed97ee9a54c2c7f52d426ae05627",
+            )
+            .unwrap(),
+            vout,
+        )
+    }
+
+    #[test]
+    fn test_consignment_report() {
+        let (asset, genesis) = rgb20::issue(
+            Chain::Testnet3,
+            s!("TST"),
+            s!("Test asset"),
+            None,
+            8,
+            vec![(outpoint(0), 1000), (outpoint(1), 500)],
+            bmap! {},
+            None,
+            None,
+        )
+        .unwrap();
+        let consignment = Consignment::with(genesis, vec![], vec![], vec![]);
+
+        let (report, updated) = consignment_report(
+            None,
+            &consignment,
+            &[],
+            validation::Status::default(),
+        )
+        .unwrap();
+        assert!(report.all_checks_passed());
+        assert_eq!(report.contract_id, *asset.id());
+        assert!(report.resolved_seals.is_empty());
+        assert_eq!(
+            report.gained_allocations,
+            bmap! {
+                outpoint(0) => vec![1000],
+                outpoint(1) => vec![500]
+            }
+        );
+        assert_eq!(updated.known_allocations(), asset.known_allocations());
+
+        // Accepting the same consignment once again gives nothing new
+        let (report, _) = consignment_report(
+            Some(&updated),
+            &consignment,
+            &[],
+            validation::Status::default(),
+        )
+        .unwrap();
+        assert!(report.all_checks_passed());
+        assert!(report.gained_allocations.is_empty());
+    }
+
+    #[test]
+    fn test_asset_page() {
+        let assets = (0..5u32)
+            .map(|vout| {
+                rgb20::issue(
+                    Chain::Testnet3,
+                    s!("TST"),
+                    s!("Test asset"),
+                    None,
+                    8,
+                    vec![(outpoint(vout), 1000)],
+                    bmap! {},
+                    None,
+                    None,
+                )
+                .unwrap()
+                .0
+            })
+            .collect::<Vec<_>>();
+        let mut sorted =
+            assets.iter().map(Asset::id).copied().collect::<Vec<_>>();
+        sorted.sort();
+
+        let page = |offset, limit| {
+            asset_page(
+                assets.iter().collect(),
+                &ListAssetsReq {
+                    offset,
+                    limit,
+                    chain: None,
+                },
+            )
+        };
+        let first = page(0, 3);
+        let second = page(3, 3);
+        assert_eq!((first.total, second.total), (5, 5));
+        assert_eq!((first.assets.len(), second.assets.len()), (3, 2));
+        let ids = first
+            .assets
+            .iter()
+            .chain(second.assets.iter())
+            .map(Asset::id)
+            .copied()
+            .collect::<Vec<_>>();
+        assert_eq!(ids, sorted);
+        assert!(page(5, 3).assets.is_empty());
+    }
+
+    #[test]
+    fn test_asset_page_chain_filter() {
+        let assets = vec![Chain::Testnet3, Chain::Mainnet, Chain::Testnet3]
+            .into_iter()
+            .map(|chain| {
+                rgb20::issue(
+                    chain,
+                    s!("TST"),
+                    s!("Test asset"),
+                    None,
+                    8,
+                    vec![(outpoint(0), 1000)],
+                    bmap! {},
+                    None,
+                    None,
+                )
+                .unwrap()
+                .0
+            })
+            .collect::<Vec<_>>();
+        let page = |chain| {
+            asset_page(
+                assets.iter().collect(),
+                &ListAssetsReq {
+                    offset: 0,
+                    limit: 10,
+                    chain,
+                },
+            )
+        };
+
+        let testnet = page(Some(Chain::Testnet3));
+        assert_eq!(testnet.total, 2);
+        assert!(testnet
+            .assets
+            .iter()
+            .all(|asset| *asset.chain() == Chain::Testnet3));
+        let mainnet = page(Some(Chain::Mainnet));
+        assert_eq!(mainnet.total, 1);
+        assert_eq!(mainnet.assets[0].id(), assets[1].id());
+        assert!(page(Some(Chain::Signet)).assets.is_empty());
+        assert_eq!(page(None).total, 3);
+    }
+
+    #[test]
+    fn test_transfer_preview() {
+        let (asset, _) = rgb20::issue(
+            Chain::Testnet3,
+            s!("TST"),
+            s!("Test asset"),
+            None,
+            8,
+            vec![(outpoint(0), 1000), (outpoint(1), 500)],
+            bmap! {},
+            None,
+            None,
+        )
+        .unwrap();
+        let witness = PartiallySignedTransaction::from_unsigned_tx(
+            bitcoin::Transaction {
+                version: 2,
+                lock_time: 0,
+                input: vec![],
+                output: vec![],
+            },
+        )
+        .unwrap();
+        let witness_txid = witness.global.unsigned_tx.txid();
+        let transfer = TransferReq {
+            contract_id: *asset.id(),
+            witness,
+            inputs: bset![outpoint(0)],
+            payment: bmap! {
+                SealEndpoint::TxOutpoint(
+                    OutpointReveal::from(outpoint(5)).commit_conceal()
+                ) => 300
+            },
+            change: bmap! {
+                SealDefinition::WitnessVout { vout: 1, blinding: 0 } => 700
+            },
+            change_outpoint: None,
+            idempotency_key: None,
+        };
+        let transition = rgb20::transfer(
+            &asset,
+            transfer.inputs.clone(),
+            transfer.payment.clone(),
+            transfer.change.clone(),
+        )
+        .unwrap();
+
+        let before = asset.clone();
+        let preview = transfer_preview(&asset, &transfer, &transition);
+        assert_eq!(asset, before);
+
+        assert_eq!(preview.contract_id, *asset.id());
+        assert_eq!(preview.payment, 300);
+        assert_eq!(preview.change, 700);
+        assert_eq!(preview.spent, asset.allocations(outpoint(0)));
+        assert_eq!(preview.allocations.len(), 1);
+        assert_eq!(
+            *preview.allocations[0].outpoint(),
+            OutPoint::new(witness_txid, 1)
+        );
+        assert_eq!(preview.allocations[0].value(), 700);
+    }
+
+    #[test]
+    fn test_balance_delta() {
+        let (asset, _) = rgb20::issue(
+            Chain::Testnet3,
+            s!("TST"),
+            s!("Test asset"),
+            None,
+            8,
+            vec![(outpoint(0), 1000), (outpoint(1), 500)],
+            bmap! {},
+            None,
+            None,
+        )
+        .unwrap();
+        let witness = PartiallySignedTransaction::from_unsigned_tx(
+            bitcoin::Transaction {
+                version: 2,
+                lock_time: 0,
+                input: vec![],
+                output: vec![],
+            },
+        )
+        .unwrap();
+        let transfer = TransferReq {
+            contract_id: *asset.id(),
+            witness,
+            inputs: bset![outpoint(0)],
+            payment: bmap! {
+                SealEndpoint::TxOutpoint(
+                    OutpointReveal::from(outpoint(6)).commit_conceal()
+                ) => 300
+            },
+            change: bmap! {
+                SealDefinition::TxOutpoint(OutpointReveal::from(outpoint(5)))
+                    => 700
+            },
+            change_outpoint: None,
+            idempotency_key: None,
+        };
+        let transition = rgb20::transfer(
+            &asset,
+            transfer.inputs.clone(),
+            transfer.payment.clone(),
+            transfer.change.clone(),
+        )
+        .unwrap();
+
+        let before = asset.clone();
+        let delta = balance_delta(&asset, &transfer, &transition);
+        assert_eq!(asset, before);
+
+        assert_eq!(delta.contract_id, *asset.id());
+        assert_eq!(
+            delta.outpoints,
+            bmap! {
+                outpoint(0) => reply::BalanceChange { before: 1000, after: 0 },
+                outpoint(5) => reply::BalanceChange { before: 0, after: 700 }
+            }
+        );
+    }
+
+    #[test]
+    fn test_inflation_transition() {
+        let (asset, genesis) = rgb20::issue(
+            Chain::Testnet3,
+            s!("TST"),
+            s!("Test asset"),
+            None,
+            8,
+            vec![(outpoint(0), 1000)],
+            bmap! { outpoint(2) => 10000 },
+            None,
+            None,
+        )
+        .unwrap();
+        let rights = genesis_rights(&genesis);
+        let issue_seal = SealDefinition::TxOutpoint(outpoint(5).into());
+        let change_seal = SealDefinition::TxOutpoint(outpoint(6).into());
+        let mut inflate = InflateReq {
+            contract_id: *asset.id(),
+            witness: PartiallySignedTransaction::from_unsigned_tx(
+                bitcoin::Transaction {
+                    version: 2,
+                    lock_time: 0,
+                    input: vec![],
+                    output: vec![],
+                },
+            )
+            .unwrap(),
+            inflation: outpoint(2),
+            amount: 4000,
+            allocation: bmap! { issue_seal => 4000 },
+            inflation_change: Some(change_seal),
+        };
+        inflate.validate().unwrap();
+
+        let (transition, inflation_right, inflation_change) =
+            inflation_transition(&asset, &rights, &inflate).unwrap();
+        assert_eq!(inflation_right.node_id, genesis.node_id());
+        assert_eq!(transition.transition_type(), *TransitionType::Issue);
+        assert_eq!(inflation_change, Some(change_seal));
+        match transition.owned_rights_by_type(*OwnedRightsType::Inflation) {
+            Some(Assignments::CustomData(states)) => {
+                assert_eq!(states.len(), 1);
+                assert_eq!(states[0].seal_definition(), Some(change_seal));
+                assert_eq!(
+                    states[0].assigned_state(),
+                    Some(&data::Revealed::U64(6000))
+                );
+            }
+            _ => panic!("remaining inflation right must be reassigned"),
+        }
+
+        let mut updated = asset.clone();
+        apply_transitions(
+            &mut updated,
+            vec![(&transition, Txid::default())],
+            &[],
+        );
+        let issued = updated.allocations(outpoint(5));
+        assert_eq!(issued.len(), 1);
+        assert_eq!(issued[0].value(), 4000);
+
+        // Once confirmed, the remaining inflation right can be spent
+        let spending = inflation_spending(
+            &asset,
+            &transition,
+            inflation_right,
+            inflation_change,
+            &inflate,
+        );
+        let mut inflated = asset.clone();
+        let mut inflated_rights = rights.clone();
+        apply_spending(&mut inflated, &mut inflated_rights, &spending);
+        assert_eq!(inflated.known_inflation().get(&outpoint(6)), Some(&6000));
+        let mut next = inflate.clone();
+        next.inflation = outpoint(6);
+        next.amount = 6000;
+        next.allocation = bmap! { issue_seal => 6000 };
+        next.inflation_change = None;
+        let (_, next_right, next_change) =
+            inflation_transition(&inflated, &inflated_rights, &next).unwrap();
+        assert_eq!(next_right.node_id, transition.node_id());
+        assert_eq!(next_change, None);
+        // The spent right can't be spent again
+        match inflation_transition(&inflated, &inflated_rights, &inflate) {
+            Err(ServiceErrorDomain::InsufficientFunds(_)) => {}
+            _ => panic!("inflation right is already spent"),
+        }
+
+        // Spending the whole capacity leaves no inflation right
+        inflate.amount = 10000;
+        inflate.allocation = bmap! { issue_seal => 10000 };
+        let (transition, _, inflation_change) =
+            inflation_transition(&asset, &rights, &inflate).unwrap();
+        assert_eq!(inflation_change, None);
+        assert!(transition
+            .owned_rights_by_type(*OwnedRightsType::Inflation)
+            .is_none());
+
+        // Issuing more than the inflation right allows is rejected
+        inflate.amount = 10001;
+        inflate.allocation = bmap! { issue_seal => 10001 };
+        inflate.validate().unwrap();
+        assert_eq!(
+            inflate.validate_capacity(asset.known_inflation()),
+            Err(InflateError::ExceedsCapacity {
+                requested: 10001,
+                capacity: 10000
+            })
+        );
+        match inflation_transition(&asset, &rights, &inflate) {
+            Err(ServiceErrorDomain::InsufficientFunds(_)) => {}
+            _ => panic!("over-cap inflation must be rejected"),
+        }
+
+        // Outpoints without inflation right can't be spent for the issue
+        inflate.amount = 100;
+        inflate.allocation = bmap! { issue_seal => 100 };
+        inflate.inflation = outpoint(0);
+        match inflation_transition(&asset, &rights, &inflate) {
+            Err(ServiceErrorDomain::InsufficientFunds(_)) => {}
+            _ => panic!("inflation right on the outpoint is unknown"),
+        }
+    }
+
+    #[test]
+    fn test_burn_transition() {
+        let issue = |epoch| {
+            rgb20::issue(
+                Chain::Testnet3,
+                s!("TST"),
+                s!("Test asset"),
+                None,
+                8,
+                vec![(outpoint(0), 1000), (outpoint(1), 500)],
+                bmap! {},
+                None,
+                epoch,
+            )
+            .unwrap()
+        };
+        let witness = |inputs: &[OutPoint]| {
+            PartiallySignedTransaction::from_unsigned_tx(bitcoin::Transaction {
+                version: 2,
+                lock_time: 0,
+                input: inputs
+                    .iter()
+                    .map(|outpoint| bitcoin::TxIn {
+                        previous_output: *outpoint,
+                        script_sig: default!(),
+                        sequence: 0,
+                        witness: vec![],
+                    })
+                    .collect(),
+                output: vec![],
+            })
+            .unwrap()
+        };
+        let (asset, genesis) = issue(Some(outpoint(3)));
+        let rights = genesis_rights(&genesis);
+        let proof_seal = SealDefinition::TxOutpoint(outpoint(6).into());
+        let mut burn = BurnReq {
+            contract_id: *asset.id(),
+            witness: witness(&[outpoint(1), outpoint(3)]),
+            outpoints: bset![outpoint(1)],
+            proof_seal: Some(proof_seal),
+        };
+        burn.validate().unwrap();
+
+        let (transition, burn_right) =
+            burn_transition(&asset, &rights, &burn).unwrap();
+        assert_eq!(burn_right.outpoint, outpoint(3));
+        assert_eq!(burn_right.node_id, genesis.node_id());
+        assert_eq!(transition.transition_type(), *TransitionType::Burn);
+        assert_eq!(
+            transition.metadata().u64(*FieldType::BurnedSupply),
+            vec![500]
+        );
+        match transition.owned_rights_by_type(*OwnedRightsType::BurnReplace) {
+            Some(Assignments::Declarative(states)) => {
+                assert_eq!(states.len(), 1);
+                assert_eq!(states[0].seal_definition(), Some(proof_seal));
+            }
+            _ => panic!("burn right must be re-assigned to the proof seal"),
+        }
+
+        // Composed burn is applied to the asset only once confirmed
+        let spending = burn_spending(&transition, burn_right, &burn);
+        assert_eq!(spending.inputs, bset![outpoint(1), outpoint(3)]);
+        let mut burned_asset = asset.clone();
+        let mut burned_rights = rights.clone();
+        let removed =
+            apply_spending(&mut burned_asset, &mut burned_rights, &spending);
+        assert_eq!(removed, asset.allocations(outpoint(1)));
+        assert_eq!(burned_asset.known_atomic_value(), 1000);
+        assert_eq!(*burned_asset.supply().known_circulating(), 1000);
+
+        // Next burn spends the burn right re-assigned to the proof seal
+        burn.outpoints = bset![outpoint(0)];
+        burn.witness = witness(&[outpoint(0), outpoint(6)]);
+        burn.proof_seal = None;
+        let (transition, burn_right) =
+            burn_transition(&burned_asset, &burned_rights, &burn).unwrap();
+        assert_eq!(burn_right.outpoint, outpoint(6));
+        assert_eq!(burn_right.node_id, spending.assigned[0].node_id);
+        assert_eq!(spending.spent.node_id, genesis.node_id());
+        let spending = burn_spending(&transition, burn_right, &burn);
+        apply_spending(&mut burned_asset, &mut burned_rights, &spending);
+        assert_eq!(*burned_asset.supply().known_circulating(), 0);
+        // The burn right is spent without proof seal, so no more burns are
+        // possible
+        match burn_transition(&asset, &burned_rights, &burn) {
+            Err(ServiceErrorDomain::Schema(_)) => {}
+            _ => panic!("burn right is already spent"),
+        }
+
+        // Witness must spend the burned outpoints
+        burn.witness = witness(&[outpoint(6)]);
+        assert_eq!(
+            burn.validate(),
+            Err(BurnError::UnspentOutpoint(outpoint(0)))
+        );
+
+        // Burning outpoint without assets is rejected
+        burn.outpoints = bset![outpoint(5)];
+        match burn_transition(&asset, &rights, &burn) {
+            Err(ServiceErrorDomain::InsufficientFunds(_)) => {}
+            _ => panic!("outpoint without assets can't be burned"),
+        }
+
+        // Assets without burn right can't be burned
+        let (asset, genesis) = issue(None);
+        burn.outpoints = bset![outpoint(1)];
+        match burn_transition(&asset, &genesis_rights(&genesis), &burn) {
+            Err(ServiceErrorDomain::Schema(_)) => {}
+            _ => panic!("asset has no burn right"),
+        }
+    }
+
+    #[test]
+    fn test_duplicate_import() {
+        let genesis = rgb20::issue(
+            Chain::Testnet3,
+            s!("TST"),
+            s!("Test asset"),
+            None,
+            8,
+            vec![(outpoint(0), 1000)],
+            bmap! { outpoint(2) => 1000 },
+            None,
+            None,
+        )
+        .unwrap()
+        .1;
+
+        let (asset, already_known) = imported_asset(None, &genesis).unwrap();
+        assert!(!already_known);
+        assert_eq!(asset.known_issues().len(), 1);
+
+        let (reimported, already_known) =
+            imported_asset(Some(asset.clone()), &genesis).unwrap();
+        assert!(already_known);
+        assert_eq!(reimported.known_issues(), asset.known_issues());
+        assert_eq!(reimported.known_allocations(), asset.known_allocations());
+    }
+
+    #[test]
+    fn test_imported_watch_only() {
+        let issue = |allocations| {
+            rgb20::issue(
+                Chain::Testnet3,
+                s!("TST"),
+                s!("Test asset"),
+                None,
+                8,
+                allocations,
+                bmap! { outpoint(2) => 1000 },
+                None,
+                None,
+            )
+            .unwrap()
+            .1
+        };
+        let watched_since = |asset: &Asset| {
+            if imported_watch_only(asset) {
+                Some(Utc::now().naive_utc())
+            } else {
+                None
+            }
+        };
+
+        // Asset issued on another node with allocations known to this one
+        // remains transferable after import
+        let (asset, _) =
+            imported_asset(None, &issue(vec![(outpoint(0), 1000)])).unwrap();
+        assert!(!imported_watch_only(&asset));
+        assert_eq!(check_spendable(&asset, watched_since(&asset)), Ok(()));
+
+        let (asset, _) = imported_asset(None, &issue(vec![])).unwrap();
+        assert!(imported_watch_only(&asset));
+        match check_spendable(&asset, watched_since(&asset)) {
+            Err(ServiceErrorDomain::WatchOnly(_)) => {}
+            _ => panic!("watch-only asset must not be transferred"),
+        }
+        // Clearing the mark makes the asset transferable
+        assert_eq!(check_spendable(&asset, None), Ok(()));
+    }
+
+    #[test]
+    fn test_pending_transfers() {
+        let seal1 = SealEndpoint::TxOutpoint(
+            OutpointReveal::from(outpoint(3)).commit_conceal(),
+        );
+        let seal2 = SealEndpoint::WitnessVout {
+            vout: 1,
+            blinding: 1,
+        };
+        let transfer = TransferReq {
+            contract_id: ContractId::default(),
+            witness: PartiallySignedTransaction::from_unsigned_tx(
+                bitcoin::Transaction {
+                    version: 2,
+                    lock_time: 0,
+                    input: vec![],
+                    output: vec![],
+                },
+            )
+            .unwrap(),
+            inputs: bset![outpoint(0)],
+            payment: bmap! { seal1 => 400, seal2 => 100 },
+            change: bmap! {},
+            change_outpoint: None,
+            idempotency_key: None,
+        };
+        let created_at = NaiveDateTime::from_timestamp(1_600_000_000, 0);
+
+        let pending = pending_transfers(&transfer, created_at);
+        assert_eq!(pending.len(), 2);
+        let transfer1 = pending
+            .iter()
+            .find(|pending| pending.seal == seal1)
+            .unwrap();
+        assert_eq!(transfer1.contract_id, ContractId::default());
+        assert_eq!(transfer1.amount, 400);
+        assert_eq!(transfer1.created_at, created_at);
+    }
+
+    #[test]
+    fn test_idempotent_replies() {
+        let config = FileCacheConfig {
+            data_dir: std::env::temp_dir().join(format!(
+                "rgb-node-test-idempotent-{}",
+                std::process::id()
+            )),
+            data_format: FileFormat::StrictEncode,
+        };
+        let _ = std::fs::remove_dir_all(&config.data_dir);
+        let mut cacher = FileCache::new(config.clone()).unwrap();
+        let unmarshaller = Reply::create_unmarshaller();
+
+        let key = [1u8; 32];
+        let mut issue = IssueReq {
+            ticker: s!("TST"),
+            name: s!("Test asset"),
+            description: None,
+            precision: 8,
+            allocation: vec![],
+            inflation: vec![],
+            renomination: None,
+            epoch: None,
+            idempotency_key: Some(key),
+        };
+        let request = Request::Issue(issue.clone());
+        assert!(
+            repeated_reply(&cacher, &unmarshaller, "issue", &request, key)
+                .unwrap()
+                .is_none()
+        );
+
+        let (asset, _) = rgb20::issue(
+            Chain::Testnet3,
+            s!("TST"),
+            s!("Test asset"),
+            None,
+            8,
+            vec![(outpoint(0), 1000)],
+            bmap! {},
+            None,
+            None,
+        )
+        .unwrap();
+        remember_reply(
+            &mut cacher,
+            &request,
+            key,
+            &Reply::Asset(asset.clone()),
+        )
+        .unwrap();
+
+        // Replies survive the daemon restart
+        let cacher = FileCache::new(config.clone()).unwrap();
+        match repeated_reply(&cacher, &unmarshaller, "issue", &request, key) {
+            Ok(Some(Reply::Asset(repeated))) => assert_eq!(repeated, asset),
+            _ => panic!("repeated issue must reply with the issued asset"),
+        }
+
+        // The same key used for the request of the other type is unrelated
+        let transfer = Request::BatchTransfer(vec![]);
+        assert!(repeated_reply(
+            &cacher,
+            &unmarshaller,
+            "transfer",
+            &transfer,
+            key
+        )
+        .unwrap()
+        .is_none());
+
+        // The same key can't be used for a different request
+        issue.ticker = s!("OTHER");
+        let other = Request::Issue(issue);
+        assert!(matches!(
+            repeated_reply(&cacher, &unmarshaller, "issue", &other, key),
+            Err(ServiceErrorDomain::Api(
+                ApiErrorType::MalformedArgument { .. }
+            ))
+        ));
+
+        // Failures are not stored, so the request is retried
+        let mut cacher = cacher;
+        let failure = Reply::Failure(reply::Failure {
+            code: 0,
+            info: s!("failure"),
+        });
+        let key = [2u8; 32];
+        remember_reply(&mut cacher, &transfer, key, &failure).unwrap();
+        assert!(repeated_reply(
+            &cacher,
+            &unmarshaller,
+            "transfer",
+            &transfer,
+            key
+        )
+        .unwrap()
+        .is_none());
+
+        std::fs::remove_dir_all(config.data_dir).unwrap();
+    }
+
+    #[test]
+    fn test_check_forgettable() {
+        let issue = |allocations| {
+            rgb20::issue(
+                Chain::Testnet3,
+                s!("TST"),
+                s!("Test asset"),
+                None,
+                8,
+                allocations,
+                bmap! { outpoint(2) => 1000 },
+                None,
+                None,
+            )
+            .unwrap()
+            .0
+        };
+
+        let asset = issue(vec![(outpoint(0), 1000)]);
+        match check_forgettable(&asset, false) {
+            Err(ServiceErrorDomain::AssetHasBalance(_)) => {}
+            _ => panic!("asset with balance must not be forgotten"),
+        }
+        assert_eq!(check_forgettable(&asset, true), Ok(()));
+
+        let empty = issue(vec![]);
+        assert_eq!(check_forgettable(&empty, false), Ok(()));
+    }
+
+    #[test]
+    fn test_check_spendable() {
+        let asset = rgb20::issue(
+            Chain::Testnet3,
+            s!("TST"),
+            s!("Test asset"),
+            None,
+            8,
+            vec![(outpoint(0), 1000)],
+            bmap! {},
+            None,
+            None,
+        )
+        .unwrap()
+        .0;
+        assert_eq!(check_spendable(&asset, None), Ok(()));
+        match check_spendable(&asset, Some(Utc::now().naive_utc())) {
+            Err(ServiceErrorDomain::WatchOnly(_)) => {}
+            _ => panic!("watch-only asset must not be transferred"),
+        }
+    }
+
+    #[test]
+    fn test_renomination_transition() {
+        let issue = |renomination| {
+            rgb20::issue(
+                Chain::Testnet3,
+                s!("TST"),
+                s!("Test asset"),
+                None,
+                8,
+                vec![(outpoint(0), 1000)],
+                bmap! {},
+                renomination,
+                None,
+            )
+            .unwrap()
+        };
+        let (asset, genesis) = issue(Some(outpoint(3)));
+        let rights = genesis_rights(&genesis);
+        let renomination_seal = SealDefinition::TxOutpoint(outpoint(6).into());
+        let mut renominate = RenominateReq {
+            contract_id: genesis.contract_id(),
+            witness: PartiallySignedTransaction::from_unsigned_tx(
+                bitcoin::Transaction {
+                    version: 2,
+                    lock_time: 0,
+                    input: vec![],
+                    output: vec![],
+                },
+            )
+            .unwrap(),
+            ticker: Some(s!("NEW")),
+            name: None,
+            renomination_seal: Some(renomination_seal),
+        };
+
+        let (transition, renomination_right) =
+            renomination_transition(&rights, &renominate).unwrap();
+        assert_eq!(renomination_right.outpoint, outpoint(3));
+        assert_eq!(renomination_right.node_id, genesis.node_id());
+        assert_eq!(transition.transition_type(), *TransitionType::Renomination);
+        assert_eq!(
+            transition.metadata().string(*FieldType::Ticker),
+            vec![s!("NEW")]
+        );
+        assert!(transition.metadata().string(*FieldType::Name).is_empty());
+        match transition.owned_rights_by_type(*OwnedRightsType::Renomination) {
+            Some(Assignments::Declarative(states)) => {
+                assert_eq!(states.len(), 1);
+                assert_eq!(
+                    states[0].seal_definition(),
+                    Some(renomination_seal)
+                );
+            }
+            _ => panic!("renomination right must be re-assigned"),
+        }
+
+        // Composed renomination is applied to the asset only once confirmed
+        let spending =
+            renomination_spending(&transition, renomination_right, &renominate);
+        let mut renamed = asset.clone();
+        let mut renamed_rights = rights.clone();
+        assert!(apply_spending(&mut renamed, &mut renamed_rights, &spending)
+            .is_empty());
+        assert_eq!(renamed.ticker(), "NEW");
+        assert_eq!(renamed.name(), asset.name());
+        assert_eq!(renamed.known_allocations(), asset.known_allocations());
+
+        // Next renomination spends the right re-assigned by the previous one
+        renominate.name = Some(s!("New asset"));
+        renominate.renomination_seal = None;
+        let (next, next_right) =
+            renomination_transition(&renamed_rights, &renominate).unwrap();
+        assert_eq!(next_right.outpoint, outpoint(6));
+        assert_eq!(next_right.node_id, transition.node_id());
+        let spending = renomination_spending(&next, next_right, &renominate);
+        apply_spending(&mut renamed, &mut renamed_rights, &spending);
+        assert_eq!(renamed.name(), "New asset");
+        // The right is spent without a new seal, so no more renominations
+        // are possible
+        match renomination_transition(&renamed_rights, &renominate) {
+            Err(ServiceErrorDomain::Schema(_)) => {}
+            _ => panic!("renomination right is already spent"),
+        }
+
+        // Assets without renomination right can't be renamed
+        match renomination_transition(
+            &genesis_rights(&issue(None).1),
+            &renominate,
+        ) {
+            Err(ServiceErrorDomain::Schema(_)) => {}
+            _ => panic!("asset has no renomination right"),
+        }
+    }
+
+    #[test]
+    fn test_change_outpoint() {
+        let (asset, _) = rgb20::issue(
+            Chain::Testnet3,
+            s!("TST"),
+            s!("Test asset"),
+            None,
+            8,
+            vec![(outpoint(0), 1000), (outpoint(1), 500)],
+            bmap! {},
+            None,
+            None,
+        )
+        .unwrap();
+        let mut transfer = TransferReq {
+            contract_id: *asset.id(),
+            witness: PartiallySignedTransaction::from_unsigned_tx(
+                bitcoin::Transaction {
+                    version: 2,
+                    lock_time: 0,
+                    input: vec![],
+                    output: vec![],
+                },
+            )
+            .unwrap(),
+            inputs: bset![outpoint(0)],
+            payment: bmap! {
+                SealEndpoint::TxOutpoint(
+                    OutpointReveal::from(outpoint(5)).commit_conceal()
+                ) => 300
+            },
+            change: bmap! {},
+            change_outpoint: Some(outpoint(7)),
+            idempotency_key: None,
+        };
+
+        let (completed, seal) = allocate_change(&asset, &transfer);
+        let seal = seal.unwrap();
+        assert_eq!(
+            seal.outpoint_reveal(Txid::default()).txid,
+            outpoint(7).txid
+        );
+        assert_eq!(completed.change, bmap! { seal => 700 });
+
+        let transition = rgb20::transfer(
+            &asset,
+            completed.inputs.clone(),
+            completed.payment.clone(),
+            completed.change.clone(),
+        )
+        .unwrap();
+        let preview = transfer_preview(&asset, &completed, &transition);
+        assert_eq!(preview.change, 700);
+        assert_eq!(preview.allocations.len(), 1);
+        assert_eq!(*preview.allocations[0].outpoint(), outpoint(7));
+        assert_eq!(preview.allocations[0].value(), 700);
+
+        // Without the change outpoint the change goes to the witness output
+        transfer.change_outpoint = None;
+        let (completed, seal) = allocate_change(&asset, &transfer);
+        match seal {
+            Some(SealDefinition::WitnessVout { vout: 0, .. }) => {}
+            _ => panic!("change must be allocated to the witness output"),
+        }
+        assert_eq!(completed.requested_amount(), 1000);
+
+        // No change is required when the inputs are fully spent
+        transfer.payment = transfer
+            .payment
+            .into_iter()
+            .map(|(seal, _)| (seal, 1000))
+            .collect();
+        let (completed, seal) = allocate_change(&asset, &transfer);
+        assert_eq!(seal, None);
+        assert!(completed.change.is_empty());
+    }
+
+    #[test]
+    fn test_batch_request() {
+        let issue = |ticker: &str, outpoint: OutPoint| {
+            rgb20::issue(
+                Chain::Testnet3,
+                ticker.to_string(),
+                s!("Test asset"),
+                None,
+                8,
+                vec![(outpoint, 1000)],
+                bmap! {},
+                None,
+                None,
+            )
+            .unwrap()
+            .0
+        };
+        let first = issue("FST", outpoint(0));
+        let second = issue("SND", outpoint(1));
+        let witness = PartiallySignedTransaction::from_unsigned_tx(
+            bitcoin::Transaction {
+                version: 2,
+                lock_time: 0,
+                input: vec![],
+                output: vec![],
+            },
+        )
+        .unwrap();
+        let transfers = vec![
+            TransferReq {
+                contract_id: *first.id(),
+                witness: witness.clone(),
+                inputs: bset![outpoint(0)],
+                payment: bmap! {
+                    SealEndpoint::TxOutpoint(
+                        OutpointReveal::from(outpoint(5)).commit_conceal()
+                    ) => 1000
+                },
+                change: bmap! {},
+                change_outpoint: None,
+                idempotency_key: None,
+            },
+            TransferReq {
+                contract_id: *second.id(),
+                witness: witness.clone(),
+                inputs: bset![outpoint(1)],
+                payment: bmap! {},
+                change: bmap! {
+                    SealSpec::from(outpoint(6)).seal_definition() => 1000
+                },
+                change_outpoint: None,
+                idempotency_key: None,
+            },
+        ];
+        assert_eq!(validate_batch(&transfers), Ok(()));
+        let inputs = bset![outpoint(0), outpoint(1)];
+        let composed = vec![(&first, &transfers[0]), (&second, &transfers[1])]
+            .into_iter()
+            .map(|(asset, transfer)| {
+                assert_eq!(
+                    check_batch_inputs(asset, transfer, &inputs),
+                    Ok(())
+                );
+                let transition = rgb20::transfer(
+                    asset,
+                    transfer.inputs.clone(),
+                    transfer.payment.clone(),
+                    transfer.change.clone(),
+                )
+                .unwrap();
+                (transfer.clone(), transition)
+            })
+            .collect::<Vec<_>>();
+
+        let request = batch_request(&composed, bmap! {});
+        assert_eq!(request.contract_id, *first.id());
+        assert_eq!(request.psbt, witness);
+        assert_eq!(request.inputs, inputs);
+        assert_eq!(request.transition, composed[0].1);
+        assert_eq!(
+            request.other_transitions,
+            bmap! { *second.id() => composed[1].1.clone() }
+        );
+        assert_eq!(
+            request.endpoints,
+            transfers[0].payment.keys().copied().collect()
+        );
+
+        // Allocations of a batched asset must not be left on the inputs of
+        // the other transfers
+        let mut conflicting = transfers[1].clone();
+        conflicting.inputs = bset![outpoint(2)];
+        assert_eq!(
+            check_batch_inputs(
+                &second,
+                &conflicting,
+                &bset![outpoint(1), outpoint(2)]
+            ),
+            Err(TransferError::ConflictingInput(outpoint(1)))
+        );
+    }
+
+    #[test]
+    fn test_issue_transfer_accept() {
+        use amplify::DumbDefault;
+        use rgb::Anchor;
+
+        // Sender side: issuing asset and preparing transfer
+        let (asset, genesis) = rgb20::issue(
+            Chain::Testnet3,
+            s!("TST"),
+            s!("Test asset"),
+            None,
+            8,
+            vec![(outpoint(0), 1000), (outpoint(1), 500)],
+            bmap! {},
+            None,
+            None,
+        )
+        .unwrap();
+        let receiver = OutpointReveal::from(outpoint(9));
+        let payment = SealEndpoint::TxOutpoint(receiver.commit_conceal());
+        let transfer = TransferReq {
+            contract_id: *asset.id(),
+            witness: PartiallySignedTransaction::from_unsigned_tx(
+                bitcoin::Transaction {
+                    version: 2,
+                    lock_time: 0,
+                    input: vec![],
+                    output: vec![],
+                },
+            )
+            .unwrap(),
+            inputs: bset![outpoint(0)],
+            payment: bmap! { payment => 300 },
+            change: bmap! {},
+            change_outpoint: Some(outpoint(7)),
+            idempotency_key: None,
+        };
+        let (transfer, _) = allocate_change(&asset, &transfer);
+        let transition = rgb20::transfer(
+            &asset,
+            transfer.inputs.clone(),
+            transfer.payment.clone(),
+            transfer.change.clone(),
+        )
+        .unwrap();
+
+        // Consigning the transfer to the receiver
+        let mut anchor = Anchor::dumb_default();
+        anchor.txid = transfer.witness.global.unsigned_tx.txid();
+        let consignment = Consignment::with(
+            genesis,
+            vec![(transition.node_id(), payment)],
+            vec![(anchor, transition)],
+            vec![],
+        );
+
+        // Receiver side: accepting the consignment with the revealed seal
+        let (report, accepted) = consignment_report(
+            None,
+            &consignment,
+            &[receiver],
+            validation::Status::default(),
+        )
+        .unwrap();
+        assert!(report.all_checks_passed());
+        assert_eq!(report.contract_id, *asset.id());
+        assert_eq!(report.resolved_seals, vec![outpoint(9)]);
+        assert_eq!(
+            report.gained_allocations.get(&outpoint(9)),
+            Some(&vec![300])
+        );
+        assert_eq!(accepted.allocations(outpoint(9)).len(), 1);
+        assert_eq!(accepted.allocations(outpoint(9))[0].value(), 300);
+    }
+
+    #[test]
+    fn test_tampered_consignment_report() {
+        use amplify::DumbDefault;
+        use rgb::Anchor;
+
+        let (asset, genesis) = rgb20::issue(
+            Chain::Testnet3,
+            s!("TST"),
+            s!("Test asset"),
+            None,
+            8,
+            vec![(outpoint(0), 1000), (outpoint(1), 500)],
+            bmap! {},
+            None,
+            None,
+        )
+        .unwrap();
+        let mut transitions = vec![];
+        for (vout, value) in [(0, 1000), (1, 500)] {
+            let payment = SealEndpoint::TxOutpoint(
+                OutpointReveal::from(outpoint(vout + 8)).commit_conceal(),
+            );
+            let transition = rgb20::transfer(
+                &asset,
+                bset![outpoint(vout)],
+                bmap! { payment => value },
+                bmap! {},
+            )
+            .unwrap();
+            let mut anchor = Anchor::dumb_default();
+            anchor.txid = outpoint(vout + 4).txid;
+            transitions.push((anchor, transition));
+        }
+        let valid_id = transitions[0].1.node_id();
+
+        // Second transition is anchored to a witness transaction which does
+        // not exist
+        let tampered_txid = Txid::from_hex(
+            "fc63f797af718cc5a11988f69507701d5fe84e58cdd900e1b02856c0ea5a058a",
+        )
+        .unwrap();
+        transitions[1].0.txid = tampered_txid;
+        let consignment =
+            Consignment::with(genesis, vec![], transitions, vec![]);
+
+        let status = validation::Status::with_failure(
+            validation::Failure::WitnessTransactionMissed(tampered_txid),
+        );
+        let (report, _) =
+            consignment_report(None, &consignment, &[], status).unwrap();
+        assert!(!report.all_checks_passed());
+        assert_eq!(report.validated_transitions, vec![valid_id]);
+
+        let status = validation::Status::with_failure(
+            validation::Failure::ScriptFailure(valid_id, 0),
+        );
+        let (report, _) =
+            consignment_report(None, &consignment, &[], status).unwrap();
+        assert_eq!(report.validated_transitions.len(), 1);
+        assert_ne!(report.validated_transitions, vec![valid_id]);
+
+        // Failures of the whole consignment fail all of its transitions
+        let status = validation::Status::with_failure(
+            validation::Failure::SchemaUnknown(schema::schema().schema_id()),
+        );
+        let (report, _) =
+            consignment_report(None, &consignment, &[], status).unwrap();
+        assert!(!report.all_checks_passed());
+        assert!(report.validated_transitions.is_empty());
+
+        let (report, _) = consignment_report(
+            None,
+            &consignment,
+            &[],
+            validation::Status::default(),
+        )
+        .unwrap();
+        assert_eq!(report.validated_transitions.len(), 2);
+    }
+
+    #[test]
+    fn test_chunk_transfer() {
+        let (_, genesis) = rgb20::issue(
+            Chain::Testnet3,
+            s!("TST"),
+            s!("Test asset"),
+            None,
+            8,
+            vec![(outpoint(0), 1000)],
+            bmap! {},
+            None,
+            None,
+        )
+        .unwrap();
+        let consignment = Consignment::with(genesis, vec![], vec![], vec![]);
+        let size = consignment.strict_encode(io::sink()).unwrap();
+        let transfer = || {
+            Reply::Transfer(reply::Transfer {
+                consignment: consignment.clone(),
+                disclosure: Disclosure::default(),
+                witness: PartiallySignedTransaction::from_unsigned_tx(
+                    bitcoin::Transaction {
+                        version: 2,
+                        lock_time: 0,
+                        input: vec![],
+                        output: vec![],
+                    },
+                )
+                .unwrap(),
+                change_outpoint: Some(outpoint(7)),
+            })
+        };
+
+        // Consignments fitting into a single chunk are sent as they are
+        let (reply, chunks) = chunk_transfer(transfer(), size);
+        assert!(matches!(reply, Reply::Transfer(_)));
+        assert!(chunks.is_empty());
+        let (reply, chunks) = chunk_transfer(Reply::Success, 1);
+        assert!(matches!(reply, Reply::Success));
+        assert!(chunks.is_empty());
+
+        let (reply, chunks) = chunk_transfer(transfer(), size / 3 + 1);
+        let chunked = match reply {
+            Reply::ChunkedTransfer(chunked) => chunked,
+            _ => panic!("transfer must be chunked"),
+        };
+        assert_eq!(chunked.chunks, 3);
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunked.change_outpoint, Some(outpoint(7)));
+        assert_eq!(
+            reply::ConsignmentChunk::reassemble(chunks).unwrap(),
+            consignment
+        );
+    }
 }
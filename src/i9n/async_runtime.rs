@@ -0,0 +1,211 @@
+// RGB standard library
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Arc, Mutex};
+
+use bitcoin::util::psbt::PartiallySignedTransaction;
+use bitcoin::OutPoint;
+use lnpbp::chain::Chain;
+use rgb::{AtomicValue, ContractId, SealDefinition, SealEndpoint};
+use rgb20::{Asset, OutpointCoins};
+
+use super::{Error, Runtime};
+use crate::error::ServiceErrorDomain;
+use crate::rpc::fungible::Request;
+use crate::rpc::reply::Transfer;
+use crate::rpc::Reply;
+use crate::util::ChainScopedContractId;
+
+/// Non-blocking interface to the RGB node for async applications.
+///
+/// Wraps [`Runtime`] performing each blocking ZMQ round-trip on the tokio
+/// blocking thread pool, so the calls do not stall the async executor. Clones
+/// share the same connection; since the connection supports only a single
+/// request at a time, concurrent calls are served one after another.
+#[derive(Clone)]
+pub struct AsyncRuntime(Arc<Mutex<Runtime>>);
+
+impl From<Runtime> for AsyncRuntime {
+    fn from(runtime: Runtime) -> Self {
+        AsyncRuntime(Arc::new(Mutex::new(runtime)))
+    }
+}
+
+impl AsyncRuntime {
+    /// Runs blocking runtime operation on the tokio blocking thread pool
+    async fn spawn_blocking<T>(
+        &self,
+        f: impl FnOnce(&mut Runtime) -> Result<T, Error> + Send + 'static,
+    ) -> Result<T, Error>
+    where
+        T: Send + 'static,
+    {
+        let runtime = self.0.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut runtime = runtime
+                .lock()
+                .map_err(|_| ServiceErrorDomain::Multithreading)?;
+            f(&mut runtime)
+        })
+        .await
+        .map_err(|_| ServiceErrorDomain::Multithreading)?
+    }
+
+    /// Sends request to the daemon, translating failure replies into the
+    /// matching [`Error`] variants
+    pub async fn command(&self, command: Request) -> Result<Arc<Reply>, Error> {
+        self.spawn_blocking(move |runtime| runtime.command(command))
+            .await
+    }
+
+    /// Async version of [`Runtime::issue`]
+    pub async fn issue(
+        &self,
+        chain: Chain,
+        ticker: String,
+        name: String,
+        description: Option<String>,
+        precision: u8,
+        allocation: Vec<OutpointCoins>,
+        inflation: Vec<OutpointCoins>,
+        renomination: Option<OutPoint>,
+        epoch: Option<OutPoint>,
+    ) -> Result<Asset, Error> {
+        self.spawn_blocking(move |runtime| {
+            runtime.issue(
+                chain,
+                ticker,
+                name,
+                description,
+                precision,
+                allocation,
+                inflation,
+                renomination,
+                epoch,
+            )
+        })
+        .await
+    }
+
+    #[deprecated(since = "0.5.0", note = "use `transfer_scoped`")]
+    #[allow(deprecated)]
+    pub async fn transfer(
+        &self,
+        contract_id: ContractId,
+        inputs: BTreeSet<OutPoint>,
+        payment: BTreeMap<SealEndpoint, AtomicValue>,
+        change: BTreeMap<SealDefinition, AtomicValue>,
+        change_outpoint: Option<OutPoint>,
+        witness: PartiallySignedTransaction,
+    ) -> Result<Transfer, Error> {
+        self.spawn_blocking(move |runtime| {
+            runtime.transfer(
+                contract_id,
+                inputs,
+                payment,
+                change,
+                change_outpoint,
+                witness,
+            )
+        })
+        .await
+    }
+
+    /// Async version of [`Runtime::transfer_scoped`]
+    pub async fn transfer_scoped(
+        &self,
+        contract_id: ChainScopedContractId,
+        inputs: BTreeSet<OutPoint>,
+        payment: BTreeMap<SealEndpoint, AtomicValue>,
+        change: BTreeMap<SealDefinition, AtomicValue>,
+        change_outpoint: Option<OutPoint>,
+        witness: PartiallySignedTransaction,
+    ) -> Result<Transfer, Error> {
+        self.spawn_blocking(move |runtime| {
+            runtime.transfer_scoped(
+                &contract_id,
+                inputs,
+                payment,
+                change,
+                change_outpoint,
+                witness,
+            )
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::i9n::Config;
+    use crate::rgbd::ContractName;
+    use internet2::{session, Session, TypedEnum, ZmqSocketAddr, ZmqType};
+    use microservices::FileFormat;
+    use std::thread;
+
+    #[test]
+    fn test_concurrent_commands() {
+        let endpoint = ZmqSocketAddr::Inproc(s!("rgb-async-test"));
+        let mut server = session::Raw::with_zmq_unencrypted(
+            ZmqType::Rep,
+            &endpoint,
+            None,
+            None,
+        )
+        .unwrap();
+        let daemon = thread::spawn(move || {
+            for _ in 0..2 {
+                server.recv_raw_message().unwrap();
+                server
+                    .send_raw_message(&Reply::Success.serialize())
+                    .unwrap();
+            }
+        });
+
+        let runtime = AsyncRuntime::from(
+            Runtime::init(Config {
+                contract_endpoints: map! { ContractName::Fungible => endpoint },
+                run_embedded: false,
+                ..Config::default()
+            })
+            .unwrap(),
+        );
+        let executor = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let replies = executor.block_on(async {
+            let first = tokio::spawn({
+                let runtime = runtime.clone();
+                async move {
+                    runtime
+                        .command(Request::Sync(FileFormat::StrictEncode))
+                        .await
+                }
+            });
+            let second = tokio::spawn({
+                let runtime = runtime.clone();
+                async move {
+                    runtime
+                        .command(Request::Sync(FileFormat::StrictEncode))
+                        .await
+                }
+            });
+            (first.await.unwrap(), second.await.unwrap())
+        });
+        assert!(matches!(*replies.0.unwrap(), Reply::Success));
+        assert!(matches!(*replies.1.unwrap(), Reply::Success));
+        daemon.join().unwrap();
+    }
+}
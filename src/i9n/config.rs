@@ -12,12 +12,25 @@
 // If not, see <https://opensource.org/licenses/MIT>.
 
 use std::collections::HashMap;
+#[cfg(feature = "serde")]
+use std::path::{Path, PathBuf};
+#[cfg(feature = "serde")]
+use std::str::FromStr;
+use std::time::Duration;
+#[cfg(feature = "serde")]
+use std::{env, fs};
 
 use internet2::ZmqSocketAddr;
 use lnpbp::chain::Chain;
+use microservices::FileFormat;
+#[cfg(feature = "serde")]
+use serde::Deserialize;
 
 use crate::constants::*;
+use crate::error::BootstrapError;
 use crate::rgbd::ContractName;
+use crate::rpc::fungible::WIRE_FORMATS;
+use crate::util;
 
 #[derive(Clone, PartialEq, Eq, Debug, Display)]
 #[display(Debug)]
@@ -27,8 +40,21 @@ pub struct Config {
     pub electrum_server: String,
     pub stash_rpc_endpoint: ZmqSocketAddr,
     pub contract_endpoints: HashMap<ContractName, ZmqSocketAddr>,
+    /// Fungible engine endpoints of the redundant RGB nodes, which are tried
+    /// in the given order when the endpoint from `contract_endpoints` does
+    /// not reply
+    pub failover_endpoints: Vec<ZmqSocketAddr>,
     pub network: Chain,
     pub run_embedded: bool,
+    /// Time to wait for the complete reply from the daemon
+    pub reply_timeout: Duration,
+    /// Whether encoding of the RPC messages is negotiated with the daemon on
+    /// [`crate::i9n::Runtime::init`]; daemons released before the
+    /// negotiation was introduced do not support it
+    pub negotiate_format: bool,
+    /// Encodings of the RPC messages offered to the daemon in the order of
+    /// preference when `negotiate_format` is set
+    pub wire_formats: Vec<FileFormat>,
 }
 
 impl Default for Config {
@@ -43,10 +69,355 @@ impl Default for Config {
             contract_endpoints: map! {
                 ContractName::Fungible => FUNGIBLED_RPC_ENDPOINT.parse().expect("Error in FUNGIBLED_RPC_ENDPOINT value")
             },
+            failover_endpoints: vec![],
             network: RGB_NETWORK
                 .parse()
                 .expect("Error in RGB_NETWORK constant value"),
             run_embedded: true,
+            reply_timeout: Duration::from_secs(RGB_REPLY_TIMEOUT_SECS),
+            negotiate_format: false,
+            wire_formats: WIRE_FORMATS.to_vec(),
         }
     }
 }
+
+impl Config {
+    /// Starts building configuration with [`ConfigBuilder`]
+    pub fn builder() -> ConfigBuilder {
+        ConfigBuilder::default()
+    }
+
+    /// Reads configuration from the first existing file of
+    /// `$XDG_CONFIG_HOME/rgb/config.toml` (`$HOME/.config/rgb/config.toml` if
+    /// `XDG_CONFIG_HOME` is not set) and `$HOME/.rgb/config.toml`. Fails with
+    /// [`BootstrapError::NoConfigFound`] if there is no such file.
+    #[cfg(feature = "serde")]
+    pub fn from_default_locations() -> Result<Config, BootstrapError> {
+        let locations = config_locations(
+            env::var_os("XDG_CONFIG_HOME").map(PathBuf::from),
+            env::var_os("HOME").map(PathBuf::from),
+        );
+        let path = locations
+            .iter()
+            .find(|path| path.is_file())
+            .ok_or(BootstrapError::NoConfigFound)?;
+        Config::from_file(path)
+    }
+
+    /// Reads configuration from TOML file with optional `endpoint`, `chain`
+    /// and `timeout` (in seconds) keys; the rest of the parameters and the
+    /// missing ones default to the values from [`Config::default`]
+    #[cfg(feature = "serde")]
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Config, BootstrapError> {
+        let path = path.as_ref();
+        let data = fs::read_to_string(path)?;
+        let file: ConfigFile = toml::from_str(&data).map_err(|err| {
+            format!("Invalid configuration file {:?}: {}", path, err)
+        })?;
+        let mut builder = Config::builder().endpoint(
+            file.endpoint.as_deref().unwrap_or(FUNGIBLED_RPC_ENDPOINT),
+        );
+        if let Some(chain) = file.chain {
+            builder =
+                builder.chain(Chain::from_str(&chain).map_err(|err| {
+                    format!("Invalid chain `{}`: {}", chain, err)
+                })?);
+        }
+        if let Some(timeout) = file.timeout {
+            builder = builder.timeout(Duration::from_secs(timeout));
+        }
+        builder.build()
+    }
+}
+
+/// Parameters of the configuration file read by [`Config::from_file`]
+#[cfg(feature = "serde")]
+#[derive(Clone, PartialEq, Eq, Debug, Default, Deserialize)]
+#[serde(crate = "serde_crate", deny_unknown_fields)]
+struct ConfigFile {
+    endpoint: Option<String>,
+    chain: Option<String>,
+    timeout: Option<u64>,
+}
+
+/// Lists configuration file locations in the order of their priority
+#[cfg(feature = "serde")]
+fn config_locations(
+    xdg_config_home: Option<PathBuf>,
+    home: Option<PathBuf>,
+) -> Vec<PathBuf> {
+    let mut locations = vec![];
+    match (xdg_config_home, &home) {
+        (Some(config_home), _) => locations.push(config_home),
+        (None, Some(home)) => locations.push(home.join(".config")),
+        (None, None) => {}
+    }
+    let mut locations = locations
+        .into_iter()
+        .map(|dir| dir.join("rgb").join("config.toml"))
+        .collect::<Vec<_>>();
+    if let Some(home) = home {
+        locations.push(home.join(".rgb").join("config.toml"));
+    }
+    locations
+}
+
+/// Builder for the integration [`Config`] validating the provided parameters
+/// once, on [`ConfigBuilder::build`]. Fungible engine endpoint is required;
+/// the rest of the parameters default to the values from
+/// [`Config::default`].
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct ConfigBuilder {
+    endpoint: Option<String>,
+    failover_endpoints: Vec<String>,
+    stash_endpoint: Option<String>,
+    network: Option<Chain>,
+    reply_timeout: Option<Duration>,
+    run_embedded: Option<bool>,
+    data_dir: Option<String>,
+    negotiate_format: Option<bool>,
+    wire_formats: Option<Vec<FileFormat>>,
+}
+
+impl ConfigBuilder {
+    /// Sets RPC endpoint of the fungible engine (required)
+    pub fn endpoint(mut self, endpoint: impl ToString) -> Self {
+        self.endpoint = Some(endpoint.to_string());
+        self
+    }
+
+    /// Adds RPC endpoint of the fungible engine of the redundant RGB node
+    /// used when the previously provided endpoints do not reply
+    pub fn failover_endpoint(mut self, endpoint: impl ToString) -> Self {
+        self.failover_endpoints.push(endpoint.to_string());
+        self
+    }
+
+    /// Sets RPC endpoint of the stash daemon
+    pub fn stash_endpoint(mut self, endpoint: impl ToString) -> Self {
+        self.stash_endpoint = Some(endpoint.to_string());
+        self
+    }
+
+    /// Sets network used by the RGB node
+    pub fn chain(mut self, chain: Chain) -> Self {
+        self.network = Some(chain);
+        self
+    }
+
+    /// Sets time to wait for the complete reply from the daemon
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.reply_timeout = Some(timeout);
+        self
+    }
+
+    /// Sets whether RGB node daemons must be run within the same process
+    pub fn embedded(mut self, run_embedded: bool) -> Self {
+        self.run_embedded = Some(run_embedded);
+        self
+    }
+
+    /// Sets data directory used by the embedded RGB node
+    pub fn data_dir(mut self, data_dir: impl ToString) -> Self {
+        self.data_dir = Some(data_dir.to_string());
+        self
+    }
+
+    /// Sets whether encoding of the RPC messages is negotiated with the
+    /// daemon on the runtime initialization
+    pub fn negotiate_format(mut self, negotiate_format: bool) -> Self {
+        self.negotiate_format = Some(negotiate_format);
+        self
+    }
+
+    /// Sets encodings of the RPC messages offered to the daemon during the
+    /// negotiation, in the order of preference. All of them must be present
+    /// in [`WIRE_FORMATS`].
+    pub fn wire_formats(
+        mut self,
+        formats: impl IntoIterator<Item = FileFormat>,
+    ) -> Self {
+        self.wire_formats = Some(formats.into_iter().collect());
+        self
+    }
+
+    /// Validates provided parameters and constructs configuration
+    pub fn build(self) -> Result<Config, BootstrapError> {
+        let endpoint = self
+            .endpoint
+            .ok_or("Fungible engine endpoint is not provided")?;
+        let endpoint = parse_endpoint(&endpoint)?;
+        let mut config = Config::default();
+        config
+            .contract_endpoints
+            .insert(ContractName::Fungible, endpoint);
+        config.failover_endpoints = self
+            .failover_endpoints
+            .iter()
+            .map(|endpoint| parse_endpoint(endpoint))
+            .collect::<Result<_, _>>()?;
+        if let Some(stash_endpoint) = self.stash_endpoint {
+            config.stash_rpc_endpoint = parse_endpoint(&stash_endpoint)?;
+        }
+        if let Some(network) = self.network {
+            config.network = network;
+        }
+        if let Some(reply_timeout) = self.reply_timeout {
+            if reply_timeout == Duration::default() {
+                Err("Reply timeout must be non-zero")?;
+            }
+            config.reply_timeout = reply_timeout;
+        }
+        if let Some(run_embedded) = self.run_embedded {
+            config.run_embedded = run_embedded;
+        }
+        if let Some(data_dir) = self.data_dir {
+            config.data_dir = data_dir;
+        }
+        if let Some(negotiate_format) = self.negotiate_format {
+            config.negotiate_format = negotiate_format;
+        }
+        if let Some(wire_formats) = self.wire_formats {
+            if wire_formats.is_empty() {
+                Err("At least one RPC message format must be offered")?;
+            }
+            if let Some(format) = wire_formats
+                .iter()
+                .find(|format| !WIRE_FORMATS.contains(format))
+            {
+                Err(format!("Unsupported RPC message format {}", format))?;
+            }
+            config.wire_formats = wire_formats;
+        }
+        Ok(config)
+    }
+}
+
+fn parse_endpoint(endpoint: &str) -> Result<ZmqSocketAddr, BootstrapError> {
+    util::parse_endpoint(endpoint).map_err(|err| {
+        BootstrapError::ArgParseError(format!("Invalid endpoint {}", err))
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_builder() {
+        assert!(matches!(
+            Config::builder().chain(Chain::Testnet3).build(),
+            Err(BootstrapError::ArgParseError(_))
+        ));
+        assert!(matches!(
+            Config::builder().endpoint("not an endpoint").build(),
+            Err(BootstrapError::ArgParseError(_))
+        ));
+        assert!(matches!(
+            Config::builder()
+                .endpoint("inproc://fungible")
+                .timeout(Duration::default())
+                .build(),
+            Err(BootstrapError::ArgParseError(_))
+        ));
+        for formats in
+            &[vec![], vec![FileFormat::StrictEncode, FileFormat::Yaml]]
+        {
+            assert!(matches!(
+                Config::builder()
+                    .endpoint("inproc://fungible")
+                    .wire_formats(formats.clone())
+                    .build(),
+                Err(BootstrapError::ArgParseError(_))
+            ));
+        }
+
+        let config = Config::builder()
+            .endpoint("inproc://fungible")
+            .failover_endpoint("tcp://127.0.0.1:61961")
+            .chain(Chain::Testnet3)
+            .timeout(Duration::from_secs(5))
+            .embedded(false)
+            .wire_formats(vec![FileFormat::StrictEncode])
+            .build()
+            .unwrap();
+        assert_eq!(
+            config.contract_endpoints.get(&ContractName::Fungible),
+            Some(&ZmqSocketAddr::Inproc(s!("fungible")))
+        );
+        assert_eq!(
+            config.failover_endpoints,
+            vec![ZmqSocketAddr::Tcp("127.0.0.1:61961".parse().unwrap())]
+        );
+        assert_eq!(config.network, Chain::Testnet3);
+        assert_eq!(config.reply_timeout, Duration::from_secs(5));
+        assert!(!config.run_embedded);
+        assert_eq!(config.wire_formats, vec![FileFormat::StrictEncode]);
+        assert_eq!(
+            config.stash_rpc_endpoint,
+            Config::default().stash_rpc_endpoint
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_from_default_locations() {
+        let home = env::temp_dir()
+            .join(format!("rgb-i9n-config-{}", std::process::id()));
+        let config_home = home.join(".config");
+        assert_eq!(
+            config_locations(None, Some(home.clone())),
+            vec![
+                config_home.join("rgb").join("config.toml"),
+                home.join(".rgb").join("config.toml")
+            ]
+        );
+        assert_eq!(
+            config_locations(Some(config_home.clone()), None),
+            vec![config_home.join("rgb").join("config.toml")]
+        );
+        assert!(config_locations(None, None).is_empty());
+
+        let _ = fs::remove_dir_all(&home);
+        fs::create_dir_all(config_home.join("rgb")).unwrap();
+        let path = config_locations(None, Some(home.clone()))
+            .into_iter()
+            .find(|path| path.is_file());
+        assert_eq!(path, None);
+
+        fs::write(
+            config_home.join("rgb").join("config.toml"),
+            "endpoint = \"tcp://127.0.0.1:61961\"\n\
+             chain = \"testnet\"\n\
+             timeout = 5\n",
+        )
+        .unwrap();
+        let path = config_locations(None, Some(home.clone()))
+            .into_iter()
+            .find(|path| path.is_file())
+            .unwrap();
+        let config = Config::from_file(path).unwrap();
+        assert_eq!(
+            config.contract_endpoints.get(&ContractName::Fungible),
+            Some(&ZmqSocketAddr::Tcp("127.0.0.1:61961".parse().unwrap()))
+        );
+        assert_eq!(config.network, Chain::Testnet3);
+        assert_eq!(config.reply_timeout, Duration::from_secs(5));
+
+        fs::write(
+            config_home.join("rgb").join("config.toml"),
+            "chain = \"nochain\"\n",
+        )
+        .unwrap();
+        assert!(matches!(
+            Config::from_file(config_home.join("rgb").join("config.toml")),
+            Err(BootstrapError::ArgParseError(_))
+        ));
+        assert!(matches!(
+            Config::from_file(home.join(".rgb").join("config.toml")),
+            Err(BootstrapError::IoError(_))
+        ));
+
+        fs::remove_dir_all(&home).unwrap();
+    }
+}
@@ -12,20 +12,72 @@
 // If not, see <https://opensource.org/licenses/MIT>.
 
 use crate::error::ServiceErrorDomain;
-use crate::rpc::reply;
+use crate::rpc::fungible;
+use crate::rpc::reply::{self, FailureCode};
 
 #[derive(Debug, Display, Error, From)]
 #[display(inner)]
 pub enum Error {
     /// Integration module internal error
     #[from]
+    #[from(internet2::transport::Error)]
+    #[from(internet2::presentation::Error)]
     ServiceError(ServiceErrorDomain),
 
     /// RGB Node returned error: {0}
     #[display(doc_comments)]
-    #[from]
     Reply(reply::Failure),
 
+    /// Asset is not known to RGB Node: {0}
+    #[display(doc_comments)]
+    UnknownAsset(String),
+
+    /// Transfer inputs do not have enough assets: {0}
+    #[display(doc_comments)]
+    InsufficientFunds(String),
+
+    /// Asset has non-zero balance and can't be forgotten unless forced: {0}
+    #[display(doc_comments)]
+    AssetHasBalance(String),
+
+    /// Asset is imported for monitoring only and can't be spent: {0}
+    #[display(doc_comments)]
+    WatchOnly(String),
+
+    /// Provided data violate RGB20 schema: {0}
+    #[display(doc_comments)]
+    SchemaViolation(String),
+
+    /// Invalid issue parameters: {0}
+    #[display(doc_comments)]
+    #[from]
+    InvalidIssue(fungible::IssueError),
+
+    /// Invalid inflation request: {0}
+    #[display(doc_comments)]
+    #[from]
+    InvalidInflation(fungible::InflateError),
+
+    /// Invalid burn request: {0}
+    #[display(doc_comments)]
+    #[from]
+    InvalidBurn(fungible::BurnError),
+
+    /// Invalid renomination request: {0}
+    #[display(doc_comments)]
+    #[from]
+    InvalidRenomination(fungible::RenominateError),
+
+    /// Invalid transfer request: {0}
+    #[display(doc_comments)]
+    #[from]
+    Transfer(fungible::TransferError),
+
+    /// Error decoding the provided data from Bech32 encoding: {0}
+    #[display(doc_comments)]
+    #[from]
+    Bech32(rgb::bech32::Error),
+
     /// Error decoding the provided data from Base64 encoding
     #[from]
     Base64(base64::DecodeError),
@@ -46,4 +98,119 @@ pub enum Error {
     /// The provided network id does not match the network used by the RGB node
     #[display(doc_comments)]
     WrongNetwork,
+
+    /// Connection to RGB node was closed before the complete reply was
+    /// received
+    #[display(doc_comments)]
+    IncompleteReply,
+
+    /// RGB node has not provided the complete reply within the timeout
+    #[display(doc_comments)]
+    ReplyTimeout,
+
+    /// Consignment chunk #{0} is missing
+    #[display(doc_comments)]
+    ChunkMissing(u32),
+
+    /// Consignment chunks are duplicated, out of range or disagree on the
+    /// total number of chunks
+    #[display(doc_comments)]
+    ChunkMismatch,
+
+    /// Error accessing the file: {0}
+    #[display(doc_comments)]
+    #[from(std::io::Error)]
+    Io(amplify::IoError),
+}
+
+impl From<reply::ChunkError> for Error {
+    fn from(err: reply::ChunkError) -> Self {
+        match err {
+            reply::ChunkError::Missing(seq) => Error::ChunkMissing(seq),
+            reply::ChunkError::Mismatch => Error::ChunkMismatch,
+            reply::ChunkError::Encoding(err) => Error::Encoding(err),
+        }
+    }
+}
+
+impl From<reply::Failure> for Error {
+    fn from(failure: reply::Failure) -> Self {
+        match failure.failure_code() {
+            Some(FailureCode::UnknownAsset) => {
+                Error::UnknownAsset(failure.info)
+            }
+            Some(FailureCode::InsufficientFunds) => {
+                Error::InsufficientFunds(failure.info)
+            }
+            Some(FailureCode::SchemaViolation) => {
+                Error::SchemaViolation(failure.info)
+            }
+            Some(FailureCode::ChainMismatch) => Error::WrongNetwork,
+            Some(FailureCode::AssetHasBalance) => {
+                Error::AssetHasBalance(failure.info)
+            }
+            Some(FailureCode::WatchOnly) => Error::WatchOnly(failure.info),
+            _ => Error::Reply(failure),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::error::{ServiceError, ServiceErrorSource};
+
+    fn service_failure(domain: ServiceErrorDomain) -> reply::Failure {
+        reply::Failure::from(ServiceError {
+            domain,
+            service: ServiceErrorSource::Contract(s!("fungible")),
+        })
+    }
+
+    #[test]
+    fn test_failure_code_mapping() {
+        let failure =
+            service_failure(ServiceErrorDomain::UnknownAsset(s!("asset")));
+        assert_eq!(failure.failure_code(), Some(FailureCode::UnknownAsset));
+        assert!(matches!(Error::from(failure), Error::UnknownAsset(_)));
+
+        let failure = service_failure(ServiceErrorDomain::InsufficientFunds(
+            s!("inputs"),
+        ));
+        assert_eq!(
+            failure.failure_code(),
+            Some(FailureCode::InsufficientFunds)
+        );
+        assert!(matches!(Error::from(failure), Error::InsufficientFunds(_)));
+
+        let failure = service_failure(ServiceErrorDomain::Schema(s!("schema")));
+        assert_eq!(failure.failure_code(), Some(FailureCode::SchemaViolation));
+        assert!(matches!(Error::from(failure), Error::SchemaViolation(_)));
+
+        let failure =
+            service_failure(ServiceErrorDomain::ChainMismatch(s!("chain")));
+        assert_eq!(failure.failure_code(), Some(FailureCode::ChainMismatch));
+        assert!(matches!(Error::from(failure), Error::WrongNetwork));
+
+        let failure =
+            service_failure(ServiceErrorDomain::AssetHasBalance(s!("asset")));
+        assert_eq!(failure.failure_code(), Some(FailureCode::AssetHasBalance));
+        assert!(matches!(Error::from(failure), Error::AssetHasBalance(_)));
+
+        let failure =
+            service_failure(ServiceErrorDomain::WatchOnly(s!("asset")));
+        assert_eq!(failure.failure_code(), Some(FailureCode::WatchOnly));
+        assert!(matches!(Error::from(failure), Error::WatchOnly(_)));
+
+        let failure = service_failure(ServiceErrorDomain::Stash);
+        assert_eq!(failure.failure_code(), Some(FailureCode::Service));
+        assert!(matches!(Error::from(failure), Error::Reply(_)));
+
+        let failure = reply::Failure {
+            code: 0xFFFF,
+            info: s!("unknown"),
+        };
+        assert_eq!(failure.failure_code(), None);
+        assert!(matches!(Error::from(failure), Error::Reply(_)));
+    }
 }
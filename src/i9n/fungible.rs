@@ -12,39 +12,150 @@
 // If not, see <https://opensource.org/licenses/MIT>.
 
 use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use std::str::FromStr;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use bitcoin::util::psbt::raw::ProprietaryKey;
 use bitcoin::util::psbt::PartiallySignedTransaction;
 use bitcoin::OutPoint;
-use bp::seals::OutpointReveal;
-use internet2::{Session, TypedEnum, Unmarshall};
+use bp::seals::{OutpointHash, OutpointReveal};
+use commit_verify::CommitConceal;
+use internet2::TypedEnum;
 use lnpbp::chain::Chain;
 use microservices::FileFormat;
 use rgb::{
     AtomicValue, Consignment, ContractId, Disclosure, Genesis, SealDefinition,
     SealEndpoint, PSBT_OUT_PUBKEY,
 };
-use rgb20::{Asset, OutpointCoins};
+use rgb20::{AccountingAmount, Asset, OutpointCoins};
 
 use super::{Error, Runtime};
-use crate::error::ServiceErrorDomain;
-use crate::rpc::reply::Transfer;
+use crate::rpc::reply::{
+    BalanceDelta, ChunkedTransfer, ConsignmentChunk, ConsignmentReport,
+    PendingTransfer, ServerInfo, Transfer, TransferPreview,
+};
 use crate::rpc::{
-    fungible::AcceptReq, fungible::IssueReq, fungible::Request,
-    fungible::TransferReq, reply, Reply,
+    fungible::validate_batch, fungible::AcceptReq, fungible::BurnReq,
+    fungible::ClearWatchOnlyReq, fungible::ConsignmentChunkReq,
+    fungible::ForgetAssetReq, fungible::InflateReq, fungible::IssueReq,
+    fungible::LabelReq, fungible::ListAssetsReq, fungible::RenominateError,
+    fungible::RenominateReq, fungible::Request, fungible::SetLabelReq,
+    fungible::TransferAcceptedReq, fungible::TransferReq, reply, wire, Reply,
+};
+use crate::util::file::ReadWrite;
+use crate::util::{
+    renomination_right, supply_by_ticker, AssetExt, ChainScopedContractId,
 };
 
 impl Runtime {
-    fn command(
+    /// Sends request to the daemon, translating failure replies into the
+    /// matching [`Error`] variants. Request name, sizes of the request and
+    /// reply and the round-trip time are logged at `debug` and `trace` levels.
+    pub(super) fn command(
         &mut self,
         command: Request,
-    ) -> Result<Arc<Reply>, ServiceErrorDomain> {
-        let data = command.serialize();
-        self.session_rpc.send_raw_message(&data)?;
-        let raw = self.session_rpc.recv_raw_message()?;
-        let reply = self.unmarshaller.unmarshall(&raw)?;
-        Ok(reply)
+    ) -> Result<Arc<Reply>, Error> {
+        let name = if log_enabled!(log::Level::Debug) {
+            command_name(&command)
+        } else {
+            s!("")
+        };
+        let data = wire::encode(&command, self.wire_format);
+        trace!("Sending `{}` request of {} bytes", name, data.len());
+        let started = Instant::now();
+        let reply = self.request(&data);
+        let elapsed = started.elapsed();
+        let reply = match reply {
+            Ok(reply) => reply,
+            Err(err) => {
+                debug!(
+                    "`{}` request failed after {:?}: {}",
+                    name, elapsed, err
+                );
+                return Err(err);
+            }
+        };
+        debug!("`{}` request completed in {:?}", name, elapsed);
+        if log_enabled!(log::Level::Trace) {
+            trace!("Got `{}` reply of {} bytes", name, reply.serialize().len());
+        }
+        match &*reply {
+            Reply::Failure(failure) => Err(failure.clone().into()),
+            Reply::ChunkedTransfer(transfer) => {
+                self.collect_chunks(transfer.clone())
+            }
+            _ => Ok(reply),
+        }
+    }
+
+    /// Requests all chunks of the chunked transfer consignment, completing
+    /// the transfer with the consignment reassembled from them
+    fn collect_chunks(
+        &mut self,
+        transfer: ChunkedTransfer,
+    ) -> Result<Arc<Reply>, Error> {
+        debug!("Collecting {} consignment chunks", transfer.chunks);
+        let mut chunks = Vec::with_capacity(transfer.chunks as usize);
+        for seq in 0..transfer.chunks {
+            match &*self.command(Request::ConsignmentChunk(
+                ConsignmentChunkReq { seq },
+            ))? {
+                Reply::ConsignmentChunk(chunk) => chunks.push(chunk.clone()),
+                _ => return Err(Error::UnexpectedResponse),
+            }
+        }
+        let consignment = Self::reassemble_consignment(chunks)?;
+        Ok(Arc::new(Reply::Transfer(transfer.complete(consignment))))
+    }
+
+    /// Checks that the daemon is alive and responsive, returning the
+    /// round-trip time of the request
+    pub fn ping(&mut self) -> Result<Duration, Error> {
+        let started = Instant::now();
+        match &*self.command(Request::Ping)? {
+            Reply::Pong => Ok(started.elapsed()),
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+
+    /// Requests the daemon version, supported schemata and network, for
+    /// checking the compatibility with the daemon before issuing commands.
+    /// Version mismatch is logged as a warning.
+    pub fn server_info(&mut self) -> Result<ServerInfo, Error> {
+        match &*self.command(Request::Info)? {
+            Reply::Info(info) => {
+                if info.version != env!("CARGO_PKG_VERSION") {
+                    warn!(
+                        "RGB node version {} does not match client version {}",
+                        info.version,
+                        env!("CARGO_PKG_VERSION")
+                    );
+                }
+                Ok(info.clone())
+            }
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+
+    /// Negotiates encoding of the RPC messages with the daemon, offering the
+    /// formats from [`crate::i9n::Config::wire_formats`]; the selected format
+    /// is used for the subsequent requests and replies and is reported by
+    /// [`Runtime::wire_format`]. Fails with [`Error::UnexpectedResponse`] if
+    /// the daemon selects a format which was not offered.
+    pub fn hello(&mut self) -> Result<FileFormat, Error> {
+        let offered = self.config.wire_formats.clone();
+        match &*self.command(Request::Hello(offered.clone()))? {
+            Reply::WireFormat(reply::WireFormat { format })
+                if offered.contains(format) =>
+            {
+                debug!("Using {} encoding of RPC messages", format);
+                self.wire_format = *format;
+                Ok(*format)
+            }
+            _ => Err(Error::UnexpectedResponse),
+        }
     }
 
     pub fn issue(
@@ -62,7 +173,7 @@ impl Runtime {
         if self.config.network != chain {
             Err(Error::WrongNetwork)?;
         }
-        let command = Request::Issue(IssueReq {
+        let issue = IssueReq {
             ticker,
             name,
             description,
@@ -71,58 +182,76 @@ impl Runtime {
             inflation,
             renomination,
             epoch,
-        });
-        match &*self.command(command)? {
+            idempotency_key: None,
+        };
+        issue.validate()?;
+        match &*self.command(Request::Issue(issue))? {
             Reply::Asset(asset) => Ok(asset.clone()),
-            Reply::Failure(failmsg) => Err(Error::Reply(failmsg.clone())),
             _ => Err(Error::UnexpectedResponse),
         }
     }
 
+    #[deprecated(since = "0.5.0", note = "use `transfer_scoped`")]
     pub fn transfer(
         &mut self,
         contract_id: ContractId,
         inputs: BTreeSet<OutPoint>,
         payment: BTreeMap<SealEndpoint, AtomicValue>,
         change: BTreeMap<SealDefinition, AtomicValue>,
-        mut witness: PartiallySignedTransaction,
+        change_outpoint: Option<OutPoint>,
+        witness: PartiallySignedTransaction,
     ) -> Result<Transfer, Error> {
-        for (index, output) in &mut witness.outputs.iter_mut().enumerate() {
-            if let Some(key) = output.bip32_derivation.keys().next() {
-                let key = key.clone();
-                output.proprietary.insert(
-                    ProprietaryKey {
-                        prefix: b"RGB".to_vec(),
-                        subtype: PSBT_OUT_PUBKEY,
-                        key: vec![],
-                    },
-                    key.key.serialize().to_vec(),
-                );
-                debug!("Output #{} commitment key will be {}", index, key);
-            } else {
-                warn!(
-                    "No public key information found for output #{}; \
-                    LNPBP1/2 commitment will be impossible.\
-                    In order to allow commitment pls add known keys derivation \
-                    information to PSBT output map",
-                    index
-                );
-            }
-        }
-        trace!("{:?}", witness);
-
-        let api = TransferReq {
+        self.request_transfer(
+            contract_id,
+            inputs,
+            payment,
+            change,
+            change_outpoint,
             witness,
+        )
+    }
+
+    pub fn transfer_scoped(
+        &mut self,
+        contract_id: &ChainScopedContractId,
+        inputs: BTreeSet<OutPoint>,
+        payment: BTreeMap<SealEndpoint, AtomicValue>,
+        change: BTreeMap<SealDefinition, AtomicValue>,
+        change_outpoint: Option<OutPoint>,
+        witness: PartiallySignedTransaction,
+    ) -> Result<Transfer, Error> {
+        let contract_id = self.unscope(contract_id)?;
+        self.request_transfer(
             contract_id,
             inputs,
             payment,
             change,
-        };
+            change_outpoint,
+            witness,
+        )
+    }
 
-        match &*self.command(Request::Transfer(api))? {
-            Reply::Failure(failure) => Err(Error::Reply(failure.clone())),
+    /// Transfers several assets within a single witness transaction. All of
+    /// the transfers must share the same witness and spend distinct inputs;
+    /// the returned consignment is made for the first of them, so the rest
+    /// may only move assets to own change seals. The disclosure covers the
+    /// whole batch.
+    pub fn batch_transfer(
+        &mut self,
+        mut transfers: Vec<TransferReq>,
+    ) -> Result<Transfer, Error> {
+        for transfer in &mut transfers {
+            prepare_witness(&mut transfer.witness);
+        }
+        validate_batch(&transfers)?;
+        for transfer in &transfers {
+            let allocations = self.request_allocations(transfer.contract_id)?;
+            transfer.validate_inputs(&allocations)?;
+        }
+
+        match &*self.command(Request::BatchTransfer(transfers))? {
             Reply::Transfer(transfer) => {
-                info!("Transfer succeeded");
+                info!("Batch transfer succeeded");
 
                 Ok(transfer.clone())
             }
@@ -130,35 +259,323 @@ impl Runtime {
         }
     }
 
+    #[deprecated(since = "0.5.0", note = "use `consolidate_scoped`")]
+    pub fn consolidate(
+        &mut self,
+        contract_id: ContractId,
+        threshold: AtomicValue,
+        seal: SealDefinition,
+        witness: PartiallySignedTransaction,
+    ) -> Result<Transfer, Error> {
+        self.request_consolidate(contract_id, threshold, seal, witness)
+    }
+
+    /// Sweeps dust allocations, i.e. known allocations with amounts below the
+    /// `threshold`, into a single new allocation assigned to the `seal`.
+    /// Spending an outpoint spends all of its allocations, so other
+    /// allocations on the same outpoints are swept as well. Fails with
+    /// [`Error::Transfer`] reporting no inputs if there are no dust
+    /// allocations.
+    pub fn consolidate_scoped(
+        &mut self,
+        contract_id: &ChainScopedContractId,
+        threshold: AtomicValue,
+        seal: SealDefinition,
+        witness: PartiallySignedTransaction,
+    ) -> Result<Transfer, Error> {
+        let contract_id = self.unscope(contract_id)?;
+        self.request_consolidate(contract_id, threshold, seal, witness)
+    }
+
+    #[deprecated(since = "0.5.0", note = "use `inflate_scoped`")]
+    pub fn inflate(
+        &mut self,
+        contract_id: ContractId,
+        inflation: OutPoint,
+        amount: AtomicValue,
+        allocation: BTreeMap<SealDefinition, AtomicValue>,
+        inflation_change: Option<SealDefinition>,
+        witness: PartiallySignedTransaction,
+    ) -> Result<Transfer, Error> {
+        self.request_inflate(
+            contract_id,
+            inflation,
+            amount,
+            allocation,
+            inflation_change,
+            witness,
+        )
+    }
+
+    pub fn inflate_scoped(
+        &mut self,
+        contract_id: &ChainScopedContractId,
+        inflation: OutPoint,
+        amount: AtomicValue,
+        allocation: BTreeMap<SealDefinition, AtomicValue>,
+        inflation_change: Option<SealDefinition>,
+        witness: PartiallySignedTransaction,
+    ) -> Result<Transfer, Error> {
+        let contract_id = self.unscope(contract_id)?;
+        self.request_inflate(
+            contract_id,
+            inflation,
+            amount,
+            allocation,
+            inflation_change,
+            witness,
+        )
+    }
+
+    #[deprecated(since = "0.5.0", note = "use `burn_scoped`")]
+    pub fn burn(
+        &mut self,
+        contract_id: ContractId,
+        outpoints: BTreeSet<OutPoint>,
+        proof_seal: Option<SealDefinition>,
+        witness: PartiallySignedTransaction,
+    ) -> Result<Transfer, Error> {
+        self.request_burn(contract_id, outpoints, proof_seal, witness)
+    }
+
+    pub fn burn_scoped(
+        &mut self,
+        contract_id: &ChainScopedContractId,
+        outpoints: BTreeSet<OutPoint>,
+        proof_seal: Option<SealDefinition>,
+        witness: PartiallySignedTransaction,
+    ) -> Result<Transfer, Error> {
+        let contract_id = self.unscope(contract_id)?;
+        self.request_burn(contract_id, outpoints, proof_seal, witness)
+    }
+
+    #[deprecated(since = "0.5.0", note = "use `rename_scoped`")]
+    pub fn rename(
+        &mut self,
+        contract_id: ContractId,
+        ticker: Option<String>,
+        name: Option<String>,
+        renomination_seal: Option<SealDefinition>,
+        witness: PartiallySignedTransaction,
+    ) -> Result<Transfer, Error> {
+        self.request_rename(
+            contract_id,
+            ticker,
+            name,
+            renomination_seal,
+            witness,
+        )
+    }
+
+    /// Renames the asset spending its renomination right, which is checked to
+    /// exist before the request is sent. At least one of `ticker` or `name`
+    /// must be given.
+    pub fn rename_scoped(
+        &mut self,
+        contract_id: &ChainScopedContractId,
+        ticker: Option<String>,
+        name: Option<String>,
+        renomination_seal: Option<SealDefinition>,
+        witness: PartiallySignedTransaction,
+    ) -> Result<Transfer, Error> {
+        let contract_id = self.unscope(contract_id)?;
+        self.request_rename(
+            contract_id,
+            ticker,
+            name,
+            renomination_seal,
+            witness,
+        )
+    }
+
+    #[deprecated(since = "0.5.0", note = "use `simulate_transfer_scoped`")]
+    pub fn simulate_transfer(
+        &mut self,
+        contract_id: ContractId,
+        inputs: BTreeSet<OutPoint>,
+        payment: BTreeMap<SealEndpoint, AtomicValue>,
+        change: BTreeMap<SealDefinition, AtomicValue>,
+        change_outpoint: Option<OutPoint>,
+        witness: PartiallySignedTransaction,
+    ) -> Result<TransferPreview, Error> {
+        self.request_simulate_transfer(
+            contract_id,
+            inputs,
+            payment,
+            change,
+            change_outpoint,
+            witness,
+        )
+    }
+
+    pub fn simulate_transfer_scoped(
+        &mut self,
+        contract_id: &ChainScopedContractId,
+        inputs: BTreeSet<OutPoint>,
+        payment: BTreeMap<SealEndpoint, AtomicValue>,
+        change: BTreeMap<SealDefinition, AtomicValue>,
+        change_outpoint: Option<OutPoint>,
+        witness: PartiallySignedTransaction,
+    ) -> Result<TransferPreview, Error> {
+        let contract_id = self.unscope(contract_id)?;
+        self.request_simulate_transfer(
+            contract_id,
+            inputs,
+            payment,
+            change,
+            change_outpoint,
+            witness,
+        )
+    }
+
+    /// Previews the transfer, returning per-outpoint balances before and
+    /// after it; nothing is persisted by the daemon
+    pub fn preview_transfer(
+        &mut self,
+        mut transfer: TransferReq,
+    ) -> Result<BalanceDelta, Error> {
+        prepare_witness(&mut transfer.witness);
+        transfer.validate()?;
+
+        match &*self.command(Request::PreviewTransfer(transfer))? {
+            Reply::BalanceDelta(delta) => Ok(delta.clone()),
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+
+    /// Lists payments of the outbound transfers which are not known to be
+    /// accepted by the receivers yet
+    pub fn pending_transfers(&mut self) -> Result<Vec<PendingTransfer>, Error> {
+        match &*self.command(Request::ListPendingTransfers)? {
+            Reply::PendingTransfers(pending) => Ok(pending.clone()),
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+
+    /// Marks the transfer payment to the seal as accepted by the receiver,
+    /// removing it from [`Runtime::pending_transfers`]. Returns whether the
+    /// transfer was pending.
+    pub fn mark_transfer_accepted(
+        &mut self,
+        seal: SealEndpoint,
+    ) -> Result<bool, Error> {
+        match &*self
+            .command(Request::TransferAccepted(TransferAcceptedReq { seal }))?
+        {
+            Reply::Success => Ok(true),
+            Reply::Nothing => Ok(false),
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+
+    /// Generates blinded seal for receiving assets on the outpoint, returning
+    /// the concealed seal to be shared with the sender and the blinding
+    /// factor. The blinding factor is produced by a cryptographically secure
+    /// random number generator and is kept by the runtime, so
+    /// [`Runtime::accept`] reveals the outpoint without it being provided.
+    pub fn blind_receive(
+        &mut self,
+        outpoint: OutPoint,
+    ) -> Result<(OutpointHash, u64), Error> {
+        let reveal = OutpointReveal::from(outpoint);
+        let concealed = reveal.commit_conceal();
+        self.blinded_outpoints.insert(concealed, reveal);
+        Ok((concealed, reveal.blinding))
+    }
+
+    /// Accepts incoming consignment, revealing the provided outpoints and the
+    /// outpoints blinded with [`Runtime::blind_receive`] which are the
+    /// consignment endpoints
     pub fn accept(
         &mut self,
         consignment: Consignment,
-        reveal_outpoints: Vec<OutpointReveal>,
-    ) -> Result<(), Error> {
+        mut reveal_outpoints: Vec<OutpointReveal>,
+    ) -> Result<ConsignmentReport, Error> {
+        let blinded = self.blinded_endpoints(&consignment);
+        for reveal in &blinded {
+            if !reveal_outpoints.contains(reveal) {
+                reveal_outpoints.push(*reveal);
+            }
+        }
         let api = AcceptReq {
             consignment,
             reveal_outpoints,
         };
 
         match &*self.command(Request::Accept(api))? {
-            Reply::Failure(failure) => Err(Error::Reply(failure.clone())),
-            Reply::Success => {
+            Reply::ConsignmentReport(report) => {
                 info!("Accept command succeeded");
-                Ok(())
+                for reveal in blinded {
+                    self.blinded_outpoints.remove(&reveal.commit_conceal());
+                }
+                Ok(report.clone())
             }
             _ => Err(Error::UnexpectedResponse),
         }
     }
 
+    /// Returns secrets of the outpoints blinded with [`Runtime::blind_receive`]
+    /// which are endpoints of the consignment
+    fn blinded_endpoints(
+        &self,
+        consignment: &Consignment,
+    ) -> Vec<OutpointReveal> {
+        consignment
+            .endpoints
+            .iter()
+            .filter_map(|(_, endpoint)| match endpoint {
+                SealEndpoint::TxOutpoint(concealed) => {
+                    self.blinded_outpoints.get(concealed).copied()
+                }
+                SealEndpoint::WitnessVout { .. } => None,
+            })
+            .collect()
+    }
+
+    /// Writes the consignment, e.g. the one produced by [`Runtime::transfer`],
+    /// to the file in the format used by the command-line tool, so it can be
+    /// passed to the receiving RGB node with [`Runtime::import_consignment`]
+    pub fn export_consignment(
+        &self,
+        consignment: &Consignment,
+        path: &Path,
+    ) -> Result<(), Error> {
+        consignment.write_file(path).map_err(file_error)?;
+        Ok(())
+    }
+
+    /// Reads the consignment from the file written by
+    /// [`Runtime::export_consignment`] and accepts it
+    pub fn import_consignment(
+        &mut self,
+        path: &Path,
+        reveal_outpoints: Vec<OutpointReveal>,
+    ) -> Result<ConsignmentReport, Error> {
+        let consignment = Consignment::read_file(path).map_err(file_error)?;
+        self.accept(consignment, reveal_outpoints)
+    }
+
+    /// Reassembles the consignment delivered as a sequence of
+    /// [`Reply::ConsignmentChunk`] replies. Chunks may be provided in any
+    /// order; fails with [`Error::ChunkMissing`] reporting the first missing
+    /// chunk if some of them were not delivered.
+    pub fn reassemble_consignment(
+        chunks: impl IntoIterator<Item = ConsignmentChunk>,
+    ) -> Result<Consignment, Error> {
+        Ok(ConsignmentChunk::reassemble(chunks)?)
+    }
+
+    /// Validates the consignment without accepting it: the report lists the
+    /// validation failures and the transitions which passed validation, and
+    /// the asset data kept by the RGB node are not changed
     pub fn validate(
         &mut self,
         consignment: Consignment,
-    ) -> Result<rgb::validation::Status, Error> {
+    ) -> Result<ConsignmentReport, Error> {
         match &*self.command(Request::Validate(consignment))? {
-            Reply::Failure(failure) => Err(Error::Reply(failure.clone())),
-            Reply::ValidationStatus(status) => {
+            Reply::ConsignmentReport(report) => {
                 info!("Validation succeeded");
-                Ok(status.clone())
+                Ok(report.clone())
             }
             _ => Err(Error::UnexpectedResponse),
         }
@@ -166,7 +583,6 @@ impl Runtime {
 
     pub fn enclose(&mut self, disclosure: Disclosure) -> Result<(), Error> {
         match &*self.command(Request::Enclose(disclosure))? {
-            Reply::Failure(failure) => Err(Error::Reply(failure.clone())),
             Reply::Success => {
                 info!("Enclose command succeeded");
                 Ok(())
@@ -175,58 +591,1309 @@ impl Runtime {
         }
     }
 
+    #[deprecated(since = "0.5.0", note = "use `asset_allocations_scoped`")]
     pub fn asset_allocations(
         &mut self,
         contract_id: ContractId,
     ) -> Result<BTreeMap<OutPoint, Vec<AtomicValue>>, Error> {
-        match &*self.command(Request::Allocations(contract_id))? {
-            Reply::Failure(failure) => Err(Error::Reply(failure.clone())),
-            Reply::AssetAllocations(response) => Ok(response.clone()),
-            _ => Err(Error::UnexpectedResponse),
-        }
+        self.request_allocations(contract_id)
+    }
+
+    pub fn asset_allocations_scoped(
+        &mut self,
+        contract_id: &ChainScopedContractId,
+    ) -> Result<BTreeMap<OutPoint, Vec<AtomicValue>>, Error> {
+        let contract_id = self.unscope(contract_id)?;
+        self.request_allocations(contract_id)
     }
 
+    #[deprecated(since = "0.5.0", note = "use `outpoint_assets_scoped`")]
     pub fn outpoint_assets(
         &mut self,
         outpoint: OutPoint,
     ) -> Result<BTreeMap<ContractId, Vec<AtomicValue>>, Error> {
-        match &*self.command(Request::Assets(outpoint))? {
-            Reply::Failure(failure) => Err(Error::Reply(failure.clone())),
-            Reply::OutpointAssets(response) => Ok(response.clone()),
+        self.request_outpoint_assets(outpoint)
+    }
+
+    /// Lists allocations of all assets known to the RGB node on the outpoint,
+    /// with contract ids scoped to the chain used by the node
+    pub fn outpoint_assets_scoped(
+        &mut self,
+        outpoint: OutPoint,
+    ) -> Result<BTreeMap<ChainScopedContractId, Vec<AtomicValue>>, Error> {
+        let chain = self.config.network.clone();
+        Ok(self
+            .request_outpoint_assets(outpoint)?
+            .into_iter()
+            .map(|(contract_id, values)| {
+                (
+                    ChainScopedContractId::new(contract_id, chain.clone()),
+                    values,
+                )
+            })
+            .collect())
+    }
+
+    /// Lists all assets allocated to the outpoint with their total amounts on
+    /// it, ordered by contract id. Spending the outpoint without transferring
+    /// these assets burns them, so the wallet must check it before spending
+    /// any bitcoin UTXO.
+    pub fn assets_at_outpoint(
+        &mut self,
+        outpoint: OutPoint,
+    ) -> Result<Vec<(ContractId, AccountingAmount)>, Error> {
+        let allocated = self.request_outpoint_assets(outpoint)?;
+        if allocated.is_empty() {
+            return Ok(vec![]);
+        }
+        let assets = self
+            .request_asset_page(ListAssetsReq {
+                offset: 0,
+                limit: u32::MAX,
+                chain: None,
+            })?
+            .assets;
+        allocated
+            .into_iter()
+            .map(|(contract_id, values)| {
+                let asset = assets
+                    .iter()
+                    .find(|asset| *asset.id() == contract_id)
+                    .ok_or_else(|| {
+                        Error::UnknownAsset(contract_id.to_string())
+                    })?;
+                let value =
+                    values.into_iter().fold(0, |sum: AtomicValue, value| {
+                        sum.saturating_add(value)
+                    });
+                Ok((contract_id, asset.amount(value)))
+            })
+            .collect()
+    }
+
+    #[deprecated(since = "0.5.0", note = "use `forget_asset_scoped`")]
+    pub fn forget_asset(
+        &mut self,
+        contract_id: ContractId,
+        force: bool,
+    ) -> Result<(), Error> {
+        self.request_forget_asset(contract_id, force)
+    }
+
+    /// Stops tracking the asset, removing it from the daemon asset cache.
+    /// Assets with non-zero known balance are removed only if `force` is set;
+    /// otherwise [`Error::AssetHasBalance`] is returned.
+    pub fn forget_asset_scoped(
+        &mut self,
+        contract_id: &ChainScopedContractId,
+        force: bool,
+    ) -> Result<(), Error> {
+        let contract_id = self.unscope(contract_id)?;
+        self.request_forget_asset(contract_id, force)
+    }
+
+    #[deprecated(since = "0.5.0", note = "use `set_label_scoped`")]
+    pub fn set_label(
+        &mut self,
+        contract_id: ContractId,
+        label: Option<String>,
+    ) -> Result<(), Error> {
+        self.request_set_label(contract_id, label)
+    }
+
+    /// Sets private user label of the asset (e.g. "company treasury"), or
+    /// removes it if `None` is given. Labels are kept by the RGB node
+    /// separately from the asset data and do not affect them.
+    pub fn set_label_scoped(
+        &mut self,
+        contract_id: &ChainScopedContractId,
+        label: Option<String>,
+    ) -> Result<(), Error> {
+        let contract_id = self.unscope(contract_id)?;
+        self.request_set_label(contract_id, label)
+    }
+
+    #[deprecated(since = "0.5.0", note = "use `label_scoped`")]
+    pub fn label(
+        &mut self,
+        contract_id: ContractId,
+    ) -> Result<Option<String>, Error> {
+        self.request_label(contract_id)
+    }
+
+    /// Returns private user label of the asset, if any
+    pub fn label_scoped(
+        &mut self,
+        contract_id: &ChainScopedContractId,
+    ) -> Result<Option<String>, Error> {
+        let contract_id = self.unscope(contract_id)?;
+        self.request_label(contract_id)
+    }
+
+    /// Makes the asset, imported from its genesis without any known
+    /// allocations, spendable. Until then transfers of the asset fail with
+    /// [`Error::WatchOnly`].
+    pub fn clear_watch_only(
+        &mut self,
+        contract_id: &ChainScopedContractId,
+    ) -> Result<(), Error> {
+        let contract_id = self.unscope(contract_id)?;
+        match &*self.command(Request::ClearWatchOnly(ClearWatchOnlyReq {
+            contract_id,
+        }))? {
+            Reply::Success => Ok(()),
             _ => Err(Error::UnexpectedResponse),
         }
     }
 
+    #[deprecated(since = "0.5.0", note = "use `export_asset_scoped`")]
     pub fn export_asset(
         &mut self,
         asset_id: ContractId,
     ) -> Result<Genesis, Error> {
-        match &*self.command(Request::ExportAsset(asset_id))? {
-            Reply::Failure(failure) => Err(Error::Reply(failure.clone())),
-            Reply::Genesis(response) => Ok(response.clone()),
-            _ => Err(Error::UnexpectedResponse),
-        }
+        self.request_genesis(asset_id)
+    }
+
+    /// Returns genesis of the known asset for sharing it with other RGB nodes,
+    /// which import it with [`Runtime::import_asset`]; genesis display
+    /// representation is its Bech32 encoding. Fails with
+    /// [`Error::UnknownAsset`] if the asset is not known to the RGB node.
+    pub fn export_asset_scoped(
+        &mut self,
+        contract_id: &ChainScopedContractId,
+    ) -> Result<Genesis, Error> {
+        let contract_id = self.unscope(contract_id)?;
+        self.request_genesis(contract_id)
     }
 
+    /// Imports asset from its genesis. Import is idempotent: if the asset is
+    /// already known, its existing data are returned.
     pub fn import_asset(&mut self, genesis: Genesis) -> Result<Asset, Error> {
         match &*self.command(Request::ImportAsset(genesis))? {
-            Reply::Failure(failure) => Err(Error::Reply(failure.clone())),
             Reply::Asset(asset) => {
                 info!("Asset import succeeded");
                 Ok(asset.clone())
             }
+            Reply::AlreadyKnown(asset) => {
+                info!("Asset is already known");
+                Ok(asset.clone())
+            }
             _ => Err(Error::UnexpectedResponse),
         }
     }
 
+    /// Imports asset from the Bech32 representation of its genesis, as
+    /// exported with [`Runtime::export_asset_scoped`]. The string is checked to have
+    /// a valid checksum and the genesis Bech32 prefix before being sent to
+    /// the RGB node.
+    pub fn import_genesis_str(&mut self, s: &str) -> Result<Asset, Error> {
+        let genesis = Genesis::from_str(s.trim())?;
+        self.import_asset(genesis)
+    }
+
     pub fn list_assets(
         &mut self,
         data_format: FileFormat,
     ) -> Result<reply::SyncFormat, Error> {
         match &*self.command(Request::Sync(data_format))? {
-            Reply::Failure(failure) => Err(Error::Reply(failure.clone())),
             Reply::Sync(response) => Ok(response.clone()),
             _ => Err(Error::UnexpectedResponse),
         }
     }
+
+    /// Lists known assets page by page; assets are ordered by their contract
+    /// ids, so the pages are stable unless the set of the known assets
+    /// changes between the requests
+    pub fn list_assets_paged(
+        &mut self,
+        offset: u32,
+        limit: u32,
+    ) -> Result<reply::AssetPage, Error> {
+        self.request_asset_page(ListAssetsReq {
+            offset,
+            limit,
+            chain: None,
+        })
+    }
+
+    /// Lists all known assets issued on the given chain
+    pub fn list_assets_on_chain(
+        &mut self,
+        chain: Chain,
+    ) -> Result<Vec<Asset>, Error> {
+        let page = self.request_asset_page(ListAssetsReq {
+            offset: 0,
+            limit: u32::MAX,
+            chain: Some(chain),
+        })?;
+        Ok(page.assets)
+    }
+
+    /// Aggregates known circulating supply of all known assets by their
+    /// tickers; see [`supply_by_ticker`] on the ambiguity of the tickers
+    pub fn supply_by_ticker(
+        &mut self,
+    ) -> Result<BTreeMap<String, AccountingAmount>, Error> {
+        let page = self.request_asset_page(ListAssetsReq {
+            offset: 0,
+            limit: u32::MAX,
+            chain: None,
+        })?;
+        Ok(supply_by_ticker(&page.assets))
+    }
+
+    /// Extracts contract id for the request, failing with
+    /// [`Error::WrongNetwork`] if the contract is issued on the chain other
+    /// than the one used by the RGB node
+    fn unscope(
+        &self,
+        contract_id: &ChainScopedContractId,
+    ) -> Result<ContractId, Error> {
+        if *contract_id.chain() != self.config.network {
+            return Err(Error::WrongNetwork);
+        }
+        Ok(contract_id.contract_id())
+    }
+
+    fn request_transfer(
+        &mut self,
+        contract_id: ContractId,
+        inputs: BTreeSet<OutPoint>,
+        payment: BTreeMap<SealEndpoint, AtomicValue>,
+        change: BTreeMap<SealDefinition, AtomicValue>,
+        change_outpoint: Option<OutPoint>,
+        mut witness: PartiallySignedTransaction,
+    ) -> Result<Transfer, Error> {
+        prepare_witness(&mut witness);
+
+        let api = TransferReq {
+            witness,
+            contract_id,
+            inputs,
+            payment,
+            change,
+            change_outpoint,
+            idempotency_key: None,
+        };
+        api.validate()?;
+        let allocations = self.request_allocations(contract_id)?;
+        api.validate_inputs(&allocations)?;
+
+        match &*self.command(Request::Transfer(api))? {
+            Reply::Transfer(transfer) => {
+                info!("Transfer succeeded");
+
+                Ok(transfer.clone())
+            }
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+
+    fn request_consolidate(
+        &mut self,
+        contract_id: ContractId,
+        threshold: AtomicValue,
+        seal: SealDefinition,
+        witness: PartiallySignedTransaction,
+    ) -> Result<Transfer, Error> {
+        let allocations = self.request_allocations(contract_id)?;
+        let (inputs, amount) = allocations
+            .iter()
+            .filter(|(_, amounts)| {
+                amounts.iter().any(|amount| *amount < threshold)
+            })
+            .fold(
+                (BTreeSet::new(), 0 as AtomicValue),
+                |(mut inputs, sum), (outpoint, amounts)| {
+                    inputs.insert(*outpoint);
+                    let sum = amounts
+                        .iter()
+                        .fold(sum, |sum, amount| sum.saturating_add(*amount));
+                    (inputs, sum)
+                },
+            );
+        self.request_transfer(
+            contract_id,
+            inputs,
+            bmap! {},
+            bmap! { seal => amount },
+            None,
+            witness,
+        )
+    }
+
+    fn request_inflate(
+        &mut self,
+        contract_id: ContractId,
+        inflation: OutPoint,
+        amount: AtomicValue,
+        allocation: BTreeMap<SealDefinition, AtomicValue>,
+        inflation_change: Option<SealDefinition>,
+        mut witness: PartiallySignedTransaction,
+    ) -> Result<Transfer, Error> {
+        prepare_witness(&mut witness);
+
+        let api = InflateReq {
+            contract_id,
+            witness,
+            inflation,
+            amount,
+            allocation,
+            inflation_change,
+        };
+        api.validate()?;
+
+        match &*self.command(Request::Inflate(api))? {
+            Reply::Transfer(transfer) => {
+                info!("Inflation succeeded");
+
+                Ok(transfer.clone())
+            }
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+
+    fn request_burn(
+        &mut self,
+        contract_id: ContractId,
+        outpoints: BTreeSet<OutPoint>,
+        proof_seal: Option<SealDefinition>,
+        mut witness: PartiallySignedTransaction,
+    ) -> Result<Transfer, Error> {
+        prepare_witness(&mut witness);
+
+        let api = BurnReq {
+            contract_id,
+            witness,
+            outpoints,
+            proof_seal,
+        };
+        api.validate()?;
+        let allocations = self.request_allocations(contract_id)?;
+        api.validate_allocations(&allocations)?;
+
+        match &*self.command(Request::Burn(api))? {
+            Reply::Transfer(transfer) => {
+                info!("Burn succeeded");
+
+                Ok(transfer.clone())
+            }
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+
+    fn request_rename(
+        &mut self,
+        contract_id: ContractId,
+        ticker: Option<String>,
+        name: Option<String>,
+        renomination_seal: Option<SealDefinition>,
+        mut witness: PartiallySignedTransaction,
+    ) -> Result<Transfer, Error> {
+        prepare_witness(&mut witness);
+
+        let api = RenominateReq {
+            contract_id,
+            witness,
+            ticker,
+            name,
+            renomination_seal,
+        };
+        api.validate()?;
+        let genesis = self.request_genesis(contract_id)?;
+        renomination_right(&genesis)
+            .ok_or(RenominateError::NoRenominationRight)?;
+
+        match &*self.command(Request::Renominate(api))? {
+            Reply::Transfer(transfer) => {
+                info!("Renomination succeeded");
+
+                Ok(transfer.clone())
+            }
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+
+    fn request_simulate_transfer(
+        &mut self,
+        contract_id: ContractId,
+        inputs: BTreeSet<OutPoint>,
+        payment: BTreeMap<SealEndpoint, AtomicValue>,
+        change: BTreeMap<SealDefinition, AtomicValue>,
+        change_outpoint: Option<OutPoint>,
+        witness: PartiallySignedTransaction,
+    ) -> Result<TransferPreview, Error> {
+        let api = TransferReq {
+            witness,
+            contract_id,
+            inputs,
+            payment,
+            change,
+            change_outpoint,
+            idempotency_key: None,
+        };
+        api.validate()?;
+
+        match &*self.command(Request::TransferDryRun(api))? {
+            Reply::TransferPreview(preview) => Ok(preview.clone()),
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+
+    fn request_outpoint_assets(
+        &mut self,
+        outpoint: OutPoint,
+    ) -> Result<BTreeMap<ContractId, Vec<AtomicValue>>, Error> {
+        match &*self.command(Request::Assets(outpoint))? {
+            Reply::OutpointAssets(response) => Ok(response.clone()),
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+
+    fn request_allocations(
+        &mut self,
+        contract_id: ContractId,
+    ) -> Result<BTreeMap<OutPoint, Vec<AtomicValue>>, Error> {
+        match &*self.command(Request::Allocations(contract_id))? {
+            Reply::AssetAllocations(response) => Ok(response.clone()),
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+
+    fn request_forget_asset(
+        &mut self,
+        contract_id: ContractId,
+        force: bool,
+    ) -> Result<(), Error> {
+        match &*self.command(Request::ForgetAsset(ForgetAssetReq {
+            contract_id,
+            force,
+        }))? {
+            Reply::Success => Ok(()),
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+
+    fn request_set_label(
+        &mut self,
+        contract_id: ContractId,
+        label: Option<String>,
+    ) -> Result<(), Error> {
+        match &*self
+            .command(Request::SetLabel(SetLabelReq { contract_id, label }))?
+        {
+            Reply::Success => Ok(()),
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+
+    fn request_label(
+        &mut self,
+        contract_id: ContractId,
+    ) -> Result<Option<String>, Error> {
+        match &*self.command(Request::Label(LabelReq { contract_id }))? {
+            Reply::Label(label) => Ok(label.clone()),
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+
+    fn request_genesis(
+        &mut self,
+        contract_id: ContractId,
+    ) -> Result<Genesis, Error> {
+        match &*self.command(Request::ExportAsset(contract_id))? {
+            Reply::Genesis(response) => Ok(response.clone()),
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+
+    fn request_asset_page(
+        &mut self,
+        list: ListAssetsReq,
+    ) -> Result<reply::AssetPage, Error> {
+        match &*self.command(Request::ListAssets(list))? {
+            Reply::AssetPage(page) => Ok(page.clone()),
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+}
+
+/// Extracts name of the request for logging: its display representation up to
+/// the list of the request arguments
+fn command_name(command: &Request) -> String {
+    let mut name = command.to_string();
+    if let Some(pos) = name.find('(') {
+        name.truncate(pos);
+    }
+    name
+}
+
+/// Distinguishes file access errors from the errors in the file data
+fn file_error(err: strict_encoding::Error) -> Error {
+    match err {
+        strict_encoding::Error::Io(err) => Error::Io(err),
+        err => Error::Encoding(err),
+    }
+}
+
+/// Adds to the witness transaction outputs information on the keys used for
+/// LNPBP1/2 commitments
+fn prepare_witness(witness: &mut PartiallySignedTransaction) {
+    for (index, output) in &mut witness.outputs.iter_mut().enumerate() {
+        if let Some(key) = output.bip32_derivation.keys().next() {
+            let key = key.clone();
+            output.proprietary.insert(
+                ProprietaryKey {
+                    prefix: b"RGB".to_vec(),
+                    subtype: PSBT_OUT_PUBKEY,
+                    key: vec![],
+                },
+                key.key.serialize().to_vec(),
+            );
+            debug!("Output #{} commitment key will be {}", index, key);
+        } else {
+            warn!(
+                "No public key information found for output #{}; \
+                LNPBP1/2 commitment will be impossible.\
+                In order to allow commitment pls add known keys derivation \
+                information to PSBT output map",
+                index
+            );
+        }
+    }
+    trace!("{:?}", witness);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::error::{
+        BootstrapError, ServiceError, ServiceErrorDomain, ServiceErrorSource,
+    };
+    use crate::i9n::Config;
+    use crate::rpc::fungible::WIRE_FORMATS;
+    use crate::util::AssetExt;
+    use bitcoin::hashes::hex::FromHex;
+    use bitcoin::{Transaction, Txid};
+    use internet2::{
+        session, CreateUnmarshaller, Session, Unmarshall, ZmqSocketAddr,
+        ZmqType,
+    };
+    use microservices::FileFormat;
+    use rgb::{validation, Node};
+    use std::sync::Mutex;
+    use std::{env, fs, thread};
+
+    /// Runs mock daemon sending the given replies to the requests and
+    /// connects the runtime to it
+    fn serve(
+        name: &str,
+        replies: Vec<Reply>,
+    ) -> (Runtime, thread::JoinHandle<()>) {
+        let endpoint = ZmqSocketAddr::Inproc(name.to_string());
+        let mut server = session::Raw::with_zmq_unencrypted(
+            ZmqType::Rep,
+            &endpoint,
+            None,
+            None,
+        )
+        .unwrap();
+        let daemon = thread::spawn(move || {
+            for reply in replies {
+                server.recv_raw_message().unwrap();
+                server.send_raw_message(&reply.serialize()).unwrap();
+            }
+        });
+        let runtime = Runtime::init(
+            Config::builder()
+                .endpoint(format!("inproc://{}", name))
+                .embedded(false)
+                .build()
+                .unwrap(),
+        )
+        .unwrap();
+        (runtime, daemon)
+    }
+
+    fn witness() -> PartiallySignedTransaction {
+        PartiallySignedTransaction::from_unsigned_tx(Transaction {
+            version: 2,
+            lock_time: 0,
+            input: vec![],
+            output: vec![],
+        })
+        .unwrap()
+    }
+
+    fn genesis() -> Genesis {
+        let txid = Txid::from_hex(
+            "fc63f797af718cc5a11988f69507701d5fe84e58cdd900e1b02856c0ea5a058a",
+        )
+        .unwrap();
+        rgb20::issue(
+            Chain::Testnet3,
+            s!("TST"),
+            s!("Test asset"),
+            None,
+            2,
+            vec![(OutPoint::new(txid, 0), 1000)],
+            bmap! {},
+            None,
+            None,
+        )
+        .unwrap()
+        .1
+    }
+
+    struct CaptureLogger(Arc<Mutex<Vec<String>>>);
+
+    impl log::Log for CaptureLogger {
+        fn enabled(&self, _: &log::Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &log::Record) {
+            if record.target() == "rgb_node::i9n::fungible" {
+                self.0.lock().unwrap().push(record.args().to_string());
+            }
+        }
+
+        fn flush(&self) {}
+    }
+
+    #[test]
+    fn test_command_logging() {
+        let records = Arc::new(Mutex::new(vec![]));
+        log::set_boxed_logger(Box::new(CaptureLogger(records.clone())))
+            .unwrap();
+        log::set_max_level(log::LevelFilter::Trace);
+
+        let (mut runtime, daemon) =
+            serve("rgb-command-logging", vec![Reply::Success]);
+        runtime
+            .command(Request::Sync(FileFormat::StrictEncode))
+            .unwrap();
+        daemon.join().unwrap();
+
+        let records = records.lock().unwrap();
+        assert!(records
+            .iter()
+            .any(|record| record.starts_with("Sending `sync` request of ")));
+        assert!(records
+            .iter()
+            .any(|record| record.starts_with("`sync` request completed in ")));
+        assert!(records
+            .iter()
+            .any(|record| record.starts_with("Got `sync` reply of ")));
+    }
+
+    #[test]
+    fn test_consignment_file_roundtrip() {
+        let consignment = Consignment::with(genesis(), vec![], vec![], vec![]);
+        let contract_id = consignment.genesis.contract_id();
+
+        let endpoint = ZmqSocketAddr::Inproc(s!("rgb-consignment-file"));
+        let mut server = session::Raw::with_zmq_unencrypted(
+            ZmqType::Rep,
+            &endpoint,
+            None,
+            None,
+        )
+        .unwrap();
+        let expected = consignment.clone();
+        let daemon = thread::spawn(move || {
+            let data = server.recv_raw_message().unwrap();
+            let request =
+                Request::create_unmarshaller().unmarshall(&data).unwrap();
+            match &*request {
+                Request::Accept(accept) => {
+                    assert_eq!(accept.consignment, expected)
+                }
+                _ => panic!("unexpected request"),
+            }
+            let report = reply::ConsignmentReport {
+                contract_id,
+                status: validation::Status::default(),
+                validated_transitions: vec![],
+                resolved_seals: vec![],
+                gained_allocations: bmap! {},
+            };
+            server
+                .send_raw_message(&Reply::ConsignmentReport(report).serialize())
+                .unwrap();
+        });
+
+        let mut runtime = Runtime::init(
+            Config::builder()
+                .endpoint("inproc://rgb-consignment-file")
+                .embedded(false)
+                .build()
+                .unwrap(),
+        )
+        .unwrap();
+        let path = env::temp_dir().join("rgb-i9n-consignment.rgbc");
+        runtime.export_consignment(&consignment, &path).unwrap();
+        let report = runtime.import_consignment(&path, vec![]).unwrap();
+        assert_eq!(report.contract_id, contract_id);
+        daemon.join().unwrap();
+
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(
+            runtime.import_consignment(&path, vec![]),
+            Err(Error::Io(_))
+        ));
+    }
+
+    #[test]
+    fn test_reassemble_consignment() {
+        use strict_encoding::strict_serialize;
+
+        let consignment = Consignment::with(genesis(), vec![], vec![], vec![]);
+        let size = strict_serialize(&consignment).unwrap().len();
+        let chunks = ConsignmentChunk::split(&consignment, size / 3 + 1);
+        assert_eq!(chunks.len(), 3);
+        assert!(chunks.iter().all(|chunk| chunk.total == 3));
+
+        assert_eq!(
+            Runtime::reassemble_consignment(chunks.clone()).unwrap(),
+            consignment
+        );
+        let shuffled =
+            vec![chunks[2].clone(), chunks[0].clone(), chunks[1].clone()];
+        assert_eq!(
+            Runtime::reassemble_consignment(shuffled).unwrap(),
+            consignment
+        );
+
+        let gap = vec![chunks[2].clone(), chunks[0].clone()];
+        assert!(matches!(
+            Runtime::reassemble_consignment(gap),
+            Err(Error::ChunkMissing(1))
+        ));
+        assert!(matches!(
+            Runtime::reassemble_consignment(vec![]),
+            Err(Error::ChunkMissing(0))
+        ));
+        let duplicate = vec![chunks[0].clone(), chunks[0].clone()];
+        assert!(matches!(
+            Runtime::reassemble_consignment(duplicate),
+            Err(Error::ChunkMismatch)
+        ));
+    }
+
+    #[test]
+    fn test_chunked_transfer() {
+        use strict_encoding::strict_serialize;
+
+        let name = "rgb-chunked-transfer";
+        let asset = Asset::from_genesis(&genesis()).unwrap();
+        let input = *asset.known_allocations()[0].outpoint();
+        let consignment = Consignment::with(genesis(), vec![], vec![], vec![]);
+        let size = strict_serialize(&consignment).unwrap().len();
+        let (chunked, chunks) = reply::Transfer {
+            consignment: consignment.clone(),
+            disclosure: Disclosure::default(),
+            witness: witness(),
+            change_outpoint: None,
+        }
+        .into_chunks(size / 3 + 1);
+        assert_eq!(chunks.len(), 3);
+
+        let endpoint = ZmqSocketAddr::Inproc(name.to_string());
+        let mut server = session::Raw::with_zmq_unencrypted(
+            ZmqType::Rep,
+            &endpoint,
+            None,
+            None,
+        )
+        .unwrap();
+        let daemon = thread::spawn(move || {
+            let unmarshaller = Request::create_unmarshaller();
+            let mut requested = vec![];
+            for _ in 0..2 + chunks.len() {
+                let raw = server.recv_raw_message().unwrap();
+                let reply = match &*unmarshaller.unmarshall(&raw).unwrap() {
+                    Request::Allocations(_) => {
+                        Reply::AssetAllocations(bmap! { input => vec![1000] })
+                    }
+                    Request::Transfer(_) => {
+                        Reply::ChunkedTransfer(chunked.clone())
+                    }
+                    Request::ConsignmentChunk(chunk) => {
+                        requested.push(chunk.seq);
+                        Reply::ConsignmentChunk(
+                            chunks[chunk.seq as usize].clone(),
+                        )
+                    }
+                    request => panic!("unexpected request {}", request),
+                };
+                server.send_raw_message(&reply.serialize()).unwrap();
+            }
+            requested
+        });
+        let mut runtime = Runtime::init(
+            Config::builder()
+                .endpoint(format!("inproc://{}", name))
+                .embedded(false)
+                .build()
+                .unwrap(),
+        )
+        .unwrap();
+
+        let contract_id = ChainScopedContractId::new(
+            *asset.id(),
+            runtime.config.network.clone(),
+        );
+        let transfer = runtime
+            .transfer_scoped(
+                &contract_id,
+                bset! { input },
+                bmap! {
+                    SealEndpoint::TxOutpoint(
+                        OutpointReveal::from(input).commit_conceal()
+                    ) => 1000
+                },
+                bmap! {},
+                None,
+                witness(),
+            )
+            .unwrap();
+        assert_eq!(transfer.consignment, consignment);
+        assert_eq!(daemon.join().unwrap(), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_ping() {
+        let (mut runtime, daemon) =
+            serve("rgb-ping", vec![Reply::Pong, Reply::Success]);
+
+        assert!(runtime.ping().unwrap() > Duration::from_secs(0));
+        assert!(matches!(runtime.ping(), Err(Error::UnexpectedResponse)));
+        daemon.join().unwrap();
+    }
+
+    #[test]
+    fn test_server_info() {
+        let info = ServerInfo {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            schema_ids: vec![rgb20::schema::schema().schema_id()],
+            chain: Chain::Testnet3,
+        };
+        let (mut runtime, daemon) =
+            serve("rgb-server-info", vec![Reply::Info(info.clone())]);
+
+        let reported = runtime.server_info().unwrap();
+        assert_eq!(reported.chain, Chain::Testnet3);
+        assert_eq!(reported, info);
+        daemon.join().unwrap();
+    }
+
+    #[test]
+    fn test_wire_format_negotiation() {
+        let negotiate = |name: &str,
+                         offered: Vec<FileFormat>,
+                         format: FileFormat,
+                         requests: usize| {
+            let endpoint = ZmqSocketAddr::Inproc(name.to_string());
+            let mut server = session::Raw::with_zmq_unencrypted(
+                ZmqType::Rep,
+                &endpoint,
+                None,
+                None,
+            )
+            .unwrap();
+            let expected = offered.clone();
+            let daemon = thread::spawn(move || {
+                let unmarshaller = Request::create_unmarshaller();
+                let raw = server.recv_raw_message().unwrap();
+                match &*unmarshaller.unmarshall(&raw).unwrap() {
+                    Request::Hello(formats) => assert_eq!(formats, &expected),
+                    _ => panic!("handshake is expected"),
+                }
+                server
+                    .send_raw_message(
+                        &Reply::WireFormat(reply::WireFormat { format })
+                            .serialize(),
+                    )
+                    .unwrap();
+                for _ in 0..requests {
+                    let raw = server.recv_raw_message().unwrap();
+                    assert_eq!(wire::detect_format(&raw), format);
+                    let reply =
+                        match &*wire::decode(&unmarshaller, &raw, format)
+                            .unwrap()
+                        {
+                            Request::Ping => Reply::Pong,
+                            Request::Label(label) => Reply::Label(Some(
+                                label.contract_id.to_string(),
+                            )),
+                            _ => panic!("unexpected request"),
+                        };
+                    server
+                        .send_raw_message(&wire::encode(&reply, format))
+                        .unwrap();
+                }
+            });
+            let runtime = Runtime::init(
+                Config::builder()
+                    .endpoint(format!("inproc://{}", name))
+                    .embedded(false)
+                    .timeout(Duration::from_millis(200))
+                    .negotiate_format(true)
+                    .wire_formats(offered)
+                    .build()
+                    .unwrap(),
+            );
+            (runtime, daemon)
+        };
+
+        let (runtime, daemon) = negotiate(
+            "rgb-hello",
+            WIRE_FORMATS.to_vec(),
+            FileFormat::StrictEncode,
+            1,
+        );
+        let mut runtime = runtime.unwrap();
+        assert_eq!(runtime.wire_format(), FileFormat::StrictEncode);
+        runtime.ping().unwrap();
+        daemon.join().unwrap();
+
+        // Daemon selects non-default format preferred by the client, which is
+        // then used for all requests and replies
+        let (runtime, daemon) = negotiate(
+            "rgb-hello-json",
+            vec![FileFormat::Json, FileFormat::StrictEncode],
+            FileFormat::Json,
+            2,
+        );
+        let mut runtime = runtime.unwrap();
+        assert_eq!(runtime.wire_format(), FileFormat::Json);
+        runtime.ping().unwrap();
+        let contract_id = ContractId::default();
+        assert_eq!(
+            runtime.request_label(contract_id).unwrap(),
+            Some(contract_id.to_string())
+        );
+        daemon.join().unwrap();
+
+        // Daemon selects a format which was not offered
+        let (runtime, daemon) = negotiate(
+            "rgb-hello-unoffered",
+            vec![FileFormat::StrictEncode],
+            FileFormat::Json,
+            0,
+        );
+        assert!(matches!(runtime, Err(BootstrapError::ArgParseError(_))));
+        daemon.join().unwrap();
+    }
+
+    #[test]
+    fn test_import_genesis_str() {
+        let genesis = genesis();
+        let asset = Asset::from_genesis(&genesis).unwrap();
+        let (mut runtime, daemon) = serve(
+            "rgb-import-genesis-str",
+            vec![
+                Reply::Asset(asset.clone()),
+                Reply::AlreadyKnown(asset.clone()),
+            ],
+        );
+
+        let bech32 = genesis.to_string();
+        assert!(bech32.starts_with("genesis1"));
+        let imported = runtime.import_genesis_str(&bech32).unwrap();
+        assert!(imported.semantic_eq(&asset));
+        // Repeated import returns the known asset
+        let imported = runtime.import_genesis_str(&bech32).unwrap();
+        assert!(imported.semantic_eq(&asset));
+
+        // Corrupted checksum is detected before sending the request
+        let mut corrupted = bech32.clone();
+        let last = corrupted.pop().unwrap();
+        corrupted.push(if last == 'q' { 'p' } else { 'q' });
+        assert!(matches!(
+            runtime.import_genesis_str(&corrupted),
+            Err(Error::Bech32(_))
+        ));
+        // Other Bech32-encoded data are not accepted as a genesis
+        assert!(matches!(
+            runtime.import_genesis_str(&genesis.contract_id().to_string()),
+            Err(Error::Bech32(_))
+        ));
+        daemon.join().unwrap();
+    }
+
+    #[test]
+    fn test_blind_receive() {
+        let (mut runtime, daemon) = serve("rgb-blind-receive", vec![]);
+        let txid = Txid::from_hex(
+            "fc63f797af718cc5a11988f69507701d5fe84e58cdd900e1b02856c0ea5a058a",
+        )
+        .unwrap();
+        let outpoint = OutPoint::new(txid, 1);
+
+        let (concealed1, blinding1) = runtime.blind_receive(outpoint).unwrap();
+        let (concealed2, blinding2) = runtime.blind_receive(outpoint).unwrap();
+        assert_ne!(blinding1, blinding2);
+        assert_ne!(concealed1, concealed2);
+        let reveal = OutpointReveal {
+            blinding: blinding1,
+            txid: outpoint.txid,
+            vout: outpoint.vout,
+        };
+        assert_eq!(reveal.commit_conceal(), concealed1);
+
+        // Only secrets of the consignment endpoints are revealed
+        let consignment = Consignment::with(
+            genesis(),
+            vec![(genesis().node_id(), SealEndpoint::TxOutpoint(concealed1))],
+            vec![],
+            vec![],
+        );
+        assert_eq!(runtime.blinded_endpoints(&consignment), vec![reveal]);
+        daemon.join().unwrap();
+    }
+
+    #[test]
+    fn test_pending_transfers() {
+        let pending = PendingTransfer {
+            contract_id: genesis().contract_id(),
+            amount: 100,
+            seal: SealEndpoint::WitnessVout {
+                vout: 0,
+                blinding: 1,
+            },
+            created_at: chrono::NaiveDateTime::from_timestamp(1_600_000_000, 0),
+        };
+        let (mut runtime, daemon) = serve(
+            "rgb-pending-transfers",
+            vec![
+                Reply::PendingTransfers(vec![pending]),
+                Reply::Success,
+                Reply::Nothing,
+                Reply::PendingTransfers(vec![]),
+            ],
+        );
+
+        assert_eq!(runtime.pending_transfers().unwrap(), vec![pending]);
+        assert!(runtime.mark_transfer_accepted(pending.seal).unwrap());
+        assert!(!runtime.mark_transfer_accepted(pending.seal).unwrap());
+        assert!(runtime.pending_transfers().unwrap().is_empty());
+        daemon.join().unwrap();
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_forget_asset() {
+        let has_balance = reply::Failure::from(ServiceError {
+            domain: ServiceErrorDomain::AssetHasBalance(s!("asset")),
+            service: ServiceErrorSource::Contract(s!("fungible")),
+        });
+        let (mut runtime, daemon) = serve(
+            "rgb-forget-asset",
+            vec![Reply::Failure(has_balance), Reply::Success],
+        );
+
+        let contract_id = genesis().contract_id();
+        assert!(matches!(
+            runtime.forget_asset(contract_id, false),
+            Err(Error::AssetHasBalance(_))
+        ));
+        assert!(runtime.forget_asset(contract_id, true).is_ok());
+        daemon.join().unwrap();
+    }
+
+    #[test]
+    fn test_assets_at_outpoint() {
+        let txid = Txid::from_hex(
+            "fc63f797af718cc5a11988f69507701d5fe84e58cdd900e1b02856c0ea5a058a",
+        )
+        .unwrap();
+        let outpoint = OutPoint::new(txid, 0);
+        let first = Asset::from_genesis(&genesis()).unwrap();
+        let second = rgb20::issue(
+            Chain::Testnet3,
+            s!("SND"),
+            s!("Second asset"),
+            None,
+            8,
+            vec![(outpoint, 300), (outpoint, 200)],
+            bmap! {},
+            None,
+            None,
+        )
+        .unwrap()
+        .0;
+        let page = reply::AssetPage {
+            assets: vec![first.clone(), second.clone()],
+            offset: 0,
+            total: 2,
+        };
+        let (mut runtime, daemon) = serve(
+            "rgb-assets-at-outpoint",
+            vec![
+                Reply::OutpointAssets(bmap! {
+                    *first.id() => vec![1000],
+                    *second.id() => vec![300, 200]
+                }),
+                Reply::AssetPage(page),
+            ],
+        );
+
+        let mut expected = vec![
+            (*first.id(), first.amount(1000)),
+            (*second.id(), second.amount(500)),
+        ];
+        expected.sort_by_key(|(contract_id, _)| *contract_id);
+        let assets = runtime.assets_at_outpoint(outpoint).unwrap();
+        assert_eq!(assets, expected);
+        for (contract_id, amount) in assets {
+            let asset = if contract_id == *first.id() {
+                &first
+            } else {
+                &second
+            };
+            assert_eq!(amount.decimal_precision(), *asset.decimal_precision());
+        }
+        daemon.join().unwrap();
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_export_asset() {
+        let genesis = genesis();
+        let asset = Asset::from_genesis(&genesis).unwrap();
+        let unknown = reply::Failure::from(ServiceError {
+            domain: ServiceErrorDomain::UnknownAsset(s!("asset")),
+            service: ServiceErrorSource::Contract(s!("fungible")),
+        });
+        let (mut runtime, daemon) = serve(
+            "rgb-export-asset",
+            vec![Reply::Genesis(genesis), Reply::Failure(unknown)],
+        );
+
+        let exported = runtime.export_asset(*asset.id()).unwrap();
+        assert!(Asset::from_genesis(&exported).unwrap().semantic_eq(&asset));
+        assert!(matches!(
+            runtime.export_asset(ContractId::default()),
+            Err(Error::UnknownAsset(_))
+        ));
+        daemon.join().unwrap();
+    }
+
+    #[test]
+    fn test_chain_scoped_contract_id() {
+        let asset = Asset::from_genesis(&genesis()).unwrap();
+        let (mut runtime, daemon) = serve(
+            "rgb-chain-scoped-contract-id",
+            vec![
+                Reply::Label(Some(s!("company treasury"))),
+                Reply::Success,
+                Reply::OutpointAssets(bmap! { *asset.id() => vec![1000] }),
+            ],
+        );
+        assert_ne!(runtime.config.network, Chain::Testnet3);
+
+        // Testnet asset can't be queried from the node on another chain, and
+        // no request is sent to the daemon
+        let testnet_id = ChainScopedContractId::from(&asset);
+        assert_eq!(testnet_id.chain(), &Chain::Testnet3);
+        assert!(matches!(
+            runtime.asset_allocations_scoped(&testnet_id),
+            Err(Error::WrongNetwork)
+        ));
+        assert!(matches!(
+            runtime.label_scoped(&testnet_id),
+            Err(Error::WrongNetwork)
+        ));
+        assert!(matches!(
+            runtime.set_label_scoped(&testnet_id, None),
+            Err(Error::WrongNetwork)
+        ));
+        assert!(matches!(
+            runtime.forget_asset_scoped(&testnet_id, true),
+            Err(Error::WrongNetwork)
+        ));
+        assert!(matches!(
+            runtime.export_asset_scoped(&testnet_id),
+            Err(Error::WrongNetwork)
+        ));
+        assert!(matches!(
+            runtime.clear_watch_only(&testnet_id),
+            Err(Error::WrongNetwork)
+        ));
+        assert!(matches!(
+            runtime.transfer_scoped(
+                &testnet_id,
+                bset! {},
+                bmap! {},
+                bmap! {},
+                None,
+                witness()
+            ),
+            Err(Error::WrongNetwork)
+        ));
+        assert!(matches!(
+            runtime.simulate_transfer_scoped(
+                &testnet_id,
+                bset! {},
+                bmap! {},
+                bmap! {},
+                None,
+                witness()
+            ),
+            Err(Error::WrongNetwork)
+        ));
+        assert!(matches!(
+            runtime.consolidate_scoped(
+                &testnet_id,
+                100,
+                SealDefinition::TxOutpoint(OutpointReveal::from(
+                    OutPoint::default()
+                )),
+                witness()
+            ),
+            Err(Error::WrongNetwork)
+        ));
+        assert!(matches!(
+            runtime.inflate_scoped(
+                &testnet_id,
+                OutPoint::default(),
+                100,
+                bmap! {},
+                None,
+                witness()
+            ),
+            Err(Error::WrongNetwork)
+        ));
+        assert!(matches!(
+            runtime.burn_scoped(&testnet_id, bset! {}, None, witness()),
+            Err(Error::WrongNetwork)
+        ));
+        assert!(matches!(
+            runtime.rename_scoped(
+                &testnet_id,
+                Some(s!("TCK")),
+                None,
+                None,
+                witness()
+            ),
+            Err(Error::WrongNetwork)
+        ));
+
+        let node_id = ChainScopedContractId::new(
+            *asset.id(),
+            runtime.config.network.clone(),
+        );
+        assert_eq!(
+            runtime.label_scoped(&node_id).unwrap(),
+            Some(s!("company treasury"))
+        );
+        runtime.clear_watch_only(&node_id).unwrap();
+        assert_eq!(
+            runtime.outpoint_assets_scoped(OutPoint::default()).unwrap(),
+            bmap! { node_id => vec![1000] }
+        );
+        daemon.join().unwrap();
+    }
 }
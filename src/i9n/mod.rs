@@ -11,11 +11,15 @@
 // along with this software.
 // If not, see <https://opensource.org/licenses/MIT>.
 
+#[cfg(feature = "async")]
+mod async_runtime;
 mod config;
 mod error;
 mod fungible;
 mod runtime;
 
-pub use config::Config;
+#[cfg(feature = "async")]
+pub use async_runtime::AsyncRuntime;
+pub use config::{Config, ConfigBuilder};
 pub use error::Error;
 pub use runtime::Runtime;
@@ -11,23 +11,43 @@
 // along with this software.
 // If not, see <https://opensource.org/licenses/MIT>.
 
+use std::collections::BTreeMap;
+use std::io;
+use std::sync::Arc;
 use std::thread;
+use std::time::{Duration, Instant};
 
-use internet2::ZmqType;
+use amplify::IoError;
+use bp::seals::{OutpointHash, OutpointReveal};
 use internet2::{
-    session, transport, CreateUnmarshaller, PlainTranscoder, Unmarshaller,
+    presentation, session, transport, CreateUnmarshaller, PlainTranscoder,
+    Session, Unmarshaller,
 };
+use internet2::{ZmqSocketAddr, ZmqType};
+use microservices::FileFormat;
 
-use super::Config;
-use crate::error::BootstrapError;
+use super::{Config, Error};
+use crate::error::{BootstrapError, ServiceErrorDomain};
 use crate::rgbd::{self, ContractName};
-use crate::rpc::Reply;
+use crate::rpc::{wire, Reply};
+
+type RpcSession =
+    session::Raw<PlainTranscoder, transport::zmqsocket::Connection>;
 
 pub struct Runtime {
     pub(super) config: Config,
-    pub(super) session_rpc:
-        session::Raw<PlainTranscoder, transport::zmqsocket::Connection>,
+    pub(super) session_rpc: RpcSession,
     pub(super) unmarshaller: Unmarshaller<Reply>,
+    /// Fungible engine endpoints in the failover order, starting with the
+    /// one from [`Config::contract_endpoints`]
+    endpoints: Vec<ZmqSocketAddr>,
+    /// Index of the endpoint `session_rpc` is connected to
+    active_endpoint: usize,
+    /// Secrets of the outpoints blinded for receiving assets, revealed when
+    /// the incoming consignment is accepted
+    pub(super) blinded_outpoints: BTreeMap<OutpointHash, OutpointReveal>,
+    /// Encoding of the RPC messages used with the daemon
+    pub(super) wire_format: FileFormat,
 }
 
 impl Runtime {
@@ -60,22 +80,331 @@ impl Runtime {
             });
         }
 
-        let session_rpc = session::Raw::with_zmq_unencrypted(
-            ZmqType::Req,
-            config
-                .contract_endpoints
-                .get(&ContractName::Fungible)
-                .as_ref()
-                .expect(
-                    "Fungible engine is not connected in the configuration",
-                ),
-            None,
-            None,
-        )?;
-        Ok(Self {
+        let mut endpoints = vec![config
+            .contract_endpoints
+            .get(&ContractName::Fungible)
+            .expect("Fungible engine is not connected in the configuration")
+            .clone()];
+        endpoints.extend(config.failover_endpoints.iter().cloned());
+        let session_rpc = connect(&endpoints[0], config.reply_timeout)?;
+        let mut runtime = Self {
             config,
             session_rpc,
             unmarshaller: Reply::create_unmarshaller(),
+            endpoints,
+            active_endpoint: 0,
+            blinded_outpoints: bmap! {},
+            wire_format: FileFormat::StrictEncode,
+        };
+        if runtime.config.negotiate_format {
+            runtime.hello().map_err(|err| {
+                BootstrapError::ArgParseError(format!(
+                    "Unable to negotiate RPC message format: {}",
+                    err
+                ))
+            })?;
+        }
+        Ok(runtime)
+    }
+
+    /// Encoding of the RPC messages used with the daemon
+    pub fn wire_format(&self) -> FileFormat {
+        self.wire_format
+    }
+}
+
+impl Runtime {
+    /// Sends raw request to the daemon and receives its reply. If the daemon
+    /// is not reachable, i.e. the request can't be sent or no reply data are
+    /// received, fails over to the next configured endpoint; the endpoint
+    /// which replied is used for the subsequent requests. A partially
+    /// received reply means the daemon got the request, so it is not resent
+    /// to avoid performing it twice.
+    pub(super) fn request(&mut self, data: &[u8]) -> Result<Arc<Reply>, Error> {
+        let mut result = Err(Error::IncompleteReply);
+        for _ in 0..self.endpoints.len() {
+            result = self
+                .session_rpc
+                .send_raw_message(data)
+                .map_err(Error::from)
+                .and_then(|_| self.recv_reply());
+            match result {
+                Err(ref err) if is_connection_failure(err) => {
+                    // REQ socket which has not received the reply can't send
+                    // new requests, so the session is always re-created
+                    let next =
+                        (self.active_endpoint + 1) % self.endpoints.len();
+                    warn!(
+                        "RGB node at {} is not reachable ({}); using {}",
+                        self.endpoints[self.active_endpoint],
+                        err,
+                        self.endpoints[next]
+                    );
+                    self.session_rpc = connect(
+                        &self.endpoints[next],
+                        self.config.reply_timeout,
+                    )
+                    .map_err(ServiceErrorDomain::from)?;
+                    self.active_endpoint = next;
+                }
+                Err(Error::IncompleteReply) | Err(Error::ReplyTimeout) => {
+                    // Resetting REQ socket which is left waiting for the
+                    // rest of the reply
+                    self.session_rpc = connect(
+                        &self.endpoints[self.active_endpoint],
+                        self.config.reply_timeout,
+                    )
+                    .map_err(ServiceErrorDomain::from)?;
+                    break;
+                }
+                _ => break,
+            }
+        }
+        result
+    }
+
+    /// Receives reply from the daemon, collecting data from multiple frames
+    /// if the reply was fragmented by the transport
+    pub(super) fn recv_reply(&mut self) -> Result<Arc<Reply>, Error> {
+        let session_rpc = &mut self.session_rpc;
+        recv_reply(
+            |timeout| {
+                session_rpc
+                    .as_socket()
+                    .set_rcvtimeo(timeout_millis(timeout))?;
+                let frame = session_rpc.recv_raw_message()?;
+                Ok((frame, session_rpc.as_socket().get_rcvmore()?))
+            },
+            &self.unmarshaller,
+            self.wire_format,
+            self.config.reply_timeout,
+        )
+    }
+}
+
+/// Opens RPC session to the daemon endpoint. Receive timeout is set to the
+/// reply timeout, so requests to the unavailable daemon fail instead of
+/// blocking forever.
+fn connect(
+    endpoint: &ZmqSocketAddr,
+    timeout: Duration,
+) -> Result<RpcSession, transport::Error> {
+    let session =
+        session::Raw::with_zmq_unencrypted(ZmqType::Req, endpoint, None, None)?;
+    let socket = session.as_socket();
+    socket.set_rcvtimeo(timeout_millis(timeout))?;
+    socket.set_linger(0)?;
+    Ok(session)
+}
+
+/// Converts timeout into the ZMQ socket option value
+fn timeout_millis(timeout: Duration) -> i32 {
+    timeout.as_millis().min(i32::MAX as u128) as i32
+}
+
+/// Detects errors caused by the daemon being unreachable, for which the
+/// request may be repeated with the other endpoint: either the request was
+/// not sent, or no reply data were received at all
+fn is_connection_failure(err: &Error) -> bool {
+    matches!(
+        err,
+        Error::ServiceError(ServiceErrorDomain::LnpTransport(_))
+    )
+}
+
+/// Keeps receiving frames of a multipart message and decoding them in the
+/// wire `format` until a complete reply is decoded. Each receive is given the
+/// time left before the `timeout` expires, and `recv` returns the frame
+/// together with the flag whether more frames of the message follow. Fails
+/// with [`Error::IncompleteReply`] if the connection fails or the message
+/// ends in the middle of the reply and with [`Error::ReplyTimeout`] if the
+/// reply is not complete within `timeout`.
+pub(super) fn recv_reply(
+    mut recv: impl FnMut(Duration) -> Result<(Vec<u8>, bool), transport::Error>,
+    unmarshaller: &Unmarshaller<Reply>,
+    format: FileFormat,
+    timeout: Duration,
+) -> Result<Arc<Reply>, Error> {
+    let deadline = Instant::now() + timeout;
+    let mut data = Vec::<u8>::new();
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let more = match recv(remaining) {
+            Ok((frame, more)) => {
+                data.extend(frame);
+                more
+            }
+            Err(err) if data.is_empty() => {
+                return Err(ServiceErrorDomain::from(err).into())
+            }
+            Err(_) if Instant::now() >= deadline => {
+                return Err(Error::ReplyTimeout)
+            }
+            Err(_) => return Err(Error::IncompleteReply),
+        };
+        match wire::decode(unmarshaller, &data, format) {
+            Ok(reply) => return Ok(reply),
+            Err(err) if is_incomplete(&err) => {
+                trace!("Got incomplete reply of {} bytes", data.len())
+            }
+            Err(err) => return Err(ServiceErrorDomain::from(err).into()),
+        }
+        if !more {
+            return Err(Error::IncompleteReply);
+        }
+        if Instant::now() >= deadline {
+            return Err(Error::ReplyTimeout);
+        }
+    }
+}
+
+fn is_incomplete(err: &presentation::Error) -> bool {
+    let eof = IoError::from(io::ErrorKind::UnexpectedEof);
+    match err {
+        presentation::Error::NoData => true,
+        presentation::Error::Io(err) => *err == eof,
+        presentation::Error::StrictEncoding(strict_encoding::Error::Io(
+            err,
+        )) => *err == eof,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rpc::fungible::Request;
+    use crate::rpc::reply;
+    use internet2::TypedEnum;
+
+    #[test]
+    fn test_fragmented_reply() {
+        let unmarshaller = Reply::create_unmarshaller();
+        let data = Reply::Failure(reply::Failure {
+            code: 0,
+            info: s!("fragmented reply"),
         })
+        .serialize();
+        let (first, second) = data.split_at(data.len() / 2);
+
+        let mut frames =
+            vec![(first.to_vec(), true), (second.to_vec(), false)].into_iter();
+        let reply = recv_reply(
+            |_| {
+                frames.next().ok_or(transport::Error::SocketIo(
+                    io::ErrorKind::BrokenPipe,
+                ))
+            },
+            &unmarshaller,
+            FileFormat::StrictEncode,
+            Duration::from_secs(1),
+        )
+        .unwrap();
+        match &*reply {
+            Reply::Failure(failure) => {
+                assert_eq!(failure.info, "fragmented reply")
+            }
+            _ => panic!("unexpected reply"),
+        }
+
+        // Connection closes before the second half of the reply arrives
+        let mut frames = vec![(first.to_vec(), true)].into_iter();
+        let err = recv_reply(
+            |_| {
+                frames.next().ok_or(transport::Error::SocketIo(
+                    io::ErrorKind::BrokenPipe,
+                ))
+            },
+            &unmarshaller,
+            FileFormat::StrictEncode,
+            Duration::from_secs(1),
+        )
+        .unwrap_err();
+        assert!(matches!(err, Error::IncompleteReply));
+        // The daemon has received the request, so it must not be resent
+        assert!(!is_connection_failure(&err));
+
+        // Message ends in the middle of the reply, so nothing is received
+        // after it
+        let mut frames = vec![(first.to_vec(), false)].into_iter();
+        let err = recv_reply(
+            |_| Ok(frames.next().expect("no frames are left")),
+            &unmarshaller,
+            FileFormat::StrictEncode,
+            Duration::from_secs(1),
+        )
+        .unwrap_err();
+        assert!(matches!(err, Error::IncompleteReply));
+
+        // Reply does not complete within the timeout: the receive of the
+        // second frame is bounded by the time left
+        let timeout = Duration::from_millis(50);
+        let mut frames = vec![(first.to_vec(), true)].into_iter();
+        let err = recv_reply(
+            |remaining| {
+                assert!(remaining <= timeout);
+                frames.next().ok_or_else(|| {
+                    thread::sleep(remaining);
+                    transport::Error::SocketIo(io::ErrorKind::WouldBlock)
+                })
+            },
+            &unmarshaller,
+            FileFormat::StrictEncode,
+            timeout,
+        )
+        .unwrap_err();
+        assert!(matches!(err, Error::ReplyTimeout));
+        assert!(!is_connection_failure(&err));
+
+        // No reply data are received from the unreachable daemon
+        let err = recv_reply(
+            |_| Err(transport::Error::SocketIo(io::ErrorKind::TimedOut)),
+            &unmarshaller,
+            FileFormat::StrictEncode,
+            Duration::from_secs(1),
+        )
+        .unwrap_err();
+        assert!(is_connection_failure(&err));
+    }
+
+    #[test]
+    fn test_failover() {
+        let served = ZmqSocketAddr::Inproc(s!("rgb-failover-served"));
+        let mut server = session::Raw::with_zmq_unencrypted(
+            ZmqType::Rep,
+            &served,
+            None,
+            None,
+        )
+        .unwrap();
+        let daemon = thread::spawn(move || {
+            for _ in 0..2 {
+                server.recv_raw_message().unwrap();
+                server
+                    .send_raw_message(&Reply::Success.serialize())
+                    .unwrap();
+            }
+        });
+
+        let mut runtime = Runtime::init(
+            Config::builder()
+                .endpoint("inproc://rgb-failover-dead")
+                .failover_endpoint("inproc://rgb-failover-served")
+                .timeout(Duration::from_millis(200))
+                .embedded(false)
+                .build()
+                .unwrap(),
+        )
+        .unwrap();
+        let request = Request::Sync(FileFormat::StrictEncode);
+        let reply = runtime.command(request.clone()).unwrap();
+        assert!(matches!(*reply, Reply::Success));
+        assert_eq!(runtime.active_endpoint, 1);
+
+        // Last endpoint which replied is used for the next request
+        let reply = runtime.command(request).unwrap();
+        assert!(matches!(*reply, Reply::Success));
+        assert_eq!(runtime.active_endpoint, 1);
+        daemon.join().unwrap();
     }
 }
@@ -18,6 +18,7 @@ use std::collections::{BTreeMap, BTreeSet};
 use bitcoin::util::psbt::PartiallySignedTransaction;
 use bitcoin::OutPoint;
 use bp::seals::OutpointReveal;
+use lnpbp::chain::Chain;
 use rgb::{
     AtomicValue, Consignment, ContractId, Disclosure, Genesis, SealDefinition,
     SealEndpoint,
@@ -26,17 +27,80 @@ use rgb20::OutpointCoins;
 
 use microservices::FileFormat;
 
+/// Encodings of the RPC messages supported by this version of the node, in
+/// the order of preference; see [`crate::rpc::wire`] for their details
+#[cfg(feature = "serde")]
+pub const WIRE_FORMATS: &[FileFormat] =
+    &[FileFormat::StrictEncode, FileFormat::Json];
+
+/// Encodings of the RPC messages supported by this version of the node, in
+/// the order of preference; see [`crate::rpc::wire`] for their details
+#[cfg(not(feature = "serde"))]
+pub const WIRE_FORMATS: &[FileFormat] = &[FileFormat::StrictEncode];
+
+/// Selects the first of the offered RPC message encodings which is present
+/// in [`WIRE_FORMATS`]
+pub fn negotiate_wire_format(offered: &[FileFormat]) -> Option<FileFormat> {
+    offered
+        .iter()
+        .find(|format| WIRE_FORMATS.contains(format))
+        .copied()
+}
+
 #[derive(Clone, Debug, Display, Api)]
 #[api(encoding = "strict")]
 #[display(inner)]
 #[non_exhaustive]
 pub enum Request {
+    /// Checks that the daemon is alive and responsive
+    #[api(type = 0x0001)]
+    #[display("ping()")]
+    Ping,
+
+    /// Requests information about the daemon version, supported schemata
+    /// and network
+    #[api(type = 0x0003)]
+    #[display("info()")]
+    Info,
+
+    /// Negotiates encoding of the RPC messages, offering the formats
+    /// supported by the client in the order of preference
+    #[api(type = 0x0005)]
+    #[display("hello(...)")]
+    Hello(Vec<FileFormat>),
+
     #[api(type = 0x0101)]
     Issue(IssueReq),
 
+    #[api(type = 0x0102)]
+    Inflate(InflateReq),
+
     #[api(type = 0x0103)]
     Transfer(TransferReq),
 
+    #[api(type = 0x0104)]
+    #[display("dry_run({0})")]
+    TransferDryRun(TransferReq),
+
+    /// Computes per-outpoint balances before and after the transfer, without
+    /// persisting any state
+    #[api(type = 0x0111)]
+    #[display("preview_transfer({0})")]
+    PreviewTransfer(TransferReq),
+
+    /// Transfers several assets at once, committing all of the state
+    /// transitions to a single witness transaction. Only the first transfer
+    /// is consigned, so the rest may only move assets to the change seals.
+    #[api(type = 0x0110)]
+    #[display("batch_transfer(...)")]
+    BatchTransfer(Vec<TransferReq>),
+
+    #[api(type = 0x0106)]
+    Burn(BurnReq),
+
+    #[api(type = 0x010a)]
+    Renominate(RenominateReq),
+
     #[api(type = 0x0105)]
     #[display("validate(...)")]
     Validate(Consignment),
@@ -56,6 +120,19 @@ pub enum Request {
     #[display("export_asset({0})")]
     ExportAsset(ContractId),
 
+    #[api(type = 0x010c)]
+    ForgetAsset(ForgetAssetReq),
+
+    #[api(type = 0x010e)]
+    TransferAccepted(TransferAcceptedReq),
+
+    #[api(type = 0x010f)]
+    SetLabel(SetLabelReq),
+
+    /// Makes watch-only asset spendable
+    #[api(type = 0x0112)]
+    ClearWatchOnly(ClearWatchOnlyReq),
+
     #[api(type = 0x010d)]
     #[display("forget({0})")]
     Forget(OutPoint),
@@ -71,6 +148,21 @@ pub enum Request {
     #[api(type = 0xFF03)]
     #[display("allocations({0})")]
     Allocations(ContractId),
+
+    #[api(type = 0xFF04)]
+    ListAssets(ListAssetsReq),
+
+    #[api(type = 0xFF05)]
+    #[display("pending_transfers()")]
+    ListPendingTransfers,
+
+    #[api(type = 0xFF06)]
+    Label(LabelReq),
+
+    /// Requests the chunk of the consignment of the last transfer returned
+    /// as [`crate::rpc::reply::ChunkedTransfer`]
+    #[api(type = 0xFF07)]
+    ConsignmentChunk(ConsignmentChunkReq),
 }
 
 #[derive(
@@ -116,6 +208,321 @@ pub struct IssueReq {
     /// right of opening the first epoch
     #[clap(short, long)]
     pub epoch: Option<OutPoint>,
+
+    /// Key identifying the request for safe retries: if the daemon has
+    /// already issued an asset for the same key it replies with the issued
+    /// asset instead of issuing a new one
+    #[clap(skip)]
+    pub idempotency_key: Option<[u8; 32]>,
+}
+
+impl IssueReq {
+    /// Maximum supported asset precision: with more digits reserved for the
+    /// fractional part even a single asset unit does not fit into `u64`
+    pub const MAX_PRECISION: u8 = 18;
+
+    /// Performs client-side validation of the issue parameters, checking
+    /// the asset precision and that both the issued supply and the supply
+    /// cap (issued supply plus all inflation allowances) fit into `u64`
+    pub fn validate(&self) -> Result<(), IssueError> {
+        if self.precision > Self::MAX_PRECISION {
+            return Err(IssueError::PrecisionTooLarge(self.precision));
+        }
+        let issued =
+            sum_coins(&self.allocation).ok_or(IssueError::SupplyOverflow)?;
+        sum_coins(&self.inflation)
+            .and_then(|inflation| issued.checked_add(inflation))
+            .ok_or(IssueError::CapOverflow)?;
+        Ok(())
+    }
+}
+
+fn sum_coins(coins: &[OutpointCoins]) -> Option<AtomicValue> {
+    coins
+        .iter()
+        .try_fold(0u64, |sum, item| sum.checked_add(item.coins))
+}
+
+/// Errors detected by the client-side validation of the issue request
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum IssueError {
+    /// Precision {0} exceeds the maximum of 18 fractional digits
+    PrecisionTooLarge(u8),
+
+    /// Total issued supply does not fit into 64-bit atomic value
+    SupplyOverflow,
+
+    /// Supply cap, including all inflation allowances, does not fit into
+    /// 64-bit atomic value
+    CapOverflow,
+}
+
+#[derive(Clone, PartialEq, StrictEncode, StrictDecode, Debug, Display)]
+#[display("inflate({contract_id}, {amount}, ...)")]
+pub struct InflateReq {
+    /// Asset contract id
+    pub contract_id: ContractId,
+
+    /// Base layer transaction structure to use
+    pub witness: PartiallySignedTransaction,
+
+    /// Outpoint controlling the inflation right which is spent by the
+    /// secondary issue
+    pub inflation: OutPoint,
+
+    /// Amount of the newly issued assets
+    pub amount: AtomicValue,
+
+    /// Allocations of the newly issued assets
+    pub allocation: BTreeMap<SealDefinition, AtomicValue>,
+
+    /// Seal receiving the inflation right for the capacity left after this
+    /// issue. If not given, the right is assigned to the first witness
+    /// transaction output
+    pub inflation_change: Option<SealDefinition>,
+}
+
+impl InflateReq {
+    /// Performs client-side validation of the request structure, checking
+    /// that the issued amount is non-zero and matches the sum of the
+    /// allocations
+    pub fn validate(&self) -> Result<(), InflateError> {
+        if self.amount == 0 || self.allocation.values().any(|v| *v == 0) {
+            return Err(InflateError::ZeroAmount);
+        }
+        let allocated = self
+            .allocation
+            .values()
+            .fold(0u64, |sum, amount| sum.saturating_add(*amount));
+        if allocated != self.amount {
+            return Err(InflateError::AllocationMismatch {
+                amount: self.amount,
+                allocated,
+            });
+        }
+        Ok(())
+    }
+
+    /// Checks that the inflation right spent by the request has enough
+    /// capacity for the issued amount, using the provided map of known
+    /// inflation rights
+    pub fn validate_capacity(
+        &self,
+        inflation: &BTreeMap<OutPoint, AtomicValue>,
+    ) -> Result<AtomicValue, InflateError> {
+        let capacity = *inflation
+            .get(&self.inflation)
+            .ok_or(InflateError::UnknownInflation(self.inflation))?;
+        if self.amount > capacity {
+            return Err(InflateError::ExceedsCapacity {
+                requested: self.amount,
+                capacity,
+            });
+        }
+        Ok(capacity)
+    }
+}
+
+/// Errors detected during validation of the inflation request
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum InflateError {
+    /// Secondary issue or some of its allocations has zero amount
+    ZeroAmount,
+
+    /// Issued amount {amount} does not match the total of {allocated}
+    /// allocated by the request
+    AllocationMismatch {
+        amount: AtomicValue,
+        allocated: AtomicValue,
+    },
+
+    /// Outpoint {0} does not control any known inflation right
+    UnknownInflation(OutPoint),
+
+    /// Requested amount {requested} exceeds the capacity of {capacity} of the
+    /// spent inflation right
+    ExceedsCapacity {
+        requested: AtomicValue,
+        capacity: AtomicValue,
+    },
+}
+
+#[derive(Clone, PartialEq, Eq, StrictEncode, StrictDecode, Debug, Display)]
+#[display("label({contract_id})")]
+pub struct LabelReq {
+    /// Asset contract id
+    pub contract_id: ContractId,
+}
+
+#[derive(Clone, PartialEq, Eq, StrictEncode, StrictDecode, Debug, Display)]
+#[display("set_label({contract_id}, ...)")]
+pub struct SetLabelReq {
+    /// Asset contract id
+    pub contract_id: ContractId,
+
+    /// Private user label of the asset; `None` removes the existing label
+    pub label: Option<String>,
+}
+
+#[derive(Clone, PartialEq, Eq, StrictEncode, StrictDecode, Debug, Display)]
+#[display("forget_asset({contract_id}, force: {force})")]
+pub struct ForgetAssetReq {
+    /// Asset contract id
+    pub contract_id: ContractId,
+
+    /// Forget the asset even if it has non-zero known balance
+    pub force: bool,
+}
+
+#[derive(Clone, PartialEq, Eq, StrictEncode, StrictDecode, Debug, Display)]
+#[display("clear_watch_only({contract_id})")]
+pub struct ClearWatchOnlyReq {
+    /// Asset contract id
+    pub contract_id: ContractId,
+}
+
+#[derive(Clone, PartialEq, Eq, StrictEncode, StrictDecode, Debug, Display)]
+#[display("consignment_chunk({seq})")]
+pub struct ConsignmentChunkReq {
+    /// Sequence number of the requested chunk
+    pub seq: u32,
+}
+
+#[derive(Clone, PartialEq, Eq, StrictEncode, StrictDecode, Debug, Display)]
+#[display("transfer_accepted(...)")]
+pub struct TransferAcceptedReq {
+    /// Seal receiving the payment accepted by the receiver
+    pub seal: SealEndpoint,
+}
+
+#[derive(Clone, PartialEq, StrictEncode, StrictDecode, Debug, Display)]
+#[display("burn({contract_id}, ...)")]
+pub struct BurnReq {
+    /// Asset contract id
+    pub contract_id: ContractId,
+
+    /// Base layer transaction structure to use
+    pub witness: PartiallySignedTransaction,
+
+    /// Outpoints which assets are burned
+    pub outpoints: BTreeSet<OutPoint>,
+
+    /// Seal receiving the burn right, which allows to prove subsequent burns.
+    /// If not given, the right is spent and no more burns are possible
+    pub proof_seal: Option<SealDefinition>,
+}
+
+impl BurnReq {
+    /// Performs client-side validation of the request structure, checking
+    /// that there are allocations to burn and that the witness transaction
+    /// spends all of them
+    pub fn validate(&self) -> Result<(), BurnError> {
+        if self.outpoints.is_empty() {
+            return Err(BurnError::NoAllocations);
+        }
+        let spent = self
+            .witness
+            .global
+            .unsigned_tx
+            .input
+            .iter()
+            .map(|txin| txin.previous_output)
+            .collect::<BTreeSet<_>>();
+        if let Some(outpoint) = self
+            .outpoints
+            .iter()
+            .find(|outpoint| !spent.contains(outpoint))
+        {
+            return Err(BurnError::UnspentOutpoint(*outpoint));
+        }
+        Ok(())
+    }
+
+    /// Checks that each of the burned outpoints has some assets, using the
+    /// provided map of known asset allocations. Returns the burned amount.
+    pub fn validate_allocations(
+        &self,
+        allocations: &BTreeMap<OutPoint, Vec<AtomicValue>>,
+    ) -> Result<AtomicValue, BurnError> {
+        self.outpoints
+            .iter()
+            .try_fold(0u64, |sum, outpoint| match allocations.get(outpoint) {
+                Some(amounts) if !amounts.is_empty() => Ok(amounts
+                    .iter()
+                    .fold(sum, |sum, amount| sum.saturating_add(*amount))),
+                _ => Err(BurnError::UnknownAllocation(*outpoint)),
+            })
+    }
+}
+
+/// Errors detected during validation of the burn request
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum BurnError {
+    /// Burn does not specify any allocations to burn
+    NoAllocations,
+
+    /// Outpoint {0} does not have any known assets to burn
+    UnknownAllocation(OutPoint),
+
+    /// Asset does not have a known burn right
+    NoBurnRight,
+
+    /// Witness transaction does not spend burned outpoint {0}
+    UnspentOutpoint(OutPoint),
+}
+
+#[derive(Clone, PartialEq, StrictEncode, StrictDecode, Debug, Display)]
+#[display("renominate({contract_id}, ...)")]
+pub struct RenominateReq {
+    /// Asset contract id
+    pub contract_id: ContractId,
+
+    /// Base layer transaction structure to use
+    pub witness: PartiallySignedTransaction,
+
+    /// New asset ticker; if not given, the ticker is not changed
+    pub ticker: Option<String>,
+
+    /// New asset name; if not given, the name is not changed
+    pub name: Option<String>,
+
+    /// Seal receiving the renomination right, which allows subsequent
+    /// renominations. If not given, the right is spent and the asset can't
+    /// be renamed anymore
+    pub renomination_seal: Option<SealDefinition>,
+}
+
+impl RenominateReq {
+    /// Performs client-side validation of the request structure, checking
+    /// that it changes ticker or name and that the new ticker is valid
+    pub fn validate(&self) -> Result<(), RenominateError> {
+        if self.ticker.is_none() && self.name.is_none() {
+            return Err(RenominateError::NothingToChange);
+        }
+        if let Some(ticker) = &self.ticker {
+            ticker_validator(ticker)
+                .map_err(|_| RenominateError::InvalidTicker)?;
+        }
+        Ok(())
+    }
+}
+
+/// Errors detected during validation of the renomination request
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum RenominateError {
+    /// Renomination changes neither ticker nor name of the asset
+    NothingToChange,
+
+    /// Ticker must be between 3 and 8 chars, contain no spaces and consist
+    /// only of capital letters
+    InvalidTicker,
+
+    /// Asset does not have a known renomination right
+    NoRenominationRight,
 }
 
 #[derive(Clone, PartialEq, StrictEncode, StrictDecode, Debug, Display)]
@@ -141,6 +548,222 @@ pub struct TransferReq {
     ///
     /// Here we always know an explicit outpoint that will contain the assets
     pub change: BTreeMap<SealDefinition, AtomicValue>,
+
+    /// Outpoint which will receive the assets left after the payment and
+    /// change allocations. If not given, the daemon picks the change seal
+    /// itself and reports the resulting outpoint back
+    pub change_outpoint: Option<OutPoint>,
+
+    /// Key identifying the request for safe retries: if the daemon has
+    /// already performed a transfer for the same key it replies with the
+    /// original transfer data instead of creating a new transfer. All
+    /// transfers of a batch must have the same key.
+    pub idempotency_key: Option<[u8; 32]>,
+}
+
+impl TransferReq {
+    /// Performs client-side validation of the request structure, checking
+    /// that the transfer has asset inputs and that all of the payment and
+    /// change amounts are non-zero
+    pub fn validate(&self) -> Result<(), TransferError> {
+        if self.inputs.is_empty() {
+            return Err(TransferError::NoInputs);
+        }
+        if self
+            .payment
+            .values()
+            .chain(self.change.values())
+            .any(|amount| *amount == 0)
+        {
+            return Err(TransferError::ZeroAmount);
+        }
+        Ok(())
+    }
+
+    /// Total amount requested by the transfer, including both payment and
+    /// change
+    pub fn requested_amount(&self) -> AtomicValue {
+        self.payment
+            .values()
+            .chain(self.change.values())
+            .fold(0, |sum, amount| sum.saturating_add(*amount))
+    }
+
+    /// Checks that the transfer inputs control enough assets to cover the
+    /// requested amount, using the provided map of known asset allocations
+    pub fn validate_inputs(
+        &self,
+        allocations: &BTreeMap<OutPoint, Vec<AtomicValue>>,
+    ) -> Result<(), TransferError> {
+        let available = self
+            .inputs
+            .iter()
+            .filter_map(|outpoint| allocations.get(outpoint))
+            .flatten()
+            .fold(0u64, |sum, amount| sum.saturating_add(*amount));
+        let requested = self.requested_amount();
+        if requested > available {
+            return Err(TransferError::InsufficientInputs {
+                requested,
+                available,
+            });
+        }
+        Ok(())
+    }
+
+    /// Estimates virtual size of the witness transaction, assuming P2WPKH
+    /// inputs and outputs. Inputs are the transfer asset inputs together with
+    /// the inputs already present in the witness transaction; outputs are the
+    /// witness transaction outputs, or the outputs referenced by witness vout
+    /// seals of the payment and change, whichever is larger, but at least a
+    /// single output carrying the commitment.
+    pub fn estimated_vbytes(&self) -> usize {
+        let tx = &self.witness.global.unsigned_tx;
+        let inputs = tx
+            .input
+            .iter()
+            .map(|txin| txin.previous_output)
+            .chain(self.inputs.iter().copied())
+            .collect::<BTreeSet<_>>()
+            .len();
+        let witness_vouts = self
+            .payment
+            .keys()
+            .filter_map(|seal| match seal {
+                SealEndpoint::WitnessVout { vout, .. } => Some(*vout),
+                _ => None,
+            })
+            .chain(self.change.keys().filter_map(|seal| match seal {
+                SealDefinition::WitnessVout { vout, .. } => Some(*vout),
+                _ => None,
+            }))
+            .collect::<BTreeSet<_>>()
+            .len();
+        let outputs = tx.output.len().max(witness_vouts).max(1);
+        TX_OVERHEAD_VBYTES
+            + inputs * P2WPKH_INPUT_VBYTES
+            + outputs * P2WPKH_OUTPUT_VBYTES
+    }
+
+    /// Estimates fee of the witness transaction for the fee rate given in
+    /// satoshis per virtual byte; see [`TransferReq::estimated_vbytes`]
+    pub fn estimated_fee(&self, sat_per_vbyte: u64) -> u64 {
+        self.estimated_vbytes() as u64 * sat_per_vbyte
+    }
+}
+
+/// Size of transaction version, locktime, segwit marker and input and output
+/// counts, rounded up
+const TX_OVERHEAD_VBYTES: usize = 11;
+
+/// Virtual size of a P2WPKH input including its witness, rounded up
+const P2WPKH_INPUT_VBYTES: usize = 68;
+
+/// Virtual size of a P2WPKH output
+const P2WPKH_OUTPUT_VBYTES: usize = 31;
+
+/// Errors detected by the client-side validation of the transfer request
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum TransferError {
+    /// Transfer does not specify any asset inputs
+    NoInputs,
+
+    /// Asset input {0} is specified more than once
+    DuplicateInput(OutPoint),
+
+    /// Transfer contains payment or change allocation with zero amount
+    ZeroAmount,
+
+    /// Requested amount {requested} exceeds the amount of {available}
+    /// controlled by the transfer inputs
+    InsufficientInputs {
+        requested: AtomicValue,
+        available: AtomicValue,
+    },
+
+    /// Batch transfer does not contain any transfers
+    EmptyBatch,
+
+    /// Asset {0} is transferred more than once within the batch
+    DuplicateContract(ContractId),
+
+    /// Asset input {0} is spent by more than one transfer of the batch
+    ConflictingInput(OutPoint),
+
+    /// Transfers of the batch use different witness transactions
+    WitnessMismatch,
+
+    /// Transfers of the batch have different idempotency keys
+    IdempotencyKeyMismatch,
+
+    /// Transfer of asset {0} pays to external receivers, while only the
+    /// first transfer of the batch is consigned
+    UnconsignedPayment(ContractId),
+}
+
+/// Performs client-side validation of a batch transfer: each of the
+/// transfers must be valid on its own, transfer a distinct asset, spend
+/// inputs not used by the other transfers and share the same witness
+/// transaction and idempotency key. Since the consignment is produced only
+/// for the first transfer, the rest of the transfers must not have payments.
+pub fn validate_batch(transfers: &[TransferReq]) -> Result<(), TransferError> {
+    let first = transfers.first().ok_or(TransferError::EmptyBatch)?;
+    if let Some(transfer) = transfers[1..]
+        .iter()
+        .find(|transfer| !transfer.payment.is_empty())
+    {
+        return Err(TransferError::UnconsignedPayment(transfer.contract_id));
+    }
+    let mut contracts = BTreeSet::new();
+    let mut inputs = BTreeSet::new();
+    for transfer in transfers {
+        transfer.validate()?;
+        if !contracts.insert(transfer.contract_id) {
+            return Err(TransferError::DuplicateContract(transfer.contract_id));
+        }
+        if transfer.witness != first.witness {
+            return Err(TransferError::WitnessMismatch);
+        }
+        if transfer.idempotency_key != first.idempotency_key {
+            return Err(TransferError::IdempotencyKeyMismatch);
+        }
+        for outpoint in &transfer.inputs {
+            if !inputs.insert(*outpoint) {
+                return Err(TransferError::ConflictingInput(*outpoint));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Collects transfer inputs into a set, failing if some outpoint is
+/// specified more than once
+pub fn unique_inputs(
+    inputs: impl IntoIterator<Item = OutPoint>,
+) -> Result<BTreeSet<OutPoint>, TransferError> {
+    let mut set = BTreeSet::new();
+    for outpoint in inputs {
+        if !set.insert(outpoint) {
+            return Err(TransferError::DuplicateInput(outpoint));
+        }
+    }
+    Ok(set)
+}
+
+/// Request for a page of the known assets ordered by their contract ids,
+/// optionally filtered by the chain
+#[derive(Clone, PartialEq, Eq, StrictEncode, StrictDecode, Debug, Display)]
+#[display("list_assets(offset: {offset}, limit: {limit}, ...)")]
+pub struct ListAssetsReq {
+    /// Number of assets to skip
+    pub offset: u32,
+
+    /// Maximum number of assets to return
+    pub limit: u32,
+
+    /// If given, only assets issued on this chain are listed
+    pub chain: Option<Chain>,
 }
 
 #[derive(Clone, StrictEncode, StrictDecode, Debug, Display)]
@@ -168,3 +791,257 @@ fn ticker_validator(name: &str) -> Result<(), String> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bitcoin::hashes::hex::FromHex;
+    use bitcoin::{Transaction, TxIn, TxOut, Txid};
+    use commit_verify::CommitConceal;
+
+    #[test]
+    fn test_negotiate_wire_format() {
+        assert_eq!(
+            negotiate_wire_format(&[
+                FileFormat::Yaml,
+                FileFormat::StrictEncode
+            ]),
+            Some(FileFormat::StrictEncode)
+        );
+        #[cfg(feature = "serde")]
+        assert_eq!(
+            negotiate_wire_format(&[
+                FileFormat::Json,
+                FileFormat::StrictEncode
+            ]),
+            Some(FileFormat::Json)
+        );
+        assert_eq!(negotiate_wire_format(&[FileFormat::Yaml]), None);
+        assert_eq!(negotiate_wire_format(&[]), None);
+    }
+
+    fn outpoint(vout: u32) -> OutPoint {
+        OutPoint::new(
+            Txid::from_hex(
+                "d47df6cf7a0eff79d3afeab7614404e43a0fa4498ff081918a2e75d7366cd730",
+            )
+            .unwrap(),
+            vout,
+        )
+    }
+
+    fn transfer(payment: AtomicValue, change: AtomicValue) -> TransferReq {
+        let witness =
+            PartiallySignedTransaction::from_unsigned_tx(Transaction {
+                version: 2,
+                lock_time: 0,
+                input: vec![],
+                output: vec![],
+            })
+            .unwrap();
+        TransferReq {
+            contract_id: ContractId::default(),
+            witness,
+            inputs: bset![outpoint(0), outpoint(1)],
+            payment: bmap! {
+                SealEndpoint::TxOutpoint(
+                    OutpointReveal::from(outpoint(5)).commit_conceal()
+                ) => payment
+            },
+            change: bmap! {
+                SealDefinition::TxOutpoint(OutpointReveal::from(outpoint(6)))
+                    => change
+            },
+            change_outpoint: None,
+            idempotency_key: None,
+        }
+    }
+
+    fn issue(
+        precision: u8,
+        allocation: Vec<AtomicValue>,
+        inflation: Vec<AtomicValue>,
+    ) -> IssueReq {
+        let coins = |amounts: Vec<AtomicValue>| {
+            amounts
+                .into_iter()
+                .enumerate()
+                .map(|(vout, coins)| OutpointCoins {
+                    coins,
+                    outpoint: outpoint(vout as u32),
+                })
+                .collect()
+        };
+        IssueReq {
+            ticker: s!("TST"),
+            name: s!("Test asset"),
+            description: None,
+            precision,
+            allocation: coins(allocation),
+            inflation: coins(inflation),
+            renomination: None,
+            epoch: None,
+            idempotency_key: None,
+        }
+    }
+
+    #[test]
+    fn test_renominate_validation() {
+        let mut renominate = RenominateReq {
+            contract_id: ContractId::default(),
+            witness: PartiallySignedTransaction::from_unsigned_tx(
+                Transaction {
+                    version: 2,
+                    lock_time: 0,
+                    input: vec![],
+                    output: vec![],
+                },
+            )
+            .unwrap(),
+            ticker: None,
+            name: None,
+            renomination_seal: None,
+        };
+        assert_eq!(
+            renominate.validate(),
+            Err(RenominateError::NothingToChange)
+        );
+        renominate.name = Some(s!("Renamed asset"));
+        assert_eq!(renominate.validate(), Ok(()));
+        renominate.ticker = Some(s!("T"));
+        assert_eq!(renominate.validate(), Err(RenominateError::InvalidTicker));
+        renominate.ticker = Some(s!("NEW"));
+        assert_eq!(renominate.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_issue_validation() {
+        assert_eq!(issue(8, vec![1000, 500], vec![10000]).validate(), Ok(()));
+        assert_eq!(issue(18, vec![u64::MAX], vec![]).validate(), Ok(()));
+
+        assert_eq!(
+            issue(19, vec![1000], vec![]).validate(),
+            Err(IssueError::PrecisionTooLarge(19))
+        );
+        assert_eq!(
+            issue(8, vec![u64::MAX, 1], vec![]).validate(),
+            Err(IssueError::SupplyOverflow)
+        );
+        assert_eq!(
+            issue(8, vec![u64::MAX - 10], vec![5, 6]).validate(),
+            Err(IssueError::CapOverflow)
+        );
+    }
+
+    #[test]
+    fn test_transfer_validation() {
+        let allocations = bmap! {
+            outpoint(0) => vec![100, 50],
+            outpoint(1) => vec![50],
+            outpoint(2) => vec![1000]
+        };
+
+        let valid = transfer(150, 50);
+        assert_eq!(valid.validate(), Ok(()));
+        assert_eq!(valid.validate_inputs(&allocations), Ok(()));
+
+        let mut no_inputs = valid;
+        no_inputs.inputs = bset![];
+        assert_eq!(no_inputs.validate(), Err(TransferError::NoInputs));
+
+        assert_eq!(transfer(0, 50).validate(), Err(TransferError::ZeroAmount));
+        assert_eq!(transfer(150, 0).validate(), Err(TransferError::ZeroAmount));
+
+        assert_eq!(
+            transfer(150, 51).validate_inputs(&allocations),
+            Err(TransferError::InsufficientInputs {
+                requested: 201,
+                available: 200
+            })
+        );
+
+        assert_eq!(
+            unique_inputs(vec![outpoint(0), outpoint(1)]),
+            Ok(bset![outpoint(0), outpoint(1)])
+        );
+        assert_eq!(
+            unique_inputs(vec![outpoint(0), outpoint(1), outpoint(0)]),
+            Err(TransferError::DuplicateInput(outpoint(0)))
+        );
+    }
+
+    #[test]
+    fn test_batch_validation() {
+        use strict_encoding::StrictDecode;
+
+        let first = transfer(150, 50);
+        let mut second = transfer(100, 10);
+        second.contract_id = ContractId::strict_decode(&[1u8; 32][..]).unwrap();
+        second.inputs = bset![outpoint(2)];
+        assert_eq!(
+            validate_batch(&[first.clone(), second.clone()]),
+            Err(TransferError::UnconsignedPayment(second.contract_id))
+        );
+        second.payment = bmap! {};
+        assert_eq!(validate_batch(&[first.clone(), second.clone()]), Ok(()));
+
+        assert_eq!(validate_batch(&[]), Err(TransferError::EmptyBatch));
+        let mut duplicate = second.clone();
+        duplicate.contract_id = first.contract_id;
+        assert_eq!(
+            validate_batch(&[first.clone(), duplicate]),
+            Err(TransferError::DuplicateContract(first.contract_id))
+        );
+
+        let mut conflicting = second.clone();
+        conflicting.inputs.insert(outpoint(1));
+        assert_eq!(
+            validate_batch(&[first.clone(), conflicting]),
+            Err(TransferError::ConflictingInput(outpoint(1)))
+        );
+
+        let mut other_witness = second.clone();
+        other_witness.witness.global.unsigned_tx.lock_time = 1;
+        assert_eq!(
+            validate_batch(&[first.clone(), other_witness]),
+            Err(TransferError::WitnessMismatch)
+        );
+
+        let mut other_key = second;
+        other_key.idempotency_key = Some([1u8; 32]);
+        assert_eq!(
+            validate_batch(&[first, other_key]),
+            Err(TransferError::IdempotencyKeyMismatch)
+        );
+    }
+
+    #[test]
+    fn test_transfer_size_estimate() {
+        let mut transfer = transfer(150, 50);
+        transfer.inputs = bset![outpoint(0)];
+        let single = transfer.estimated_vbytes();
+        assert_eq!(single, 11 + 68 + 31);
+
+        transfer.inputs = bset![outpoint(0), outpoint(1), outpoint(2)];
+        assert_eq!(transfer.estimated_vbytes(), single + 2 * 68);
+        assert_eq!(transfer.estimated_fee(2), 2 * (single as u64 + 2 * 68));
+
+        // Witness transaction inputs spending asset inputs are not counted
+        // twice
+        transfer.witness.global.unsigned_tx.input = vec![TxIn {
+            previous_output: outpoint(0),
+            ..TxIn::default()
+        }];
+        assert_eq!(transfer.estimated_vbytes(), single + 2 * 68);
+
+        transfer.change.insert(
+            SealDefinition::WitnessVout {
+                vout: 1,
+                blinding: 0,
+            },
+            10,
+        );
+        transfer.witness.global.unsigned_tx.output = vec![TxOut::default(); 2];
+        assert_eq!(transfer.estimated_vbytes(), single + 2 * 68 + 31);
+    }
+}
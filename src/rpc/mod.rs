@@ -14,5 +14,6 @@
 pub mod fungible;
 pub mod reply;
 pub mod stash;
+pub mod wire;
 
 pub use reply::Reply;
@@ -11,18 +11,26 @@
 // along with this software.
 // If not, see <https://opensource.org/licenses/MIT>.
 
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 
 use bitcoin::util::psbt::PartiallySignedTransaction as Psbt;
 use bitcoin::OutPoint;
+use chrono::NaiveDateTime;
+use lnpbp::chain::Chain;
 use microservices::FileFormat;
-use rgb::{AtomicValue, Consignment, ContractId, Disclosure};
-use rgb20::Asset;
+use rgb::{
+    validation, AtomicValue, Consignment, ContractId, Disclosure, NodeId,
+    SchemaId, SealEndpoint,
+};
+use rgb20::{Allocation, Asset};
+use strict_encoding::{strict_deserialize, strict_serialize};
 
 #[cfg(feature = "node")]
 use crate::error::RuntimeError;
 #[cfg(any(feature = "node", feature = "client"))]
-use crate::error::ServiceError;
+use crate::error::{ServiceError, ServiceErrorDomain};
 
 #[derive(Clone, Debug, Display, Api)]
 #[api(encoding = "strict")]
@@ -41,6 +49,14 @@ pub enum Reply {
     #[display("noop()")]
     Nothing,
 
+    /// Response to the ping request
+    #[api(type = 0x0007)]
+    #[display("pong()")]
+    Pong,
+
+    #[api(type = 0x0009)]
+    Info(crate::rpc::reply::ServerInfo),
+
     #[api(type = 0xFF00)]
     Sync(crate::rpc::reply::SyncFormat),
 
@@ -48,6 +64,11 @@ pub enum Reply {
     #[display("asset({0})")]
     Asset(Asset),
 
+    /// Imported asset was already known; contains the asset data
+    #[api(type = 0xFF11)]
+    #[display("already_known({0})")]
+    AlreadyKnown(Asset),
+
     #[api(type = 0xFF02)]
     #[display("outpoint_assets(...)")]
     OutpointAssets(BTreeMap<ContractId, Vec<AtomicValue>>),
@@ -82,6 +103,39 @@ pub enum Reply {
     #[api(type = 0xFF0B)]
     #[display("validation_status({0})")]
     ValidationStatus(::rgb::validation::Status),
+
+    #[api(type = 0xFF0D)]
+    ConsignmentReport(crate::rpc::reply::ConsignmentReport),
+
+    #[api(type = 0xFF0E)]
+    TransferPreview(crate::rpc::reply::TransferPreview),
+
+    #[api(type = 0xFF0F)]
+    AssetPage(crate::rpc::reply::AssetPage),
+
+    #[api(type = 0xFF10)]
+    #[display("pending_transfers(...)")]
+    PendingTransfers(Vec<crate::rpc::reply::PendingTransfer>),
+
+    /// Private user label of the asset, if any
+    #[api(type = 0xFF12)]
+    #[display("label(...)")]
+    Label(Option<String>),
+
+    /// Part of a consignment too large for a single transport message
+    #[api(type = 0xFF13)]
+    ConsignmentChunk(crate::rpc::reply::ConsignmentChunk),
+
+    #[api(type = 0xFF14)]
+    BalanceDelta(crate::rpc::reply::BalanceDelta),
+
+    /// Encoding of the RPC messages selected by the daemon
+    #[api(type = 0xFF15)]
+    WireFormat(crate::rpc::reply::WireFormat),
+
+    /// Transfer which consignment is delivered in chunks
+    #[api(type = 0xFF16)]
+    ChunkedTransfer(crate::rpc::reply::ChunkedTransfer),
 }
 
 impl From<internet2::presentation::Error> for Reply {
@@ -114,12 +168,297 @@ impl From<ServiceError> for Reply {
 #[display("sync(using: {0}, ...)")]
 pub struct SyncFormat(pub FileFormat, pub Vec<u8>);
 
+/// Encoding of the RPC messages selected by the daemon in reply to
+/// [`crate::rpc::fungible::Request::Hello`]
+#[derive(Clone, PartialEq, Eq, Debug, Display, StrictEncode, StrictDecode)]
+#[display("wire_format({format})")]
+pub struct WireFormat {
+    /// Selected encoding
+    pub format: FileFormat,
+}
+
 #[derive(Clone, Debug, Display, StrictEncode, StrictDecode, Error)]
 #[display("transfer(...)")]
 pub struct Transfer {
     pub consignment: Consignment,
     pub disclosure: Disclosure,
     pub witness: Psbt,
+
+    /// Outpoint which receives the remaining assets as a change, if any
+    pub change_outpoint: Option<OutPoint>,
+}
+
+impl Transfer {
+    /// Splits the transfer into [`ChunkedTransfer`] and the chunks of its
+    /// consignment carrying at most `chunk_size` bytes of data each
+    pub fn into_chunks(
+        self,
+        chunk_size: usize,
+    ) -> (ChunkedTransfer, Vec<ConsignmentChunk>) {
+        let chunks = ConsignmentChunk::split(&self.consignment, chunk_size);
+        let transfer = ChunkedTransfer {
+            disclosure: self.disclosure,
+            witness: self.witness,
+            change_outpoint: self.change_outpoint,
+            chunks: chunks.len() as u32,
+        };
+        (transfer, chunks)
+    }
+}
+
+/// Transfer which consignment is too large to be sent as a single transport
+/// message. It is returned in place of [`Transfer`]; the client requests
+/// each of the consignment `chunks` with
+/// [`crate::rpc::fungible::Request::ConsignmentChunk`] and reassembles the
+/// consignment from them.
+#[derive(Clone, Debug, Display, StrictEncode, StrictDecode)]
+#[display("chunked_transfer({chunks}, ...)")]
+pub struct ChunkedTransfer {
+    pub disclosure: Disclosure,
+    pub witness: Psbt,
+
+    /// Outpoint which receives the remaining assets as a change, if any
+    pub change_outpoint: Option<OutPoint>,
+
+    /// Total number of the consignment chunks
+    pub chunks: u32,
+}
+
+impl ChunkedTransfer {
+    /// Completes the transfer with the consignment reassembled from the
+    /// chunks
+    pub fn complete(self, consignment: Consignment) -> Transfer {
+        Transfer {
+            consignment,
+            disclosure: self.disclosure,
+            witness: self.witness,
+            change_outpoint: self.change_outpoint,
+        }
+    }
+}
+
+/// Errors reassembling the consignment from its chunks
+#[derive(Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum ChunkError {
+    /// Consignment chunk #{0} is missing
+    Missing(u32),
+
+    /// Consignment chunks are duplicated, out of range or disagree on the
+    /// total number of chunks
+    Mismatch,
+
+    /// Reassembled consignment can't be decoded: {0}
+    #[from]
+    Encoding(strict_encoding::Error),
+}
+
+/// Part of the strict-encoded consignment which is too large to be sent as a
+/// single transport message. Chunks are numbered from zero; the receiver
+/// collects all `total` chunks, which may arrive in any order, and decodes
+/// the consignment from their concatenated data.
+#[derive(Clone, PartialEq, Eq, Debug, Display, StrictEncode, StrictDecode)]
+#[display("consignment_chunk({seq}/{total}, ...)")]
+pub struct ConsignmentChunk {
+    /// Sequence number of the chunk
+    pub seq: u32,
+
+    /// Total number of the consignment chunks
+    pub total: u32,
+
+    /// Chunk of the strict-encoded consignment data
+    pub data: Vec<u8>,
+}
+
+impl ConsignmentChunk {
+    /// Maximum size of the chunk data, limited by the strict encoding of
+    /// the byte vectors
+    pub const MAX_SIZE: usize = u16::MAX as usize;
+
+    /// Splits strict-encoded consignment into chunks carrying at most
+    /// `chunk_size` bytes of data (but no more than [`Self::MAX_SIZE`])
+    pub fn split(
+        consignment: &Consignment,
+        chunk_size: usize,
+    ) -> Vec<ConsignmentChunk> {
+        let data =
+            strict_serialize(consignment).expect("Memory encoders do not fail");
+        let chunks = data
+            .chunks(chunk_size.max(1).min(Self::MAX_SIZE))
+            .collect::<Vec<_>>();
+        let total = chunks.len() as u32;
+        chunks
+            .into_iter()
+            .enumerate()
+            .map(|(seq, data)| ConsignmentChunk {
+                seq: seq as u32,
+                total,
+                data: data.to_vec(),
+            })
+            .collect()
+    }
+
+    /// Reassembles the consignment from its chunks, which may be provided in
+    /// any order. Fails with [`ChunkError::Missing`] reporting the first
+    /// missing chunk if some of them were not delivered.
+    pub fn reassemble(
+        chunks: impl IntoIterator<Item = ConsignmentChunk>,
+    ) -> Result<Consignment, ChunkError> {
+        let mut total = None;
+        let mut parts = BTreeMap::new();
+        for chunk in chunks {
+            if *total.get_or_insert(chunk.total) != chunk.total
+                || chunk.seq >= chunk.total
+                || parts.insert(chunk.seq, chunk.data).is_some()
+            {
+                return Err(ChunkError::Mismatch);
+            }
+        }
+        let total = total.ok_or(ChunkError::Missing(0))?;
+        if let Some(seq) = (0..total).find(|seq| !parts.contains_key(seq)) {
+            return Err(ChunkError::Missing(seq));
+        }
+        let data = parts
+            .into_iter()
+            .flat_map(|(_, data)| data)
+            .collect::<Vec<_>>();
+        Ok(strict_deserialize(data)?)
+    }
+}
+
+/// Detailed report on the consignment validation or acceptance, listing the
+/// outcome of the validation checks and the changes to the asset state
+#[derive(Clone, Debug, Display, StrictEncode, StrictDecode)]
+#[display("consignment_report({contract_id}, ...)")]
+pub struct ConsignmentReport {
+    /// Id of the contract (asset) the consignment belongs to
+    pub contract_id: ContractId,
+
+    /// Status of the schema and consensus validation checks
+    pub status: validation::Status,
+
+    /// Ids of the consignment state transitions which are not concerned by
+    /// any of the validation failures. Empty if some of the failures concern
+    /// the consignment as a whole, like schema mismatch.
+    pub validated_transitions: Vec<NodeId>,
+
+    /// Outpoints behind the concealed consignment seals which were resolved
+    /// with the provided reveal data
+    pub resolved_seals: Vec<OutPoint>,
+
+    /// Allocations which were not known before the consignment was processed
+    pub gained_allocations: BTreeMap<OutPoint, Vec<AtomicValue>>,
+}
+
+/// Page of the known assets ordered by their contract ids, produced in reply
+/// to [`crate::rpc::fungible::Request::ListAssets`]
+#[derive(Clone, Debug, Display, StrictEncode, StrictDecode)]
+#[display("asset_page({offset}, ...)")]
+pub struct AssetPage {
+    /// Assets of the page
+    pub assets: Vec<Asset>,
+
+    /// Number of assets preceding the page
+    pub offset: u32,
+
+    /// Total number of the known assets
+    pub total: u32,
+}
+
+/// Outcome of the transfer dry run: changes to the asset allocations which
+/// would be produced by the transfer, computed without persisting any state
+#[derive(Clone, Debug, Display, StrictEncode, StrictDecode)]
+#[display("transfer_preview({contract_id}, ...)")]
+pub struct TransferPreview {
+    /// Id of the contract (asset) being transferred
+    pub contract_id: ContractId,
+
+    /// Known allocations which would be spent by the transfer inputs
+    pub spent: Vec<Allocation>,
+
+    /// New allocations with revealed seals which would be created by the
+    /// transfer (this does not include concealed payment seals)
+    pub allocations: Vec<Allocation>,
+
+    /// Total amount paid to the receivers
+    pub payment: AtomicValue,
+
+    /// Total amount allocated back to the local party as a change
+    pub change: AtomicValue,
+}
+
+/// Known asset balance of an outpoint before and after the transfer
+#[derive(
+    Clone, Copy, PartialEq, Eq, Debug, Display, StrictEncode, StrictDecode,
+)]
+#[display("{before}->{after}")]
+pub struct BalanceChange {
+    /// Sum of the known allocations on the outpoint before the transfer
+    pub before: AtomicValue,
+
+    /// Sum of the known allocations on the outpoint after the transfer
+    pub after: AtomicValue,
+}
+
+/// Per-outpoint changes of the known asset balance which would be produced
+/// by the transfer, computed without persisting any state. Only outpoints
+/// with changed balance are listed; concealed payment seals are not known to
+/// the sender and are not listed either.
+#[derive(Clone, PartialEq, Debug, Display, StrictEncode, StrictDecode)]
+#[display("balance_delta({contract_id}, ...)")]
+pub struct BalanceDelta {
+    /// Id of the contract (asset) being transferred
+    pub contract_id: ContractId,
+
+    /// Balance changes by outpoint
+    pub outpoints: BTreeMap<OutPoint, BalanceChange>,
+}
+
+/// Information about the daemon for the client compatibility checks
+#[derive(Clone, PartialEq, Eq, Debug, Display, StrictEncode, StrictDecode)]
+#[display("info({version}, {chain}, ...)")]
+pub struct ServerInfo {
+    /// Version of the RGB Node crate the daemon is built from
+    pub version: String,
+
+    /// Ids of the schemata supported by the daemon
+    pub schema_ids: Vec<SchemaId>,
+
+    /// Network used by the daemon
+    pub chain: Chain,
+}
+
+/// Payment of the outbound transfer which is not known to be accepted by the
+/// receiver yet
+#[derive(
+    Clone, Copy, PartialEq, Eq, Debug, Display, StrictEncode, StrictDecode,
+)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+#[display("pending_transfer({contract_id}, {amount}, ...)")]
+pub struct PendingTransfer {
+    /// Id of the contract (asset) being transferred
+    pub contract_id: ContractId,
+
+    /// Amount paid to the receiver
+    pub amount: AtomicValue,
+
+    /// Seal receiving the payment
+    pub seal: SealEndpoint,
+
+    /// Time when the transfer was created
+    pub created_at: NaiveDateTime,
+}
+
+impl ConsignmentReport {
+    /// Returns whether all of the validation checks have passed
+    #[inline]
+    pub fn all_checks_passed(&self) -> bool {
+        self.status.validity() == validation::Validity::Valid
+    }
 }
 
 #[derive(Clone, Debug, Display, StrictEncode, StrictDecode, Error)]
@@ -130,14 +469,107 @@ pub struct Failure {
     pub info: String,
 }
 
+impl Failure {
+    /// Constructs failure with the machine-readable failure code
+    pub fn with(code: FailureCode, info: impl ToString) -> Self {
+        Failure {
+            code: code as u16,
+            info: info.to_string(),
+        }
+    }
+
+    /// Returns machine-readable failure code, if the code is known
+    #[inline]
+    pub fn failure_code(&self) -> Option<FailureCode> {
+        FailureCode::from_u16(self.code)
+    }
+}
+
+/// Machine-readable codes for the failures reported by RGB Node daemons
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Display)]
+#[display(doc_comments)]
+#[repr(u16)]
+#[non_exhaustive]
+pub enum FailureCode {
+    /// Malformed request or reply data
+    Presentation = 0,
+
+    /// Failure of the message transport
+    Transport = 1,
+
+    /// Internal daemon runtime failure
+    Runtime = 2,
+
+    /// Generic failure of the requested service
+    Service = 3,
+
+    /// Asset is not known to the node; import it first
+    UnknownAsset = 4,
+
+    /// Transfer inputs do not have enough assets for the operation
+    InsufficientFunds = 5,
+
+    /// Provided data violate the asset schema
+    SchemaViolation = 6,
+
+    /// Provided data belong to a network different from the one used by the
+    /// node
+    ChainMismatch = 7,
+
+    /// Asset has non-zero balance, so it can't be forgotten without forcing
+    AssetHasBalance = 8,
+
+    /// Asset is imported for monitoring only and can't be spent
+    WatchOnly = 9,
+}
+
+impl FailureCode {
+    /// Parses failure code from its numeric representation
+    pub fn from_u16(code: u16) -> Option<FailureCode> {
+        Some(match code {
+            0 => FailureCode::Presentation,
+            1 => FailureCode::Transport,
+            2 => FailureCode::Runtime,
+            3 => FailureCode::Service,
+            4 => FailureCode::UnknownAsset,
+            5 => FailureCode::InsufficientFunds,
+            6 => FailureCode::SchemaViolation,
+            7 => FailureCode::ChainMismatch,
+            8 => FailureCode::AssetHasBalance,
+            9 => FailureCode::WatchOnly,
+            _ => return None,
+        })
+    }
+}
+
+#[cfg(any(feature = "node", feature = "client"))]
+impl From<&ServiceErrorDomain> for FailureCode {
+    fn from(domain: &ServiceErrorDomain) -> Self {
+        match domain {
+            ServiceErrorDomain::UnknownAsset(_) => FailureCode::UnknownAsset,
+            ServiceErrorDomain::InsufficientFunds(_) => {
+                FailureCode::InsufficientFunds
+            }
+            ServiceErrorDomain::Schema(_) => FailureCode::SchemaViolation,
+            #[cfg(feature = "fungibles")]
+            ServiceErrorDomain::SchemaViolation(_) => {
+                FailureCode::SchemaViolation
+            }
+            ServiceErrorDomain::ChainMismatch(_) => FailureCode::ChainMismatch,
+            ServiceErrorDomain::AssetHasBalance(_) => {
+                FailureCode::AssetHasBalance
+            }
+            ServiceErrorDomain::WatchOnly(_) => FailureCode::WatchOnly,
+            _ => FailureCode::Service,
+        }
+    }
+}
+
 impl From<internet2::presentation::Error> for Failure {
     fn from(err: internet2::presentation::Error) -> Self {
         // TODO #61: Save error code taken from `Error::to_value()` after
         //       implementation of `ToValue` trait and derive macro for enums
-        Failure {
-            code: 0,
-            info: format!("{}", err),
-        }
+        Failure::with(FailureCode::Presentation, err)
     }
 }
 
@@ -145,10 +577,7 @@ impl From<internet2::transport::Error> for Failure {
     fn from(err: internet2::transport::Error) -> Self {
         // TODO #61: Save error code taken from `Error::to_value()` after
         //       implementation of `ToValue` trait and derive macro for enums
-        Failure {
-            code: 1,
-            info: format!("{}", err),
-        }
+        Failure::with(FailureCode::Transport, err)
     }
 }
 
@@ -157,21 +586,13 @@ impl From<RuntimeError> for Failure {
     fn from(err: RuntimeError) -> Self {
         // TODO #61: Save error code taken from `Error::to_value()` after
         //       implementation of `ToValue` trait and derive macro for enums
-        Failure {
-            code: 2,
-            info: format!("{}", err),
-        }
+        Failure::with(FailureCode::Runtime, err)
     }
 }
 
 #[cfg(any(feature = "node", feature = "client"))]
 impl From<ServiceError> for Failure {
     fn from(err: ServiceError) -> Self {
-        // TODO #61: Save error code taken from `Error::to_value()` after
-        //       implementation of `ToValue` trait and derive macro for enums
-        Failure {
-            code: 3,
-            info: format!("{}", err),
-        }
+        Failure::with(FailureCode::from(&err.domain), err)
     }
 }
@@ -0,0 +1,169 @@
+// RGB standard library
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Encodings of the RPC messages on the wire, negotiated with
+//! [`crate::rpc::fungible::Request::Hello`].
+//!
+//! Strict encoding is the native one. With `serde` feature, messages may also
+//! be sent as JSON objects `{"type": ..., "payload": ...}` carrying the
+//! message type id and base64 of the strict-encoded message payload.
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+#[cfg(feature = "serde")]
+use amplify::IoError;
+use internet2::presentation::Error;
+use internet2::{TypedEnum, Unmarshall, Unmarshaller};
+use microservices::FileFormat;
+#[cfg(feature = "serde")]
+use strict_encoding::strict_serialize;
+
+/// RPC message in JSON wire format
+#[cfg(feature = "serde")]
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(crate = "serde_crate")]
+struct JsonMessage {
+    /// Message type id
+    #[serde(rename = "type")]
+    type_id: u16,
+
+    /// Base64 of the strict-encoded message payload
+    payload: String,
+}
+
+/// Encodes RPC message in the given wire format. Formats which are not
+/// supported on the wire (see [`crate::rpc::fungible::WIRE_FORMATS`]) are
+/// never negotiated; messages are strict-encoded for them.
+pub fn encode<T>(message: &T, format: FileFormat) -> Vec<u8>
+where
+    T: TypedEnum,
+{
+    match format {
+        #[cfg(feature = "serde")]
+        FileFormat::Json => serde_json::to_vec(&JsonMessage {
+            type_id: *message.get_type(),
+            payload: base64::encode(message.get_payload()),
+        })
+        .expect("JSON encoder does not fail on strings"),
+        _ => message.serialize(),
+    }
+}
+
+/// Decodes RPC message received in the given wire format. Incomplete
+/// messages are reported with [`Error::Io`] error of
+/// [`std::io::ErrorKind::UnexpectedEof`] kind in any format.
+pub fn decode<T>(
+    unmarshaller: &Unmarshaller<T>,
+    data: &[u8],
+    format: FileFormat,
+) -> Result<Arc<T>, Error>
+where
+    T: TypedEnum,
+{
+    match format {
+        #[cfg(feature = "serde")]
+        FileFormat::Json => {
+            let message: JsonMessage =
+                serde_json::from_slice(data).map_err(|err| {
+                    if err.is_eof() {
+                        Error::Io(IoError::from(
+                            std::io::ErrorKind::UnexpectedEof,
+                        ))
+                    } else {
+                        Error::InvalidValue
+                    }
+                })?;
+            let mut raw = strict_serialize(&message.type_id)?;
+            raw.extend(
+                base64::decode(&message.payload)
+                    .map_err(|_| Error::InvalidValue)?,
+            );
+            unmarshaller.unmarshall(&raw)
+        }
+        _ => unmarshaller.unmarshall(&data),
+    }
+}
+
+/// Detects wire format of the received RPC message, so the daemon may serve
+/// clients which negotiated different formats over the same socket. JSON
+/// messages always start with `{`, while strict-encoded ones start with the
+/// lower byte of the message type id, which is never `0x7B` for the fungible
+/// engine API.
+pub fn detect_format(data: &[u8]) -> FileFormat {
+    match data.first() {
+        #[cfg(feature = "serde")]
+        Some(b'{') => FileFormat::Json,
+        _ => FileFormat::StrictEncode,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rpc::fungible::Request;
+    use internet2::CreateUnmarshaller;
+
+    #[test]
+    fn test_strict_encoding() {
+        let unmarshaller = Request::create_unmarshaller();
+        let data = encode(&Request::Ping, FileFormat::StrictEncode);
+        assert_eq!(data, Request::Ping.serialize());
+        assert_eq!(detect_format(&data), FileFormat::StrictEncode);
+        assert!(matches!(
+            *decode(&unmarshaller, &data, FileFormat::StrictEncode).unwrap(),
+            Request::Ping
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_json_encoding() {
+        use crate::rpc::fungible::LabelReq;
+        use crate::rpc::Reply;
+        use rgb::ContractId;
+
+        let unmarshaller = Request::create_unmarshaller();
+        let request = Request::Label(LabelReq {
+            contract_id: ContractId::default(),
+        });
+        let data = encode(&request, FileFormat::Json);
+        assert_eq!(detect_format(&data), FileFormat::Json);
+        let value: serde_json::Value = serde_json::from_slice(&data).unwrap();
+        assert_eq!(value["type"], 0xFF06);
+        match &*decode(&unmarshaller, &data, FileFormat::Json).unwrap() {
+            Request::Label(label) => {
+                assert_eq!(label.contract_id, ContractId::default())
+            }
+            _ => panic!("label request must be decoded"),
+        }
+
+        let reply = encode(&Reply::Label(Some(s!("label"))), FileFormat::Json);
+        match &*decode(&Reply::create_unmarshaller(), &reply, FileFormat::Json)
+            .unwrap()
+        {
+            Reply::Label(label) => assert_eq!(label.as_deref(), Some("label")),
+            _ => panic!("label reply must be decoded"),
+        }
+
+        assert!(matches!(
+            decode(&unmarshaller, &data[..data.len() - 1], FileFormat::Json),
+            Err(Error::Io(_))
+        ));
+        assert!(matches!(
+            decode(&unmarshaller, b"{\"type\": 1}", FileFormat::Json),
+            Err(Error::InvalidValue)
+        ));
+    }
+}
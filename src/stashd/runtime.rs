@@ -281,6 +281,7 @@ impl Runtime {
             consignment,
             disclosure,
             witness: psbt,
+            change_outpoint: None,
         }))
     }
 
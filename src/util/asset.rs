@@ -0,0 +1,4042 @@
+// RGB standard library
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+// TODO: Consider moving this to RGB20 library
+
+use core::cell::Cell;
+use core::cmp::Ordering;
+use core::convert::TryFrom;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+use amplify::Wrapper;
+use bitcoin::hashes::hex::ToHex;
+use bitcoin::hashes::{sha256, Hash};
+use bitcoin::{OutPoint, Txid};
+use bp::seals::OutpointHash;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use lazy_static::lazy_static;
+use rgb::{
+    value, Assignments, AtomicValue, ContractId, Genesis, Node, NodeId,
+    SchemaId, SealDefinition,
+};
+use rgb20::schema::{FieldType, OwnedRightsType};
+use rgb20::{
+    AccountingAmount, AccountingValue, Allocation, Asset, Issue, Supply,
+};
+use strict_encoding::StrictEncode;
+
+/// Timestamp of the Bitcoin genesis block; RGB contracts can't be issued
+/// before it
+const BITCOIN_GENESIS_TIMESTAMP: i64 = 1231006505;
+
+/// Asset allocation which can't be added to the known asset allocations since
+/// either its seal or its amount is not revealed.
+///
+/// Pending allocation gets promoted to the known allocation once the missing
+/// data are revealed: for a blinded seal this happens when the consignment is
+/// accepted with the matching `OutpointReveal` data; for a confidential
+/// amount – when the amount is disclosed by the sender. The allocation is then
+/// added with [`Asset::add_allocation`] under the same `node_id` and `index`,
+/// after which the pending allocation must be discarded.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Display)]
+#[display("{node_id}#{index}")]
+pub struct PendingAllocation {
+    /// Id of the node defining the allocation
+    pub node_id: NodeId,
+
+    /// Index of the assignment within the node
+    pub index: u16,
+
+    /// Concealed seal definition
+    pub seal_confidential: OutpointHash,
+
+    /// Outpoint of the seal, if the seal is revealed
+    pub outpoint: Option<OutPoint>,
+
+    /// Allocated amount, if the amount is revealed
+    pub amount: Option<AtomicValue>,
+}
+
+/// Lists genesis allocations which are not parsed into the [`Asset`] known
+/// allocations because of concealed seals or amounts
+pub fn pending_allocations(genesis: &Genesis) -> Vec<PendingAllocation> {
+    let assignments = if let Some(assignments) =
+        genesis.owned_rights_by_type(*OwnedRightsType::Assets)
+    {
+        assignments
+    } else {
+        return vec![];
+    };
+
+    let mut pending = vec![];
+    for (index, state) in
+        assignments.to_discrete_state().into_iter().enumerate()
+    {
+        let outpoint = match state.seal_definition() {
+            Some(SealDefinition::TxOutpoint(outpoint_reveal)) => {
+                Some(OutPoint::from(outpoint_reveal))
+            }
+            // Genesis can't have seals defined against witness transaction
+            Some(SealDefinition::WitnessVout { .. }) => continue,
+            None => None,
+        };
+        let amount = state.assigned_state().map(|revealed| revealed.value);
+        if outpoint.is_some() && amount.is_some() {
+            continue;
+        }
+        pending.push(PendingAllocation {
+            node_id: genesis.node_id(),
+            index: index as u16,
+            seal_confidential: state.seal_definition_confidential(),
+            outpoint,
+            amount,
+        });
+    }
+    pending
+}
+
+/// Genesis allocation which is not parsed into the [`Asset`] data since its
+/// seal is defined against the witness transaction output, while genesis can't
+/// have a witness transaction
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Display)]
+#[display("{node_id}#{index}>witness:{vout}")]
+pub struct SkippedAllocation {
+    /// Id of the node defining the allocation
+    pub node_id: NodeId,
+
+    /// Index of the assignment within the node
+    pub index: u16,
+
+    /// Witness transaction output number used by the seal definition
+    pub vout: u32,
+
+    /// Allocated amount, if the amount is revealed
+    pub amount: Option<AtomicValue>,
+}
+
+/// Lists genesis allocations which can't be parsed into the [`Asset`] known
+/// allocations and are skipped by the parser
+pub fn skipped_allocations(genesis: &Genesis) -> Vec<SkippedAllocation> {
+    let assignments = if let Some(assignments) =
+        genesis.owned_rights_by_type(*OwnedRightsType::Assets)
+    {
+        assignments
+    } else {
+        return vec![];
+    };
+
+    assignments
+        .to_discrete_state()
+        .into_iter()
+        .enumerate()
+        .filter_map(|(index, state)| match state.seal_definition() {
+            Some(SealDefinition::WitnessVout { vout, .. }) => {
+                Some(SkippedAllocation {
+                    node_id: genesis.node_id(),
+                    index: index as u16,
+                    vout,
+                    amount: state
+                        .assigned_state()
+                        .map(|revealed| revealed.value),
+                })
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Finds outpoint controlling the asset renomination right defined by the
+/// genesis. RGB20 library does not parse the right into the [`Asset`] data,
+/// so the right is looked up in the genesis directly. Returns `None` if the
+/// genesis does not define the right or its seal is concealed.
+pub fn renomination_right(genesis: &Genesis) -> Option<OutPoint> {
+    match genesis.owned_rights_by_type(*OwnedRightsType::Renomination) {
+        // RGB20 schema allows at most a single renomination right
+        Some(Assignments::Declarative(states)) => {
+            match states.first()?.seal_definition() {
+                Some(SealDefinition::TxOutpoint(outpoint_reveal)) => {
+                    Some(OutPoint::from(outpoint_reveal))
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Event in the asset history reconstructed by [`AssetExt::history`].
+///
+/// Asset data keep timestamp only for the genesis, so the rest of the events
+/// have `timestamp` set to `None`. Sent and burned amounts can't be attributed
+/// to specific operations and are reported as aggregated events.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Display)]
+pub enum AssetEvent {
+    /// Primary issue performed by the asset genesis
+    #[display("genesis({node_id}, {amount})")]
+    Genesis {
+        node_id: NodeId,
+        timestamp: Option<NaiveDateTime>,
+        amount: AtomicValue,
+    },
+
+    /// Secondary issue performed by spending inflation right
+    #[display("issue({node_id}, {amount})")]
+    Issue {
+        node_id: NodeId,
+        timestamp: Option<NaiveDateTime>,
+        amount: AtomicValue,
+    },
+
+    /// Allocation created by a state transition other than an issue
+    #[display("received({node_id}, {amount})")]
+    Received {
+        node_id: NodeId,
+        outpoint: OutPoint,
+        timestamp: Option<NaiveDateTime>,
+        amount: AtomicValue,
+    },
+
+    /// Total of the received and issued assets which are not allocated to
+    /// the known outpoints anymore and were not burned
+    #[display("sent({amount})")]
+    Sent {
+        timestamp: Option<NaiveDateTime>,
+        amount: AtomicValue,
+    },
+
+    /// Total of the burned assets, i.e. the difference between the issued
+    /// amount and known circulating supply
+    #[display("burned({amount})")]
+    Burned {
+        timestamp: Option<NaiveDateTime>,
+        amount: AtomicValue,
+    },
+}
+
+impl AssetEvent {
+    /// Time of the event, if known
+    pub fn timestamp(&self) -> Option<NaiveDateTime> {
+        match self {
+            AssetEvent::Genesis { timestamp, .. }
+            | AssetEvent::Issue { timestamp, .. }
+            | AssetEvent::Received { timestamp, .. }
+            | AssetEvent::Sent { timestamp, .. }
+            | AssetEvent::Burned { timestamp, .. } => *timestamp,
+        }
+    }
+
+    /// Amount of assets affected by the event
+    pub fn amount(&self) -> AtomicValue {
+        match self {
+            AssetEvent::Genesis { amount, .. }
+            | AssetEvent::Issue { amount, .. }
+            | AssetEvent::Received { amount, .. }
+            | AssetEvent::Sent { amount, .. }
+            | AssetEvent::Burned { amount, .. } => *amount,
+        }
+    }
+}
+
+/// Kind of the asset seals defined on an outpoint, reported by
+/// [`AssetExt::outpoint_role`]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Display)]
+pub enum OutpointRole {
+    /// Outpoint controls inflation right and must not be spent as an
+    /// ordinary asset change
+    #[display("inflation")]
+    Inflation,
+
+    /// Outpoint has asset allocations
+    #[display("allocation")]
+    Allocation,
+
+    /// Outpoint both controls inflation right and has asset allocations
+    #[display("both")]
+    Both,
+
+    /// Outpoint is not known to be used by the asset
+    #[display("none")]
+    None,
+}
+
+/// Confirmation status of the witness transaction of an allocation, reported
+/// by [`AssetExt::allocation_status`]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Display)]
+pub enum AllocationStatus {
+    /// Transaction is not mined yet (or is not known to the chain data
+    /// source), so the allocation is pending
+    #[display("pending")]
+    Unconfirmed,
+
+    /// Transaction is mined in the block at the given height
+    #[display("confirmed at {0}")]
+    Confirmed(u32),
+}
+
+impl AllocationStatus {
+    /// Number of confirmations of the allocation given the current chain tip
+    /// height; zero for unconfirmed allocations
+    pub fn confirmations(&self, tip_height: u32) -> u32 {
+        match self {
+            AllocationStatus::Unconfirmed => 0,
+            AllocationStatus::Confirmed(height) => {
+                tip_height.saturating_sub(*height).saturating_add(1)
+            }
+        }
+    }
+}
+
+/// Spending of an outpoint applied to the asset data with
+/// [`AssetExt::on_outpoint_spent`], recorded together with the height of the
+/// block containing the spending transaction, so it can be rolled back with
+/// [`AssetExt::rollback_to_height`] if the block gets orphaned
+#[derive(Clone, PartialEq, Debug)]
+pub struct OutpointSpending {
+    /// Spent outpoint
+    pub outpoint: OutPoint,
+
+    /// Height of the block containing the spending transaction
+    pub height: u32,
+
+    /// Allocations removed from the asset data by the spending
+    pub allocations: Vec<Allocation>,
+}
+
+/// Outcome of adding allocation with [`AssetExt::insert_allocation`]
+#[derive(Clone, Copy, PartialEq, Debug, Display)]
+pub enum AllocationInsertion {
+    /// Allocation was added to the known allocations
+    #[display("added")]
+    Added,
+
+    /// The same allocation is already known, so the asset data were not
+    /// changed
+    #[display("known")]
+    Known,
+
+    /// Different allocation with the same `node_id` and `index` is already
+    /// known; the asset data were not changed. Contains the known allocation.
+    #[display("conflict({0})")]
+    Conflict(Allocation),
+}
+
+/// Inconsistencies in the asset supply data detected by
+/// [`AssetExt::verify_supply_consistency`]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum SupplyError {
+    /// Asset data do not contain primary issue
+    NoPrimaryIssue,
+
+    /// Known circulating supply {circulating} differs from {expected}
+    /// issued by the known primary and secondary issues and not burned
+    CirculatingMismatch {
+        circulating: AtomicValue,
+        expected: AtomicValue,
+    },
+
+    /// Burned supply of {burned} exceeds the total of {issued} issued by the
+    /// known primary and secondary issues
+    BurnedExceedsIssued {
+        burned: AtomicValue,
+        issued: AtomicValue,
+    },
+
+    /// Secondary issues of {issued} exceed total inflation capacity of
+    /// {capacity}
+    ExceedsInflationCapacity {
+        issued: AtomicValue,
+        capacity: AtomicValue,
+    },
+
+    /// Known allocations of {allocated} exceed the maximum possible supply of
+    /// {supply}
+    AllocationsExceedSupply {
+        allocated: AtomicValue,
+        supply: AtomicValue,
+    },
+}
+
+/// Errors splitting the allocation with [`AllocationExt::split`]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum AllocationError {
+    /// Requested amount of {requested} exceeds allocated value of {available}
+    InsufficientValue {
+        available: AtomicValue,
+        requested: AtomicValue,
+    },
+}
+
+/// Difference between known allocations and issues of two snapshots of the
+/// same asset data, produced by [`AssetExt::diff`]. Entries are listed in the
+/// order they are known to the respective snapshot.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct AssetDiff {
+    /// Allocations known to the other snapshot only
+    pub added_allocations: Vec<Allocation>,
+
+    /// Allocations known to this snapshot only
+    pub removed_allocations: Vec<Allocation>,
+
+    /// Issues known to the other snapshot only
+    pub added_issues: Vec<Issue>,
+
+    /// Issues known to this snapshot only
+    pub removed_issues: Vec<Issue>,
+}
+
+impl AssetDiff {
+    /// Checks whether both snapshots have the same allocations and issues
+    pub fn is_empty(&self) -> bool {
+        self.added_allocations.is_empty()
+            && self.removed_allocations.is_empty()
+            && self.added_issues.is_empty()
+            && self.removed_issues.is_empty()
+    }
+}
+
+/// Asset data with memoized total known balance, for assets with many known
+/// allocations. Asset data are accessible read-only via [`Deref`]; known
+/// allocations may be changed only with [`CachedAsset::add_allocation`] and
+/// [`CachedAsset::remove_allocation`], which invalidate the cached balance.
+///
+/// [`Deref`]: core::ops::Deref
+#[derive(Clone, Debug)]
+pub struct CachedAsset {
+    asset: Asset,
+    balance: Cell<Option<AtomicValue>>,
+}
+
+impl CachedAsset {
+    /// Wraps the asset data; the balance is computed on the first request
+    pub fn new(asset: Asset) -> CachedAsset {
+        CachedAsset {
+            asset,
+            balance: Cell::new(None),
+        }
+    }
+
+    /// Unwraps the asset data, dropping the cached balance
+    pub fn into_asset(self) -> Asset {
+        self.asset
+    }
+
+    /// Sum of all known allocations, same as [`Asset::known_atomic_value`],
+    /// recomputed only after the known allocations were changed
+    pub fn cached_known_value(&self) -> AtomicValue {
+        if let Some(balance) = self.balance.get() {
+            return balance;
+        }
+        let balance = self.asset.known_atomic_value();
+        self.balance.set(Some(balance));
+        balance
+    }
+
+    /// Adds known allocation with [`Asset::add_allocation`], invalidating
+    /// the cached balance if the allocation was added
+    pub fn add_allocation(
+        &mut self,
+        outpoint: OutPoint,
+        node_id: NodeId,
+        index: u16,
+        value: value::Revealed,
+    ) -> bool {
+        let added = self.asset.add_allocation(outpoint, node_id, index, value);
+        if added {
+            self.balance.set(None);
+        }
+        added
+    }
+
+    /// Removes known allocation with [`Asset::remove_allocation`],
+    /// invalidating the cached balance if the allocation was removed
+    pub fn remove_allocation(
+        &mut self,
+        outpoint: OutPoint,
+        node_id: NodeId,
+        index: u16,
+        value: value::Revealed,
+    ) -> bool {
+        let removed = self
+            .asset
+            .remove_allocation(outpoint, node_id, index, value);
+        if removed {
+            self.balance.set(None);
+        }
+        removed
+    }
+}
+
+impl core::ops::Deref for CachedAsset {
+    type Target = Asset;
+
+    fn deref(&self) -> &Asset {
+        &self.asset
+    }
+}
+
+impl From<Asset> for CachedAsset {
+    fn from(asset: Asset) -> CachedAsset {
+        CachedAsset::new(asset)
+    }
+}
+
+/// Version of the asset data format, written to the JSON envelope used by
+/// [`AssetExt::to_json`] and to the fungible daemon cache files. Version 1 is
+/// the format of the data written before the versioning was introduced.
+pub const ASSET_JSON_VERSION: u16 = 2;
+
+/// Asset data fields which may be missing in version 1 of the asset data,
+/// since they were added to the RGB20 asset data later, with the JSON values
+/// they default to
+#[cfg(feature = "serde")]
+const ASSET_JSON_V1_DEFAULTS: &[(&str, &str)] = &[
+    ("description", "null"),
+    ("knownIssues", "[]"),
+    ("knownInflation", "{}"),
+    ("knownAllocations", "[]"),
+];
+
+/// Errors exporting and importing asset data in JSON format
+#[cfg(feature = "serde")]
+#[derive(Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum AssetJsonError {
+    /// JSON serialization error: {0}
+    #[from]
+    Json(serde_json::Error),
+
+    /// Unsupported version {0} of the asset data JSON file
+    UnsupportedVersion(u16),
+}
+
+/// Top-level object of the asset JSON file allowing future migrations of the
+/// asset data format
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "serde_crate")]
+struct AssetEnvelope<A> {
+    version: u16,
+    asset: A,
+    /// Private user label of the asset, which is not a part of the asset
+    /// data
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    label: Option<String>,
+}
+
+/// Errors extracting asset data from the contract genesis
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum GenesisError {
+    /// Genesis has schema id {found}, while RGB20 schema id {expected} was
+    /// expected; the contract is not an RGB20 asset
+    SchemaIdMismatch { expected: SchemaId, found: SchemaId },
+
+    /// Genesis does not define required metadata field {0}
+    MissingField(FieldType),
+
+    /// Genesis timestamp {0} is out of the valid range
+    InvalidTimestamp(i64),
+
+    /// Genesis of contract {0} does not match the asset data
+    ContractMismatch(ContractId),
+
+    /// Genesis assigns both inflation right and asset allocation to outpoint
+    /// {0}, which makes spending of the outpoint ambiguous
+    SealRoleConflict(OutPoint),
+
+    /// {0}
+    #[from]
+    #[from(rgb20::schema::Error)]
+    Asset(rgb20::Error),
+}
+
+lazy_static! {
+    /// Id of the RGB20 schema, which is constructed only once
+    pub static ref RGB20_SCHEMA_ID: SchemaId =
+        rgb20::schema::schema().schema_id();
+}
+
+/// Checks that the genesis defines all metadata fields required for the
+/// asset data, reporting the first missing one, and that the genesis
+/// timestamp is not earlier than the Bitcoin genesis block. RGB20 library
+/// reports all missing fields as
+/// [`rgb20::schema::Error::NotAllFieldsPresent`], without naming the field.
+fn check_required_fields(genesis: &Genesis) -> Result<(), GenesisError> {
+    let meta = genesis.metadata();
+    let fields = [
+        (
+            FieldType::Precision,
+            meta.u8(*FieldType::Precision).is_empty(),
+        ),
+        (
+            FieldType::IssuedSupply,
+            meta.u64(*FieldType::IssuedSupply).is_empty(),
+        ),
+        (
+            FieldType::Ticker,
+            meta.string(*FieldType::Ticker).is_empty(),
+        ),
+        (FieldType::Name, meta.string(*FieldType::Name).is_empty()),
+        (
+            FieldType::Timestamp,
+            meta.i64(*FieldType::Timestamp).is_empty(),
+        ),
+    ];
+    if let Some((field, _)) = fields.iter().find(|(_, missing)| *missing) {
+        return Err(GenesisError::MissingField(*field));
+    }
+
+    // RGB20 library converts the timestamp with a panicking
+    // `NaiveDateTime::from_timestamp`, so it must be validated beforehand
+    let timestamp = meta.i64(*FieldType::Timestamp)[0];
+    if timestamp < BITCOIN_GENESIS_TIMESTAMP
+        || NaiveDateTime::from_timestamp_opt(timestamp, 0).is_none()
+    {
+        return Err(GenesisError::InvalidTimestamp(timestamp));
+    }
+    Ok(())
+}
+
+/// Sums inflation amounts assigned to the concealed seals of the genesis,
+/// which constitute the issue limit of the asset data. The sum saturates at
+/// `u64::MAX`, which is also used if some of the amounts are concealed.
+///
+/// Also reports whether accumulation of the issue limit by the RGB20 library
+/// overflows: the library checks for `u64::MAX` only before each addition,
+/// so a sum exceeding it panics in debug builds and wraps otherwise.
+fn concealed_inflation(genesis: &Genesis) -> (AtomicValue, bool) {
+    let mut issue_limit: AtomicValue = 0;
+    let mut overflows = false;
+    let states = genesis
+        .owned_rights_by_type(*OwnedRightsType::Inflation)
+        .map(|assignments| assignments.to_custom_state())
+        .unwrap_or_default();
+    for state in states {
+        match (state.seal_definition(), state.assigned_state()) {
+            (Some(_), Some(_)) => {}
+            (None, Some(data)) => {
+                let amount = data.u64().unwrap_or_default();
+                if issue_limit < AtomicValue::MAX {
+                    overflows |= issue_limit.checked_add(amount).is_none();
+                }
+                issue_limit = issue_limit.saturating_add(amount);
+            }
+            _ => issue_limit = AtomicValue::MAX,
+        }
+    }
+    (issue_limit, overflows)
+}
+
+/// Parses asset data from the genesis which inflation amounts would overflow
+/// the issue limit accumulated by the RGB20 library. The genesis is parsed
+/// without inflation rights, and the data depending on them or on the genesis
+/// id are restored afterwards.
+fn parse_saturated(
+    genesis: &Genesis,
+    issue_limit: AtomicValue,
+) -> Result<Asset, GenesisError> {
+    let mut owned_rights = genesis.owned_rights().clone().into_inner();
+    let inflation = owned_rights
+        .remove(&*OwnedRightsType::Inflation)
+        .map(|assignments| assignments.to_custom_state())
+        .unwrap_or_default();
+    let stripped = Genesis::with(
+        genesis.schema_id(),
+        genesis.chain().clone(),
+        genesis.metadata().clone(),
+        owned_rights,
+        genesis.public_rights().clone().into_inner(),
+    );
+    let asset = Asset::try_from(stripped)?;
+
+    let mut known_inflation = BTreeMap::new();
+    for state in inflation {
+        if let (Some(seal), Some(data)) =
+            (state.seal_definition(), state.assigned_state())
+        {
+            let outpoint =
+                OutPoint::try_from(seal).map_err(rgb20::Error::from)?;
+            let amount = data
+                .u64()
+                .ok_or(rgb20::schema::Error::NotAllFieldsPresent)?;
+            known_inflation.insert(outpoint, amount);
+        }
+    }
+    let node_id = NodeId::from_inner(genesis.contract_id().into_inner());
+    let known_issues = asset
+        .known_issues()
+        .iter()
+        .map(|issue| Issue::with(genesis.node_id(), *issue.amount(), None))
+        .collect();
+    let known_allocations = asset
+        .known_allocations()
+        .iter()
+        .map(|allocation| {
+            Allocation::with(
+                node_id,
+                *allocation.index(),
+                *allocation.outpoint(),
+                *allocation.revealed_amount(),
+            )
+        })
+        .collect();
+    let supply = asset.supply();
+    Ok(Asset::with(
+        genesis.to_string(),
+        genesis.contract_id(),
+        asset.ticker().clone(),
+        asset.name().clone(),
+        asset.description().clone(),
+        Supply::with(
+            *supply.known_circulating(),
+            *supply.is_issued_known(),
+            issue_limit,
+        ),
+        asset.chain().clone(),
+        *asset.decimal_precision(),
+        *asset.date(),
+        known_issues,
+        known_inflation,
+        known_allocations,
+    ))
+}
+
+/// Helper methods for RGB20 [`Asset`] data which are not provided by the RGB20
+/// library itself
+pub trait AssetExt {
+    /// Extracts asset data from the contract genesis.
+    ///
+    /// Unlike `TryFrom<Genesis>` implementation from RGB20 library, marks all
+    /// issues as known for the assets which genesis does not define any
+    /// inflation rights: such assets have fixed supply and their total
+    /// circulating supply is equal to the supply issued by the genesis.
+    ///
+    /// Genesis allocations which can't be parsed are skipped with a warning;
+    /// use [`AssetExt::parse_genesis`] to get the list of them. Missing
+    /// required metadata fields are reported by their [`FieldType`].
+    fn from_genesis(genesis: &Genesis) -> Result<Asset, GenesisError>;
+
+    /// Extracts asset data from the contract genesis in the same way as
+    /// [`AssetExt::from_genesis`], returning also the list of genesis
+    /// allocations which were skipped by the parser. If the list is not
+    /// empty, the parsed asset data are incomplete.
+    fn parse_genesis(
+        genesis: &Genesis,
+    ) -> Result<(Asset, Vec<SkippedAllocation>), GenesisError>;
+
+    /// Updates asset data from the re-imported contract genesis. Fields
+    /// defined by the genesis (ticker, name, description, precision, date and
+    /// the issue limit) are replaced; genesis issue, inflation rights and
+    /// allocations are merged into the known ones with
+    /// [`AssetExt::insert_allocation`], keeping allocations acquired through
+    /// transfers. Known circulating supply is kept, since it accounts for
+    /// secondary issues and burns.
+    ///
+    /// Genesis allocations removed from the asset data as spent are restored
+    /// by the merge; their spending has to be re-applied with
+    /// [`AssetExt::on_outpoint_spent`].
+    fn refresh_from_genesis(
+        &mut self,
+        genesis: &Genesis,
+    ) -> Result<(), GenesisError>;
+
+    /// Merges another snapshot of the same asset data (like one restored from
+    /// a backup) into this one. Known issues are deduplicated by their id,
+    /// preferring the issue with known origin; inflation rights and
+    /// allocations are merged, the latter with
+    /// [`AssetExt::insert_allocation`]. Known circulating supply is set to the
+    /// larger of the two, and the supply is marked as fully known only if
+    /// both snapshots agree on it.
+    fn merge(&mut self, other: &Asset) -> Result<(), GenesisError>;
+
+    /// Returns primary issue of the asset, i.e. the one performed by the
+    /// genesis. A valid asset has exactly one primary issue; if there are
+    /// more, the first one is returned.
+    fn primary_issue(&self) -> Option<&Issue>;
+
+    /// Iterates over all known secondary issues of the asset, i.e. issues
+    /// originating from the spending of inflation rights
+    fn secondary_issues(&self) -> Box<dyn Iterator<Item = &Issue> + '_>;
+
+    /// Finds known issue by the id of the node which performed it
+    fn issue_by_id(&self, id: NodeId) -> Option<&Issue>;
+
+    /// Lists known inflation rights as pairs of the outpoint controlling the
+    /// right and the maximum atomic amount it allows to issue, ordered by
+    /// the outpoint
+    fn inflation_rights(&self) -> Vec<(OutPoint, AtomicValue)>;
+
+    /// Returns aggregate atomic amount which may ever be issued by all of the
+    /// inflation rights: the sum of the known inflation rights and of the
+    /// supply issue limit, which accounts for the rights on concealed seals
+    /// (and equals to `u64::MAX` if their amounts are unknown). The sum
+    /// saturates at `u64::MAX`.
+    fn total_inflation_capacity(&self) -> AtomicValue;
+
+    /// Detects whether the outpoint controls known inflation right, has known
+    /// asset allocations, or both
+    fn outpoint_role(&self, outpoint: &OutPoint) -> OutpointRole;
+
+    /// Computes digest of the known allocations, which does not depend on
+    /// the order in which the allocations were added, for detecting changes
+    /// in the allocation set between synchronizations. Allocations are
+    /// strict-encoded in the canonical order: by outpoint, then by node id
+    /// and then by assignment index.
+    fn allocations_digest(&self) -> sha256::Hash;
+
+    /// Derives confirmation status of the known allocations on the outpoint
+    /// from the chain data, since the asset data do not carry it: the heights
+    /// of the mined transactions and the current chain tip height. Transactions
+    /// reported above the chain tip are considered unconfirmed, as the chain
+    /// data are inconsistent for them (e.g. after a reorg). Returns `None` if
+    /// the asset has no known allocations on the outpoint.
+    fn allocation_status(
+        &self,
+        outpoint: &OutPoint,
+        tx_heights: &BTreeMap<Txid, u32>,
+        tip_height: u32,
+    ) -> Option<AllocationStatus>;
+
+    /// Selects the known allocations to cover the target atomic amount with
+    /// the fewest transaction inputs, minimizing witness transaction size and
+    /// fee. Allocations are grouped by outpoint, since spending an outpoint
+    /// spends all of its allocations; returns the selected outpoints with
+    /// their total amounts, or `None` if all known allocations do not cover
+    /// the target.
+    ///
+    /// Plain largest-first selection already uses the fewest inputs, but
+    /// overshoots the target with its smallest input, producing larger change
+    /// and leaving small allocations unspent. Here all but the last input are
+    /// the largest outpoints, and the last input is the smallest outpoint
+    /// which still covers the rest of the target.
+    fn select_allocations_min_inputs(
+        &self,
+        target: AtomicValue,
+    ) -> Option<Vec<(OutPoint, AtomicValue)>>;
+
+    /// Returns known allocations with amounts below the `threshold`. Such
+    /// dust allocations clutter coin selection and may cost more in fees than
+    /// they are worth, so they are better consolidated into a single
+    /// allocation.
+    fn dust_allocations(&self, threshold: AtomicValue) -> Vec<Allocation>;
+
+    /// Returns fraction of the known circulating supply held by the known
+    /// allocations on the outpoint, or `0.0` if the known circulating supply
+    /// is zero
+    fn ownership_fraction(&self, outpoint: &OutPoint) -> f64;
+
+    /// Checks the supply arithmetic of the asset data: known circulating
+    /// supply must be equal to the sum of the primary and all known secondary
+    /// issues less the `burned` supply, and secondary issues must not exceed
+    /// [`AssetExt::total_inflation_capacity`]. The asset data do not keep
+    /// the burned supply, so it must be provided by the caller from the known
+    /// burn transitions.
+    fn verify_supply_consistency(
+        &self,
+        burned: AtomicValue,
+    ) -> Result<(), SupplyError>;
+
+    /// Checks that the known allocations do not assign more assets than may
+    /// ever be issued: the primary issue plus
+    /// [`AssetExt::total_inflation_capacity`]. Allocations exceeding it can be
+    /// produced only by corrupted or malicious data, so the check should be
+    /// performed each time allocations are added to the asset.
+    fn verify_allocations_within_supply(&self) -> Result<(), SupplyError>;
+
+    /// Lists known inflation outpoints for which there is no secondary issue
+    /// originating from them. These outpoints are either unspent, or their
+    /// spending is not known yet.
+    fn unreconciled_inflation(&self) -> Vec<OutPoint>;
+
+    /// Checks whether the asset supply is fully audited, i.e. each known
+    /// inflation outpoint has a corresponding secondary issue, or all issues
+    /// were already confirmed as known by [`AssetExt::reconcile`]. For such
+    /// assets known circulating supply is equal to the total circulating
+    /// supply.
+    fn is_fully_reconciled(&self) -> bool;
+
+    /// Re-creates asset data updating `is_issued_known` flag of the supply.
+    /// The flag is set if each known inflation outpoint either has a
+    /// corresponding secondary issue or is present in `unspent_inflation`
+    /// set of outpoints confirmed unspent; otherwise the flag is reset to
+    /// `None`, since spending status of the rest of inflation outpoints is
+    /// unknown.
+    fn reconcile(&self, unspent_inflation: &BTreeSet<OutPoint>) -> Asset;
+
+    /// Re-creates asset data removing all known allocations on the burned
+    /// outpoints and decreasing known circulating supply by the burned
+    /// amount. Returns the updated asset together with the burned amount.
+    fn burn(&self, outpoints: &BTreeSet<OutPoint>) -> (Asset, AtomicValue);
+
+    /// Applies confirmed spending of the outpoint to the asset data, removing
+    /// and returning all known allocations on that outpoint. If the outpoint
+    /// controls an inflation right which does not have a corresponding known
+    /// secondary issue, the supply is marked as having unknown issues, since
+    /// spending of the right may have inflated the asset.
+    fn on_outpoint_spent(&mut self, outpoint: &OutPoint) -> Vec<Allocation>;
+
+    /// Rolls back the outpoint spendings from the blocks above `height`,
+    /// orphaned by a chain reorganization: allocations removed by these
+    /// spendings are restored, and if some of the spent outpoints controls a
+    /// known inflation right, issued supply is marked as not reconciled, since
+    /// the secondary issue may have been orphaned as well. Returns the
+    /// restored allocations.
+    fn rollback_to_height(
+        &mut self,
+        height: u32,
+        spendings: &[OutpointSpending],
+    ) -> Vec<Allocation>;
+
+    /// Adds allocation to the known allocations, enforcing `node_id` and
+    /// `index` as the allocation primary key. Unlike
+    /// [`Asset::add_allocation`], which only skips exact duplicates, rejects
+    /// allocations conflicting with the known allocation under the same key.
+    fn insert_allocation(
+        &mut self,
+        outpoint: OutPoint,
+        node_id: NodeId,
+        index: u16,
+        value: value::Revealed,
+    ) -> AllocationInsertion;
+
+    /// Adds several allocations with [`AssetExt::insert_allocation`],
+    /// skipping the ones already known and the ones conflicting with the
+    /// known allocations. Returns the number of the added allocations.
+    fn add_allocations<I>(&mut self, allocations: I) -> usize
+    where
+        I: IntoIterator<Item = (OutPoint, NodeId, u16, value::Revealed)>;
+
+    /// Returns known allocations originating from the asset issuance: the
+    /// genesis or one of the known secondary issues
+    fn issued_allocations(&self) -> Vec<&Allocation>;
+
+    /// Returns known allocations received via transfers, i.e. all known
+    /// allocations not returned by [`AssetExt::issued_allocations`]
+    fn received_allocations(&self) -> Vec<&Allocation>;
+
+    /// Returns known allocations created by the state transition (or genesis)
+    /// with the given node id, in the order they are known to the asset
+    fn allocations_by_node(&self, node_id: NodeId) -> Vec<&Allocation>;
+
+    /// Detects pairs of known allocations claiming the same assignment, i.e.
+    /// having the same `node_id` and `index`, but a different seal or
+    /// amount. Valid asset data can't contain such pairs: they appear only
+    /// when allocations from conflicting consignments were added with
+    /// [`Asset::add_allocation`], and neither allocation of the pair should be
+    /// treated as spendable until the conflict is resolved.
+    ///
+    /// Inputs of the state transitions are not a part of the asset data, so
+    /// transitions spending the same outpoint under different node ids can't
+    /// be detected here.
+    fn detect_allocation_conflicts(&self) -> Vec<(Allocation, Allocation)>;
+
+    /// Compares two snapshots of the asset data ignoring the order of known
+    /// allocations and issues. Snapshots are equal if they have the same id,
+    /// ticker, name, supply, chain and precision and the same sets of known
+    /// allocations and issues; the rest of the data (like description or
+    /// inflation rights) are not compared.
+    fn semantic_eq(&self, other: &Asset) -> bool;
+
+    /// Lists allocations and issues added and removed in the `other`
+    /// snapshot of the asset data comparing to this one
+    fn diff(&self, other: &Asset) -> AssetDiff;
+
+    /// Reconstructs chronological log of the asset events from the known
+    /// issues and allocations. The log starts with the genesis event,
+    /// followed by secondary issues in the order they are known, allocations
+    /// received from other state transitions (ordered by node id and
+    /// outpoint) and, finally, aggregated sent and burned amounts.
+    fn history(&self) -> Vec<AssetEvent>;
+
+    /// Returns short asset identifier for display purposes; see
+    /// [`contract_fingerprint`]
+    fn fingerprint(&self) -> String;
+
+    /// Renders human-readable one-line asset summary with the ticker, name,
+    /// total known balance and the supply status, e.g.
+    /// `TST (Test asset): 15.00 TST known, 15.00/115.00 minted`. The
+    /// minted amount is the known circulating supply and the cap is the
+    /// primary issue plus [`AssetExt::total_inflation_capacity`], rendered as
+    /// `unlimited` when the capacity is unknown. Unlike `Display`
+    /// implementation of [`Asset`], which prints debug data, the summary is
+    /// intended for the users.
+    fn summary(&self) -> String;
+
+    /// Returns the asset issue date from the contract genesis timestamp. The
+    /// date is stored without a timezone, while the genesis timestamp is
+    /// always UTC; use this method instead of interpreting the raw date.
+    fn issued_at(&self) -> DateTime<Utc>;
+
+    /// Returns name of the file for caching the asset data, without an
+    /// extension: hex encoding of the full contract id. Unlike ticker and
+    /// name, which are user-controlled and may collide or contain path
+    /// separators, the contract id is unique and safe for use in paths.
+    fn cache_filename(&self) -> String;
+
+    /// Returns value of a single atomic unit in accounting terms, i.e. the
+    /// minimum transferable amount, rendered with the asset decimal
+    /// precision: `"0.00000001"` for 8 decimal digits and `"1"` for
+    /// indivisible assets
+    fn smallest_unit(&self) -> String;
+
+    /// Detects whether the asset has non-zero decimal precision, i.e. its
+    /// whole units may be split. Amounts of indivisible assets are integer
+    /// numbers of whole units; use
+    /// [`AccountingAmountExt::indivisible_value`] for them instead of the
+    /// floating point accounting value.
+    fn is_divisible(&self) -> bool;
+
+    /// Wraps raw atomic value, as found in the protocol data structures, into
+    /// an accounting amount with the asset decimal precision
+    fn amount(&self, atomic: AtomicValue) -> AccountingAmount;
+
+    /// Lists distinct decimal precisions implied by the known allocations,
+    /// for debugging precision mismatches. Allocations keep bare atomic
+    /// values, so the precision of each allocation is derived relative to the
+    /// asset: it is the asset decimal precision increased by the number of
+    /// decimal digits the atomic value has to be scaled down by to fit into
+    /// the maximum asset supply (primary issue and inflation capacity). A
+    /// consistent asset reports just its declared precision; any other value
+    /// indicates an allocation scaled with a wrong precision.
+    fn allocation_precisions(&self) -> BTreeSet<u8>;
+
+    /// Exports all known asset allocations as CSV table with
+    /// `outpoint,node_id,index,atomic_value,accounting_value` columns,
+    /// including the header row. Rows are ordered by outpoint; outpoints are
+    /// rendered as `txid:vout`, and accounting values use the asset decimal
+    /// precision.
+    fn allocations_to_csv(&self) -> String;
+
+    /// Exports asset data as JSON object of the form
+    /// `{ "version": 2, "asset": {...} }`
+    #[cfg(feature = "serde")]
+    fn to_json(&self) -> Result<String, AssetJsonError>;
+
+    /// Exports asset state in this node's own compact JSON format for
+    /// moving assets between wallets, unlike [`AssetExt::to_json`], which
+    /// serializes internal asset data. The format is not defined by any RGB
+    /// standard, so other implementations may not read it. Fields are mapped
+    /// as follows:
+    /// - `"schema"`: always `"RGB20"`;
+    /// - `"contractId"`: Bech32 encoding of the contract id;
+    /// - `"ticker"`, `"name"` and `"description"` (`null` if absent): asset
+    ///   metadata;
+    /// - `"precision"`: decimal precision;
+    /// - `"issuedSupply"`: known circulating supply in atomic units;
+    /// - `"allocations"`: array of known allocations ordered by outpoint,
+    ///   each being `{ "outpoint": "txid:vout", "amount": atomic_value }`.
+    #[cfg(feature = "serde")]
+    fn to_rgb20_json(&self) -> Result<String, AssetJsonError>;
+
+    /// Imports asset data from JSON object produced by [`AssetExt::to_json`];
+    /// see [`AssetExt::migrate`]
+    #[cfg(feature = "serde")]
+    fn from_json(json: &str) -> Result<Asset, AssetJsonError>;
+
+    /// Exports asset data as JSON in the same way as [`AssetExt::to_json`],
+    /// adding private user label of the asset to the envelope. The label is
+    /// not a part of the asset data, so it does not affect asset identity,
+    /// [`AssetExt::semantic_eq`] or [`AssetExt::fingerprint`].
+    #[cfg(feature = "serde")]
+    fn to_json_labeled(
+        &self,
+        label: Option<&str>,
+    ) -> Result<String, AssetJsonError>;
+
+    /// Imports asset data together with the asset label from JSON object
+    /// produced by [`AssetExt::to_json_labeled`]
+    #[cfg(feature = "serde")]
+    fn from_json_labeled(
+        json: &str,
+    ) -> Result<(Asset, Option<String>), AssetJsonError>;
+
+    /// Upgrades asset data from the parsed JSON envelope of any supported
+    /// version to the current asset data. Fields which were added to the
+    /// asset data after version 1 and are missing in its data are set to
+    /// their default (empty) values, so caches written by the earlier
+    /// releases still load after the node upgrade. Envelopes of unknown
+    /// versions are rejected.
+    #[cfg(feature = "serde")]
+    fn migrate(raw_value: serde_json::Value) -> Result<Asset, AssetJsonError>;
+}
+
+impl AssetExt for Asset {
+    fn from_genesis(genesis: &Genesis) -> Result<Asset, GenesisError> {
+        let (asset, skipped) = Asset::parse_genesis(genesis)?;
+        if !skipped.is_empty() {
+            warn!(
+                "Genesis of asset {} has {} allocation(s) with seals defined \
+                against witness transaction; they are skipped",
+                asset.id(),
+                skipped.len()
+            );
+        }
+        Ok(asset)
+    }
+
+    fn parse_genesis(
+        genesis: &Genesis,
+    ) -> Result<(Asset, Vec<SkippedAllocation>), GenesisError> {
+        let expected = *RGB20_SCHEMA_ID;
+        if genesis.schema_id() != expected {
+            return Err(GenesisError::SchemaIdMismatch {
+                expected,
+                found: genesis.schema_id(),
+            });
+        }
+        check_required_fields(genesis)?;
+        let asset = match concealed_inflation(genesis) {
+            (issue_limit, true) => parse_saturated(genesis, issue_limit)?,
+            _ => Asset::try_from(genesis.clone())?,
+        };
+        if let Some(outpoint) = asset
+            .known_inflation()
+            .keys()
+            .find(|outpoint| !asset.allocations(**outpoint).is_empty())
+        {
+            return Err(GenesisError::SealRoleConflict(*outpoint));
+        }
+        let skipped = skipped_allocations(genesis);
+
+        let has_inflation = genesis
+            .owned_rights_by_type(*OwnedRightsType::Inflation)
+            .map(|assignments| assignments.len() > 0)
+            .unwrap_or_default();
+        if has_inflation {
+            return Ok((asset, skipped));
+        }
+
+        let supply = Supply::with(
+            *asset.supply().known_circulating(),
+            Some(true),
+            *asset.supply().issue_limit(),
+        );
+        Ok((with_supply(&asset, supply), skipped))
+    }
+
+    fn refresh_from_genesis(
+        &mut self,
+        genesis: &Genesis,
+    ) -> Result<(), GenesisError> {
+        let fresh = Asset::from_genesis(genesis)?;
+        if fresh.id() != self.id() {
+            return Err(GenesisError::ContractMismatch(*fresh.id()));
+        }
+
+        let mut known_issues = self.known_issues().clone();
+        merge_issues(&mut known_issues, fresh.known_issues());
+        let mut known_inflation = self.known_inflation().clone();
+        for (outpoint, amount) in fresh.known_inflation() {
+            known_inflation.entry(*outpoint).or_insert(*amount);
+        }
+        let supply = Supply::with(
+            *self.supply().known_circulating(),
+            *self.supply().is_issued_known(),
+            *fresh.supply().issue_limit(),
+        );
+
+        let mut refreshed = Asset::with(
+            fresh.genesis().clone(),
+            *fresh.id(),
+            fresh.ticker().clone(),
+            fresh.name().clone(),
+            fresh.description().clone(),
+            supply,
+            fresh.chain().clone(),
+            *fresh.decimal_precision(),
+            *fresh.date(),
+            known_issues,
+            known_inflation,
+            self.known_allocations().clone(),
+        );
+        for allocation in fresh.known_allocations() {
+            refreshed.insert_allocation(
+                *allocation.outpoint(),
+                *allocation.node_id(),
+                *allocation.index(),
+                *allocation.revealed_amount(),
+            );
+        }
+        *self = refreshed;
+        Ok(())
+    }
+
+    fn merge(&mut self, other: &Asset) -> Result<(), GenesisError> {
+        if other.id() != self.id() {
+            return Err(GenesisError::ContractMismatch(*other.id()));
+        }
+
+        let mut known_issues = vec![];
+        merge_issues(&mut known_issues, self.known_issues());
+        merge_issues(&mut known_issues, other.known_issues());
+        let mut known_inflation = self.known_inflation().clone();
+        for (outpoint, amount) in other.known_inflation() {
+            known_inflation.entry(*outpoint).or_insert(*amount);
+        }
+        let is_issued_known = if self.supply().is_issued_known()
+            == other.supply().is_issued_known()
+        {
+            *self.supply().is_issued_known()
+        } else {
+            None
+        };
+        let supply = Supply::with(
+            *self
+                .supply()
+                .known_circulating()
+                .max(other.supply().known_circulating()),
+            is_issued_known,
+            *self.supply().issue_limit(),
+        );
+
+        let mut merged = Asset::with(
+            self.genesis().clone(),
+            *self.id(),
+            self.ticker().clone(),
+            self.name().clone(),
+            self.description().clone(),
+            supply,
+            self.chain().clone(),
+            *self.decimal_precision(),
+            *self.date(),
+            known_issues,
+            known_inflation,
+            self.known_allocations().clone(),
+        );
+        for allocation in other.known_allocations() {
+            merged.insert_allocation(
+                *allocation.outpoint(),
+                *allocation.node_id(),
+                *allocation.index(),
+                *allocation.revealed_amount(),
+            );
+        }
+        *self = merged;
+        Ok(())
+    }
+
+    fn primary_issue(&self) -> Option<&Issue> {
+        let mut primary = self
+            .known_issues()
+            .iter()
+            .filter(|issue| issue.is_primary());
+        let first = primary.next();
+        debug_assert!(
+            primary.next().is_none(),
+            "Asset {} has more than one primary issue",
+            self.id()
+        );
+        first
+    }
+
+    fn secondary_issues(&self) -> Box<dyn Iterator<Item = &Issue> + '_> {
+        Box::new(
+            self.known_issues()
+                .iter()
+                .filter(|issue| issue.is_secondary()),
+        )
+    }
+
+    fn issue_by_id(&self, id: NodeId) -> Option<&Issue> {
+        self.known_issues().iter().find(|issue| *issue.id() == id)
+    }
+
+    fn inflation_rights(&self) -> Vec<(OutPoint, AtomicValue)> {
+        self.known_inflation()
+            .iter()
+            .map(|(outpoint, amount)| (*outpoint, *amount))
+            .collect()
+    }
+
+    fn total_inflation_capacity(&self) -> AtomicValue {
+        self.known_inflation()
+            .values()
+            .fold(*self.supply().issue_limit(), |sum, amount| {
+                sum.saturating_add(*amount)
+            })
+    }
+
+    fn outpoint_role(&self, outpoint: &OutPoint) -> OutpointRole {
+        let inflation = self.known_inflation().contains_key(outpoint);
+        let allocation = self
+            .known_allocations()
+            .iter()
+            .any(|allocation| allocation.outpoint() == outpoint);
+        match (inflation, allocation) {
+            (true, true) => OutpointRole::Both,
+            (true, false) => OutpointRole::Inflation,
+            (false, true) => OutpointRole::Allocation,
+            (false, false) => OutpointRole::None,
+        }
+    }
+
+    fn allocations_digest(&self) -> sha256::Hash {
+        let mut allocations = self.known_allocations().clone();
+        allocations.sort_by_key(|allocation| {
+            (
+                *allocation.outpoint(),
+                *allocation.node_id(),
+                *allocation.index(),
+            )
+        });
+        let mut engine = sha256::Hash::engine();
+        allocations
+            .strict_encode(&mut engine)
+            .expect("Memory encoders do not fail");
+        sha256::Hash::from_engine(engine)
+    }
+
+    fn allocation_status(
+        &self,
+        outpoint: &OutPoint,
+        tx_heights: &BTreeMap<Txid, u32>,
+        tip_height: u32,
+    ) -> Option<AllocationStatus> {
+        self.known_allocations()
+            .iter()
+            .find(|allocation| allocation.outpoint() == outpoint)?;
+        Some(match tx_heights.get(&outpoint.txid) {
+            Some(height) if *height <= tip_height => {
+                AllocationStatus::Confirmed(*height)
+            }
+            _ => AllocationStatus::Unconfirmed,
+        })
+    }
+
+    fn select_allocations_min_inputs(
+        &self,
+        target: AtomicValue,
+    ) -> Option<Vec<(OutPoint, AtomicValue)>> {
+        if target == 0 {
+            return Some(vec![]);
+        }
+        let mut inputs = BTreeMap::<OutPoint, AtomicValue>::new();
+        for allocation in self.known_allocations() {
+            let amount = inputs.entry(*allocation.outpoint()).or_default();
+            *amount = amount.saturating_add(allocation.value());
+        }
+        let mut inputs = inputs.into_iter().collect::<Vec<_>>();
+        inputs.sort_by(|(outpoint1, amount1), (outpoint2, amount2)| {
+            amount2.cmp(amount1).then(outpoint1.cmp(outpoint2))
+        });
+
+        let mut selected = vec![];
+        let mut covered: AtomicValue = 0;
+        for (pos, (outpoint, amount)) in inputs.iter().enumerate() {
+            if covered.saturating_add(*amount) >= target {
+                // The smallest of the remaining outpoints covering the rest
+                // of the target completes the selection
+                let last = inputs[pos..]
+                    .iter()
+                    .filter(|(_, amount)| {
+                        covered.saturating_add(*amount) >= target
+                    })
+                    .min_by_key(|(outpoint, amount)| (*amount, *outpoint))
+                    .copied()
+                    .unwrap_or((*outpoint, *amount));
+                selected.push(last);
+                return Some(selected);
+            }
+            covered += amount;
+            selected.push((*outpoint, *amount));
+        }
+        None
+    }
+
+    fn dust_allocations(&self, threshold: AtomicValue) -> Vec<Allocation> {
+        self.known_allocations()
+            .iter()
+            .filter(|allocation| allocation.value() < threshold)
+            .cloned()
+            .collect()
+    }
+
+    fn ownership_fraction(&self, outpoint: &OutPoint) -> f64 {
+        let circulating = *self.supply().known_circulating();
+        if circulating == 0 {
+            return 0.0;
+        }
+        let balance = self
+            .allocations(*outpoint)
+            .iter()
+            .fold(0 as AtomicValue, |sum, allocation| {
+                sum.saturating_add(allocation.value())
+            });
+        balance as f64 / circulating as f64
+    }
+
+    fn verify_supply_consistency(
+        &self,
+        burned: AtomicValue,
+    ) -> Result<(), SupplyError> {
+        let primary =
+            self.primary_issue().ok_or(SupplyError::NoPrimaryIssue)?;
+        let secondary = self
+            .secondary_issues()
+            .fold(0u64, |sum, issue| sum.saturating_add(*issue.amount()));
+        let issued = primary.amount().saturating_add(secondary);
+        let expected = issued
+            .checked_sub(burned)
+            .ok_or(SupplyError::BurnedExceedsIssued { burned, issued })?;
+        let circulating = *self.supply().known_circulating();
+        if circulating != expected {
+            return Err(SupplyError::CirculatingMismatch {
+                circulating,
+                expected,
+            });
+        }
+        let capacity = self.total_inflation_capacity();
+        if secondary > capacity {
+            return Err(SupplyError::ExceedsInflationCapacity {
+                issued: secondary,
+                capacity,
+            });
+        }
+        Ok(())
+    }
+
+    fn verify_allocations_within_supply(&self) -> Result<(), SupplyError> {
+        let allocated = self
+            .known_allocations()
+            .iter()
+            .fold(0u64, |sum, allocation| {
+                sum.saturating_add(allocation.value())
+            });
+        let supply = max_supply(self);
+        if allocated > supply {
+            return Err(SupplyError::AllocationsExceedSupply {
+                allocated,
+                supply,
+            });
+        }
+        Ok(())
+    }
+
+    fn unreconciled_inflation(&self) -> Vec<OutPoint> {
+        self.known_inflation()
+            .keys()
+            .filter(|outpoint| {
+                !self
+                    .secondary_issues()
+                    .any(|issue| issue.origin() == &Some(**outpoint))
+            })
+            .copied()
+            .collect()
+    }
+
+    fn is_fully_reconciled(&self) -> bool {
+        *self.supply().is_issued_known() == Some(true)
+            || self.unreconciled_inflation().is_empty()
+    }
+
+    fn reconcile(&self, unspent_inflation: &BTreeSet<OutPoint>) -> Asset {
+        let is_issued_known = self
+            .unreconciled_inflation()
+            .iter()
+            .all(|outpoint| unspent_inflation.contains(outpoint));
+        let supply = Supply::with(
+            *self.supply().known_circulating(),
+            if is_issued_known { Some(true) } else { None },
+            *self.supply().issue_limit(),
+        );
+        with_supply(self, supply)
+    }
+
+    fn burn(&self, outpoints: &BTreeSet<OutPoint>) -> (Asset, AtomicValue) {
+        let mut asset = self.clone();
+        let mut burned = 0u64;
+        for allocation in self
+            .known_allocations()
+            .iter()
+            .filter(|allocation| outpoints.contains(allocation.outpoint()))
+        {
+            if asset.remove_allocation(
+                *allocation.outpoint(),
+                *allocation.node_id(),
+                *allocation.index(),
+                *allocation.revealed_amount(),
+            ) {
+                burned = burned.saturating_add(allocation.value());
+            }
+        }
+        let supply = Supply::with(
+            self.supply().known_circulating().saturating_sub(burned),
+            *self.supply().is_issued_known(),
+            *self.supply().issue_limit(),
+        );
+        (with_supply(&asset, supply), burned)
+    }
+
+    fn on_outpoint_spent(&mut self, outpoint: &OutPoint) -> Vec<Allocation> {
+        let spent = self.allocations(*outpoint);
+        for allocation in &spent {
+            self.remove_allocation(
+                *outpoint,
+                *allocation.node_id(),
+                *allocation.index(),
+                *allocation.revealed_amount(),
+            );
+        }
+
+        if self.unreconciled_inflation().contains(outpoint) {
+            let supply = Supply::with(
+                *self.supply().known_circulating(),
+                Some(false),
+                *self.supply().issue_limit(),
+            );
+            *self = with_supply(self, supply);
+        }
+        spent
+    }
+
+    fn rollback_to_height(
+        &mut self,
+        height: u32,
+        spendings: &[OutpointSpending],
+    ) -> Vec<Allocation> {
+        let mut restored = vec![];
+        let mut unreconciled = false;
+        for spending in spendings.iter().filter(|s| s.height > height) {
+            for allocation in &spending.allocations {
+                if let AllocationInsertion::Added = self.insert_allocation(
+                    *allocation.outpoint(),
+                    *allocation.node_id(),
+                    *allocation.index(),
+                    *allocation.revealed_amount(),
+                ) {
+                    restored.push(*allocation);
+                }
+            }
+            unreconciled |=
+                self.known_inflation().contains_key(&spending.outpoint);
+        }
+
+        if unreconciled {
+            let supply = Supply::with(
+                *self.supply().known_circulating(),
+                None,
+                *self.supply().issue_limit(),
+            );
+            *self = with_supply(self, supply);
+        }
+        restored
+    }
+
+    fn insert_allocation(
+        &mut self,
+        outpoint: OutPoint,
+        node_id: NodeId,
+        index: u16,
+        value: value::Revealed,
+    ) -> AllocationInsertion {
+        let new = Allocation::with(node_id, index, outpoint, value);
+        match self.known_allocations().iter().find(|allocation| {
+            *allocation.node_id() == node_id && *allocation.index() == index
+        }) {
+            Some(known) if *known == new => AllocationInsertion::Known,
+            Some(known) => AllocationInsertion::Conflict(*known),
+            None => {
+                self.add_allocation(outpoint, node_id, index, value);
+                AllocationInsertion::Added
+            }
+        }
+    }
+
+    fn add_allocations<I>(&mut self, allocations: I) -> usize
+    where
+        I: IntoIterator<Item = (OutPoint, NodeId, u16, value::Revealed)>,
+    {
+        allocations
+            .into_iter()
+            .filter(|(outpoint, node_id, index, value)| {
+                self.insert_allocation(*outpoint, *node_id, *index, *value)
+                    == AllocationInsertion::Added
+            })
+            .count()
+    }
+
+    fn issued_allocations(&self) -> Vec<&Allocation> {
+        self.known_allocations()
+            .iter()
+            .filter(|allocation| is_issued_allocation(self, allocation))
+            .collect()
+    }
+
+    fn received_allocations(&self) -> Vec<&Allocation> {
+        self.known_allocations()
+            .iter()
+            .filter(|allocation| !is_issued_allocation(self, allocation))
+            .collect()
+    }
+
+    fn allocations_by_node(&self, node_id: NodeId) -> Vec<&Allocation> {
+        self.known_allocations()
+            .iter()
+            .filter(|allocation| *allocation.node_id() == node_id)
+            .collect()
+    }
+
+    fn detect_allocation_conflicts(&self) -> Vec<(Allocation, Allocation)> {
+        let allocations = self.known_allocations();
+        let mut conflicts = vec![];
+        for (pos, allocation) in allocations.iter().enumerate() {
+            for other in &allocations[pos + 1..] {
+                if other.node_id() == allocation.node_id()
+                    && other.index() == allocation.index()
+                    && other != allocation
+                {
+                    conflicts.push((*allocation, *other));
+                }
+            }
+        }
+        conflicts
+    }
+
+    fn semantic_eq(&self, other: &Asset) -> bool {
+        self.id() == other.id()
+            && self.ticker() == other.ticker()
+            && self.name() == other.name()
+            && self.supply() == other.supply()
+            && self.chain() == other.chain()
+            && self.decimal_precision() == other.decimal_precision()
+            && self.diff(other).is_empty()
+    }
+
+    fn diff(&self, other: &Asset) -> AssetDiff {
+        fn missing<T: Copy + PartialEq>(from: &[T], items: &[T]) -> Vec<T> {
+            items
+                .iter()
+                .filter(|item| !from.contains(item))
+                .copied()
+                .collect()
+        }
+
+        AssetDiff {
+            added_allocations: missing(
+                self.known_allocations(),
+                other.known_allocations(),
+            ),
+            removed_allocations: missing(
+                other.known_allocations(),
+                self.known_allocations(),
+            ),
+            added_issues: missing(self.known_issues(), other.known_issues()),
+            removed_issues: missing(other.known_issues(), self.known_issues()),
+        }
+    }
+
+    fn history(&self) -> Vec<AssetEvent> {
+        let mut events = vec![];
+        let mut issued = 0u64;
+        if let Some(issue) = self.primary_issue() {
+            issued = *issue.amount();
+            events.push(AssetEvent::Genesis {
+                node_id: *issue.id(),
+                timestamp: Some(*self.date()),
+                amount: *issue.amount(),
+            });
+        }
+        for issue in self.secondary_issues() {
+            issued = issued.saturating_add(*issue.amount());
+            events.push(AssetEvent::Issue {
+                node_id: *issue.id(),
+                timestamp: None,
+                amount: *issue.amount(),
+            });
+        }
+
+        let mut received = self
+            .known_allocations()
+            .iter()
+            .filter(|allocation| {
+                self.issue_by_id(*allocation.node_id()).is_none()
+            })
+            .map(|allocation| {
+                (
+                    *allocation.node_id(),
+                    *allocation.outpoint(),
+                    allocation.value(),
+                )
+            })
+            .collect::<Vec<_>>();
+        received.sort();
+        let mut inflow = issued;
+        for (node_id, outpoint, amount) in received {
+            inflow = inflow.saturating_add(amount);
+            events.push(AssetEvent::Received {
+                node_id,
+                outpoint,
+                timestamp: None,
+                amount,
+            });
+        }
+
+        let burned = issued.saturating_sub(*self.supply().known_circulating());
+        let sent = inflow
+            .saturating_sub(burned)
+            .saturating_sub(self.known_atomic_value());
+        if sent > 0 {
+            events.push(AssetEvent::Sent {
+                timestamp: None,
+                amount: sent,
+            });
+        }
+        if burned > 0 {
+            events.push(AssetEvent::Burned {
+                timestamp: None,
+                amount: burned,
+            });
+        }
+        events
+    }
+
+    #[inline]
+    fn fingerprint(&self) -> String {
+        contract_fingerprint(*self.id())
+    }
+
+    #[inline]
+    fn cache_filename(&self) -> String {
+        self.id().as_inner().to_hex()
+    }
+
+    fn summary(&self) -> String {
+        let precision = *self.decimal_precision();
+        let balance = self.amount(self.known_atomic_value());
+        let capacity = self.total_inflation_capacity();
+        let cap = if capacity == AtomicValue::MAX {
+            s!("unlimited")
+        } else {
+            let primary = self.primary_issue().map(|issue| *issue.amount());
+            format_accounting_value(
+                primary.unwrap_or(0).saturating_add(capacity),
+                precision,
+            )
+        };
+        format!(
+            "{} ({}): {} known, {}/{} minted",
+            self.ticker(),
+            self.name(),
+            balance.format_grouped(self.ticker()),
+            format_accounting_value(
+                *self.supply().known_circulating(),
+                precision
+            ),
+            cap
+        )
+    }
+
+    #[inline]
+    fn issued_at(&self) -> DateTime<Utc> {
+        DateTime::from_utc(*self.date(), Utc)
+    }
+
+    #[inline]
+    fn smallest_unit(&self) -> String {
+        format_accounting_value(1, *self.decimal_precision())
+    }
+
+    #[inline]
+    fn is_divisible(&self) -> bool {
+        *self.decimal_precision() > 0
+    }
+
+    #[inline]
+    fn amount(&self, atomic: AtomicValue) -> AccountingAmount {
+        AccountingAmount::from_fractioned_atomic_value(
+            *self.decimal_precision(),
+            atomic,
+        )
+    }
+
+    fn allocation_precisions(&self) -> BTreeSet<u8> {
+        let precision = *self.decimal_precision();
+        let supply = max_supply(self);
+        self.known_allocations()
+            .iter()
+            .map(|allocation| {
+                let mut value = allocation.value();
+                let mut implied = precision;
+                while value > supply {
+                    value /= 10;
+                    implied = implied.saturating_add(1);
+                }
+                implied
+            })
+            .collect()
+    }
+
+    fn allocations_to_csv(&self) -> String {
+        let precision = *self.decimal_precision();
+        let mut csv =
+            s!("outpoint,node_id,index,atomic_value,accounting_value\n");
+        let mut allocations = self.known_allocations().clone();
+        allocations.sort_by_key(|allocation| {
+            (
+                *allocation.outpoint(),
+                *allocation.node_id(),
+                *allocation.index(),
+            )
+        });
+        for allocation in allocations {
+            csv += &format!(
+                "{},{},{},{},{}\n",
+                allocation.outpoint(),
+                allocation.node_id(),
+                allocation.index(),
+                allocation.value(),
+                format_accounting_value(allocation.value(), precision)
+            );
+        }
+        csv
+    }
+
+    #[cfg(feature = "serde")]
+    fn to_json(&self) -> Result<String, AssetJsonError> {
+        self.to_json_labeled(None)
+    }
+
+    #[cfg(feature = "serde")]
+    fn from_json(json: &str) -> Result<Asset, AssetJsonError> {
+        Asset::migrate(serde_json::from_str(json)?)
+    }
+
+    #[cfg(feature = "serde")]
+    fn to_rgb20_json(&self) -> Result<String, AssetJsonError> {
+        let mut allocations = self.known_allocations().clone();
+        allocations.sort_by_key(|allocation| {
+            (
+                *allocation.outpoint(),
+                *allocation.node_id(),
+                *allocation.index(),
+            )
+        });
+        let allocations = allocations
+            .iter()
+            .map(|allocation| {
+                serde_json::json!({
+                    "outpoint": allocation.outpoint().to_string(),
+                    "amount": allocation.value(),
+                })
+            })
+            .collect::<Vec<_>>();
+        Ok(serde_json::to_string(&serde_json::json!({
+            "schema": "RGB20",
+            "contractId": self.id().to_string(),
+            "ticker": self.ticker(),
+            "name": self.name(),
+            "description": self.description(),
+            "precision": self.decimal_precision(),
+            "issuedSupply": self.supply().known_circulating(),
+            "allocations": allocations,
+        }))?)
+    }
+
+    #[cfg(feature = "serde")]
+    fn to_json_labeled(
+        &self,
+        label: Option<&str>,
+    ) -> Result<String, AssetJsonError> {
+        Ok(serde_json::to_string(&AssetEnvelope {
+            version: ASSET_JSON_VERSION,
+            asset: self,
+            label: label.map(str::to_owned),
+        })?)
+    }
+
+    #[cfg(feature = "serde")]
+    fn from_json_labeled(
+        json: &str,
+    ) -> Result<(Asset, Option<String>), AssetJsonError> {
+        let raw_value: serde_json::Value = serde_json::from_str(json)?;
+        let label = match raw_value.get("label") {
+            Some(label) => serde_json::from_value(label.clone())?,
+            None => None,
+        };
+        Ok((Asset::migrate(raw_value)?, label))
+    }
+
+    #[cfg(feature = "serde")]
+    fn migrate(raw_value: serde_json::Value) -> Result<Asset, AssetJsonError> {
+        let mut envelope: AssetEnvelope<serde_json::Value> =
+            serde_json::from_value(raw_value)?;
+        match envelope.version {
+            1 => {
+                if let Some(asset) = envelope.asset.as_object_mut() {
+                    for (field, default) in ASSET_JSON_V1_DEFAULTS {
+                        asset.entry(*field).or_insert_with(|| {
+                            serde_json::from_str(default)
+                                .expect("hardcoded JSON value")
+                        });
+                    }
+                }
+                Ok(serde_json::from_value(envelope.asset)?)
+            }
+            ASSET_JSON_VERSION => Ok(serde_json::from_value(envelope.asset)?),
+            version => Err(AssetJsonError::UnsupportedVersion(version)),
+        }
+    }
+}
+
+/// Helper methods for RGB20 [`Allocation`] which are not provided by the RGB20
+/// library itself
+pub trait AllocationExt {
+    /// Splits the allocation for a partial spend into the spent part with the
+    /// given amount and the change part with the remaining value; the change
+    /// value is zero if the whole allocation is spent. Both parts keep the
+    /// `node_id`, `index` and outpoint of the source allocation and its
+    /// blinding factor: the blinding factors of the new assignments are
+    /// generated once the state transition is constructed.
+    fn split(
+        &self,
+        amount: AtomicValue,
+    ) -> Result<(Allocation, Allocation), AllocationError>;
+}
+
+impl AllocationExt for Allocation {
+    fn split(
+        &self,
+        amount: AtomicValue,
+    ) -> Result<(Allocation, Allocation), AllocationError> {
+        let available = self.value();
+        let change = available.checked_sub(amount).ok_or(
+            AllocationError::InsufficientValue {
+                available,
+                requested: amount,
+            },
+        )?;
+        let part = |value| {
+            Allocation::with(
+                *self.node_id(),
+                *self.index(),
+                *self.outpoint(),
+                value::Revealed {
+                    value,
+                    ..*self.revealed_amount()
+                },
+            )
+        };
+        Ok((part(amount), part(change)))
+    }
+}
+
+/// Rounding of the exact decimal accounting value to the floating point
+/// [`AccountingValue`] by [`AccountingAmountExt::accounting_value_with`].
+///
+/// Most decimal fractions (like `0.1`) are not representable in floating
+/// point, and integer values above 2^53 are not representable either, so the
+/// mode selects which of the two closest representable values is used. Since
+/// amounts are never negative, truncation and flooring are the same.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Display)]
+pub enum RoundingMode {
+    /// Representable value closest to zero
+    #[display("truncate")]
+    Truncate,
+
+    /// Nearest representable value; ties are rounded to the value with even
+    /// mantissa
+    #[display("round")]
+    Round,
+
+    /// Smallest representable value not less than the exact one
+    #[display("ceil")]
+    Ceil,
+
+    /// Largest representable value not greater than the exact one
+    #[display("floor")]
+    Floor,
+}
+
+/// Helper methods for RGB20 [`AccountingAmount`] which are not provided by
+/// the RGB20 library itself
+pub trait AccountingAmountExt {
+    /// Constructs zero amount with the given decimal precision, suitable for
+    /// seeding balance accumulators (unlike `Default`, which always has zero
+    /// precision)
+    fn zero(decimal_precision: u8) -> Self;
+
+    /// Detects whether the amount has zero value
+    fn is_zero(&self) -> bool;
+
+    /// Returns the exact number of whole units for the amount with zero
+    /// decimal precision, or `None` for divisible amounts. Unlike the
+    /// floating point accounting value, the result does not lose precision
+    /// for large amounts.
+    fn indivisible_value(&self) -> Option<AtomicValue>;
+
+    /// Converts the amount into floating point accounting value, rounding it
+    /// explicitly with the given mode. The conversion provided by the rgb20
+    /// library, [`AccountingAmount::accounting_value`], rounds implicitly
+    /// (and for values above 2^53 twice), so the direction of its error is
+    /// unknown; use [`RoundingMode::Truncate`] to never overstate the amount.
+    ///
+    /// Exact rounding is supported for decimal precisions up to 22; amounts
+    /// with larger precision are converted implicitly, ignoring `mode`.
+    fn accounting_value_with(&self, mode: RoundingMode) -> AccountingValue;
+
+    /// Compares amounts by their accounting value, so amounts with different
+    /// decimal precision are ordered meaningfully. The comparison is exact
+    /// (not going through floating point); amounts with the same accounting
+    /// value but different precision (like `1.0` and `1.00`) are ordered by
+    /// the precision, which keeps the ordering total and consistent with
+    /// equality.
+    ///
+    /// Since `AccountingAmount` is defined by the rgb20 library it can't
+    /// implement `Ord` here; use this method with `sort_by` and similar APIs.
+    fn cmp_value(&self, other: &Self) -> Ordering;
+
+    /// Formats the amount for display to the user, grouping the digits of
+    /// the integer part into thousands with commas and appending the ticker,
+    /// e.g. `1,234,567.00 TICKER`
+    fn format_grouped(&self, ticker: &str) -> String {
+        self.format_grouped_with(ticker, ',')
+    }
+
+    /// Formats the amount in the same way as
+    /// [`AccountingAmountExt::format_grouped`], using the provided thousands
+    /// separator
+    fn format_grouped_with(&self, ticker: &str, separator: char) -> String;
+}
+
+impl AccountingAmountExt for AccountingAmount {
+    #[inline]
+    fn zero(decimal_precision: u8) -> Self {
+        AccountingAmount::from_fractioned_atomic_value(decimal_precision, 0)
+    }
+
+    #[inline]
+    fn is_zero(&self) -> bool {
+        self.atomic_value() == 0
+    }
+
+    #[inline]
+    fn indivisible_value(&self) -> Option<AtomicValue> {
+        if self.decimal_precision() == 0 {
+            Some(self.atomic_value())
+        } else {
+            None
+        }
+    }
+
+    fn accounting_value_with(&self, mode: RoundingMode) -> AccountingValue {
+        let atomic = self.atomic_value();
+        if atomic == 0 {
+            return 0.0;
+        }
+        let precision = self.decimal_precision();
+        // Same as `AccountingAmount::accounting_value`, which does not support
+        // precisions above 19
+        let implicit = atomic as f64 / 10f64.powi(precision as i32);
+        if precision > MAX_EXACT_PRECISION {
+            return implicit;
+        }
+        let divider = 10u128.pow(precision as u32);
+        let cmp = |value: AccountingValue| {
+            let (mantissa, exp) = float_parts(value);
+            cmp_float_exact(mantissa, exp, atomic, divider)
+        };
+
+        // Implicit conversion is off by at most a couple of ulps, so the
+        // search for the closest values takes just a few steps
+        let mut floor = implicit;
+        while cmp(floor) == Ordering::Greater {
+            floor = f64::from_bits(floor.to_bits() - 1);
+        }
+        while cmp(f64::from_bits(floor.to_bits() + 1)) != Ordering::Greater {
+            floor = f64::from_bits(floor.to_bits() + 1);
+        }
+        if cmp(floor) == Ordering::Equal {
+            return floor;
+        }
+        let ceil = f64::from_bits(floor.to_bits() + 1);
+
+        match mode {
+            RoundingMode::Truncate | RoundingMode::Floor => floor,
+            RoundingMode::Ceil => ceil,
+            RoundingMode::Round => {
+                // Comparing the exact value with the midpoint of the closest
+                // values, which is not representable in floating point
+                let (floor_mantissa, floor_exp) = float_parts(floor);
+                let (ceil_mantissa, ceil_exp) = float_parts(ceil);
+                let exp = floor_exp.min(ceil_exp);
+                let sum = (floor_mantissa << (floor_exp - exp))
+                    + (ceil_mantissa << (ceil_exp - exp));
+                match cmp_float_exact(sum, exp - 1, atomic, divider) {
+                    Ordering::Less => ceil,
+                    Ordering::Greater => floor,
+                    Ordering::Equal if floor.to_bits() % 2 == 0 => floor,
+                    Ordering::Equal => ceil,
+                }
+            }
+        }
+    }
+
+    fn cmp_value(&self, other: &Self) -> Ordering {
+        // Bringing the value with lower precision to the precision of the
+        // other one; if the scaled value overflows u128 it exceeds any atomic
+        // value
+        let cmp_scaled = |value: AtomicValue,
+                          scaled: AtomicValue,
+                          scale: u8| {
+            if scaled == 0 {
+                return value.cmp(&0);
+            }
+            10u128
+                .checked_pow(scale as u32)
+                .and_then(|multiplier| (scaled as u128).checked_mul(multiplier))
+                .map(|scaled| (value as u128).cmp(&scaled))
+                .unwrap_or(Ordering::Less)
+        };
+        let (precision, other_precision) =
+            (self.decimal_precision(), other.decimal_precision());
+        if precision >= other_precision {
+            cmp_scaled(
+                self.atomic_value(),
+                other.atomic_value(),
+                precision - other_precision,
+            )
+        } else {
+            cmp_scaled(
+                other.atomic_value(),
+                self.atomic_value(),
+                other_precision - precision,
+            )
+            .reverse()
+        }
+        .then(precision.cmp(&other_precision))
+    }
+
+    fn format_grouped_with(&self, ticker: &str, separator: char) -> String {
+        let value = format_accounting_value(
+            self.atomic_value(),
+            self.decimal_precision(),
+        );
+        let (integer, fraction) = match value.find('.') {
+            Some(pos) => value.split_at(pos),
+            None => (value.as_str(), ""),
+        };
+        let mut grouped =
+            String::with_capacity(value.len() + integer.len() / 3);
+        for (pos, digit) in integer.chars().enumerate() {
+            if pos > 0 && (integer.len() - pos) % 3 == 0 {
+                grouped.push(separator);
+            }
+            grouped.push(digit);
+        }
+        format!("{}{} {}", grouped, fraction, ticker)
+    }
+}
+
+/// Largest decimal precision for which
+/// [`AccountingAmountExt::accounting_value_with`] rounds exactly: the power of
+/// ten is exactly representable in `f64`, and its product with the mantissa
+/// of any `f64` value fits `u128`
+const MAX_EXACT_PRECISION: u8 = 22;
+
+/// Number of contract id bytes used in the fingerprint
+const FINGERPRINT_LEN: usize = 8;
+
+/// Computes short fingerprint of the contract id for use in asset listings:
+/// hex encoding of the first 8 bytes of the id. The fingerprint is
+/// deterministic, but unlike the full contract id it is not guaranteed to be
+/// unique, so it must not be used to identify the asset outside of the local
+/// user interface.
+pub fn contract_fingerprint(contract_id: ContractId) -> String {
+    contract_id.as_inner()[..FINGERPRINT_LEN].to_hex()
+}
+
+/// Aggregates known circulating supply of the assets by their tickers.
+///
+/// Tickers are not unique: different contracts may use the same ticker, and
+/// their supplies are summed together, so the aggregation is suitable for
+/// statistics only and must not be used to tell the assets apart. Supplies
+/// of the assets with the same ticker but different decimal precision are
+/// brought to the largest of the precisions; sums saturate at `u64::MAX`
+/// atomic units.
+pub fn supply_by_ticker(
+    assets: &[Asset],
+) -> BTreeMap<String, AccountingAmount> {
+    let mut precisions = BTreeMap::<&str, u8>::new();
+    for asset in assets {
+        let precision = precisions.entry(asset.ticker()).or_insert(0);
+        *precision = (*precision).max(*asset.decimal_precision());
+    }
+    let mut supply = BTreeMap::<&str, AtomicValue>::new();
+    for asset in assets {
+        let scale =
+            precisions[asset.ticker().as_str()] - *asset.decimal_precision();
+        let value = 10u64
+            .checked_pow(scale as u32)
+            .and_then(|multiplier| {
+                asset.supply().known_circulating().checked_mul(multiplier)
+            })
+            .unwrap_or(AtomicValue::MAX);
+        let sum = supply.entry(asset.ticker()).or_insert(0);
+        *sum = sum.saturating_add(value);
+    }
+    supply
+        .into_iter()
+        .map(|(ticker, value)| {
+            (
+                ticker.to_owned(),
+                AccountingAmount::from_fractioned_atomic_value(
+                    precisions[ticker],
+                    value,
+                ),
+            )
+        })
+        .collect()
+}
+
+/// Renders atomic value as a decimal number with the given number of digits
+/// after the decimal point. Unlike conversion through floating point
+/// `AccountingValue` does not lose precision for large values.
+fn format_accounting_value(atomic_value: AtomicValue, precision: u8) -> String {
+    if precision == 0 {
+        return atomic_value.to_string();
+    }
+    // Padding the digits with zeros so that at least one digit is left for
+    // the integer part; no arithmetic is needed, so any precision fits
+    let digits =
+        format!("{:0width$}", atomic_value, width = precision as usize + 1);
+    let (integer, fraction) =
+        digits.split_at(digits.len() - precision as usize);
+    format!("{}.{}", integer, fraction)
+}
+
+/// Splits positive finite floating point value into its mantissa and binary
+/// exponent, such that `value == mantissa * 2^exp`
+fn float_parts(value: f64) -> (u128, i32) {
+    let bits = value.to_bits();
+    let exp = ((bits >> 52) & 0x7FF) as i32;
+    let fraction = (bits & ((1 << 52) - 1)) as u128;
+    if exp == 0 {
+        (fraction, -1074)
+    } else {
+        (fraction | 1 << 52, exp - 1075)
+    }
+}
+
+/// Compares `mantissa * 2^exp` with `atomic / divider` exactly
+fn cmp_float_exact(
+    mantissa: u128,
+    exp: i32,
+    atomic: AtomicValue,
+    divider: u128,
+) -> Ordering {
+    let lhs = mantissa * divider;
+    let rhs = atomic as u128;
+    if lhs == 0 || rhs == 0 {
+        return lhs.cmp(&rhs);
+    }
+    // Shifts overflowing u128 mean that the shifted side is the greater one
+    if exp >= 0 {
+        if exp as u32 >= lhs.leading_zeros() {
+            return Ordering::Greater;
+        }
+        (lhs << exp).cmp(&rhs)
+    } else {
+        let shift = exp.unsigned_abs();
+        if shift >= rhs.leading_zeros() {
+            return Ordering::Less;
+        }
+        lhs.cmp(&(rhs << shift))
+    }
+}
+
+/// Detects whether the allocation was created by the genesis or by one of the
+/// known issues of the asset
+fn is_issued_allocation(asset: &Asset, allocation: &Allocation) -> bool {
+    let node_id = *allocation.node_id();
+    node_id == NodeId::from_inner(asset.id().into_inner())
+        || asset.issue_by_id(node_id).is_some()
+}
+
+/// Adds issues to the known issues, keeping a single entry per issue id. Of
+/// the two entries with the same id the one with known origin is kept.
+fn merge_issues(known_issues: &mut Vec<Issue>, issues: &[Issue]) {
+    for issue in issues {
+        match known_issues
+            .iter_mut()
+            .find(|known| known.id() == issue.id())
+        {
+            Some(known) => {
+                if known.origin().is_none() && issue.origin().is_some() {
+                    *known = *issue;
+                }
+            }
+            None => known_issues.push(*issue),
+        }
+    }
+}
+
+/// Maximum possible asset supply: the primary issue amount together with the
+/// capacity of all known inflation rights
+fn max_supply(asset: &Asset) -> AtomicValue {
+    asset
+        .primary_issue()
+        .map(|issue| *issue.amount())
+        .unwrap_or_default()
+        .saturating_add(asset.total_inflation_capacity())
+}
+
+/// Re-creates asset data replacing its supply information
+fn with_supply(asset: &Asset, supply: Supply) -> Asset {
+    Asset::with(
+        asset.genesis().clone(),
+        *asset.id(),
+        asset.ticker().clone(),
+        asset.name().clone(),
+        asset.description().clone(),
+        supply,
+        asset.chain().clone(),
+        *asset.decimal_precision(),
+        *asset.date(),
+        asset.known_issues().clone(),
+        asset.known_inflation().clone(),
+        asset.known_allocations().clone(),
+    )
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+    use bitcoin::hashes::hex::FromHex;
+    use bitcoin::{OutPoint, Txid};
+    use commit_verify::CommitVerify;
+    use lnpbp::chain::Chain;
+    use rgb::data::Revealed;
+    use rgb::{secp256k1zkp, ConcealSeals, OwnedState};
+    use std::collections::BTreeMap;
+    use strict_encoding::{strict_deserialize, strict_serialize};
+
+    pub(crate) fn outpoint(vout: u32) -> OutPoint {
+        OutPoint::new(
+            Txid::from_hex(
+                "fc63f797af718cc5a11988f69507701d5fe84e58cdd900e1b02856c0ea5a058a",
+            )
+            .unwrap(),
+            vout,
+        )
+    }
+
+    pub(crate) fn issue(
+        inflation: BTreeMap<OutPoint, AtomicValue>,
+    ) -> (Asset, Genesis) {
+        rgb20::issue(
+            Chain::Testnet3,
+            s!("TST"),
+            s!("Test asset"),
+            None,
+            2,
+            vec![(outpoint(0), 1000), (outpoint(1), 500)],
+            inflation,
+            None,
+            None,
+        )
+        .unwrap()
+    }
+
+    fn allocation_seal(genesis: &Genesis, index: usize) -> OutpointHash {
+        genesis
+            .owned_rights_by_type(*OwnedRightsType::Assets)
+            .unwrap()
+            .to_discrete_state()[index]
+            .seal_definition_confidential()
+    }
+
+    pub(crate) fn asset() -> Asset {
+        issue(bmap! { outpoint(2) => 10000 }).0
+    }
+
+    fn revealed(value: AtomicValue) -> value::Revealed {
+        value::Revealed {
+            value,
+            blinding: secp256k1zkp::key::ONE_KEY.into(),
+        }
+    }
+
+    /// Builder re-creating the asset data with some of its fields replaced
+    struct Rebuild<'asset> {
+        asset: &'asset Asset,
+        supply: Supply,
+        decimal_precision: u8,
+        known_issues: Vec<Issue>,
+        known_allocations: Vec<Allocation>,
+    }
+
+    fn rebuild(asset: &Asset) -> Rebuild {
+        Rebuild {
+            asset,
+            supply: *asset.supply(),
+            decimal_precision: *asset.decimal_precision(),
+            known_issues: asset.known_issues().clone(),
+            known_allocations: asset.known_allocations().clone(),
+        }
+    }
+
+    impl Rebuild<'_> {
+        fn supply(mut self, supply: Supply) -> Self {
+            self.supply = supply;
+            self
+        }
+
+        fn decimal_precision(mut self, decimal_precision: u8) -> Self {
+            self.decimal_precision = decimal_precision;
+            self
+        }
+
+        fn issue(mut self, issue: Issue) -> Self {
+            self.known_issues.push(issue);
+            self
+        }
+
+        fn allocations(mut self, allocations: Vec<Allocation>) -> Self {
+            self.known_allocations = allocations;
+            self
+        }
+
+        fn build(self) -> Asset {
+            let asset = self.asset;
+            Asset::with(
+                asset.genesis().clone(),
+                *asset.id(),
+                asset.ticker().clone(),
+                asset.name().clone(),
+                asset.description().clone(),
+                self.supply,
+                asset.chain().clone(),
+                self.decimal_precision,
+                *asset.date(),
+                self.known_issues,
+                asset.known_inflation().clone(),
+                self.known_allocations,
+            )
+        }
+    }
+
+    #[test]
+    fn test_issue_lookup() {
+        let asset = asset();
+        let primary = *asset.primary_issue().unwrap();
+        assert_eq!(*primary.amount(), 1500);
+        assert_eq!(asset.secondary_issues().count(), 0);
+
+        let secondary_id = NodeId::commit(b"secondary issue");
+        let secondary = Issue::with(secondary_id, 300, Some(outpoint(2)));
+        let asset = rebuild(&asset).issue(secondary).build();
+
+        assert_eq!(asset.primary_issue(), Some(&primary));
+        assert_eq!(
+            asset.secondary_issues().collect::<Vec<_>>(),
+            vec![&secondary]
+        );
+        assert_eq!(asset.issue_by_id(*primary.id()), Some(&primary));
+        assert_eq!(asset.issue_by_id(secondary_id), Some(&secondary));
+        assert_eq!(asset.issue_by_id(NodeId::default()), None);
+    }
+
+    #[test]
+    fn test_fixed_supply_from_genesis() {
+        let (_, genesis) = issue(bmap! {});
+        let asset = Asset::from_genesis(&genesis).unwrap();
+        assert_eq!(asset.supply().total_circulating(), Some(1500));
+
+        // Secondary issues of inflatable assets may be unknown
+        let (_, genesis) = issue(bmap! { outpoint(2) => 10000 });
+        let asset = Asset::from_genesis(&genesis).unwrap();
+        assert_eq!(asset.supply().total_circulating(), None);
+    }
+
+    fn with_metadata(
+        genesis: &Genesis,
+        f: impl FnOnce(&mut BTreeMap<rgb::schema::FieldType, BTreeSet<Revealed>>),
+    ) -> Genesis {
+        let mut metadata = genesis.metadata().clone().into_inner();
+        f(&mut metadata);
+        Genesis::with(
+            genesis.schema_id(),
+            genesis.chain().clone(),
+            metadata.into(),
+            genesis.owned_rights().clone().into_inner(),
+            genesis.public_rights().clone().into_inner(),
+        )
+    }
+
+    #[test]
+    fn test_schema_id_mismatch() {
+        let (_, genesis) = issue(bmap! {});
+        let expected = genesis.schema_id();
+        let found = SchemaId::default();
+        let foreign = Genesis::with(
+            found,
+            genesis.chain().clone(),
+            genesis.metadata().clone(),
+            genesis.owned_rights().clone().into_inner(),
+            genesis.public_rights().clone().into_inner(),
+        );
+        let err = Asset::from_genesis(&foreign).unwrap_err();
+        assert_eq!(err, GenesisError::SchemaIdMismatch { expected, found });
+        let message = err.to_string();
+        assert!(message.contains(&expected.to_string()));
+        assert!(message.contains(&found.to_string()));
+    }
+
+    #[test]
+    fn test_missing_genesis_fields() {
+        let (_, genesis) = issue(bmap! {});
+        for field in &[
+            FieldType::Precision,
+            FieldType::IssuedSupply,
+            FieldType::Ticker,
+            FieldType::Name,
+            FieldType::Timestamp,
+        ] {
+            assert_eq!(
+                Asset::from_genesis(&with_metadata(&genesis, |metadata| {
+                    metadata.remove(&**field);
+                })),
+                Err(GenesisError::MissingField(*field))
+            );
+        }
+        assert!(Asset::from_genesis(&genesis).is_ok());
+    }
+
+    #[test]
+    fn test_invalid_genesis_timestamp() {
+        let (_, genesis) = issue(bmap! {});
+        for timestamp in &[i64::MAX, i64::MIN, 0, BITCOIN_GENESIS_TIMESTAMP - 1]
+        {
+            let genesis = with_metadata(&genesis, |metadata| {
+                metadata.insert(
+                    *FieldType::Timestamp,
+                    bset![Revealed::I64(*timestamp)],
+                );
+            });
+            assert_eq!(
+                Asset::from_genesis(&genesis),
+                Err(GenesisError::InvalidTimestamp(*timestamp))
+            );
+        }
+    }
+
+    #[test]
+    fn test_saturated_issue_limit() {
+        let amount = AtomicValue::MAX / 2 + 1;
+        let (_, mut genesis) = issue(bmap! {
+            outpoint(2) => amount,
+            outpoint(3) => amount,
+            outpoint(4) => amount,
+            outpoint(5) => 10
+        });
+        let assignments = genesis
+            .owned_rights_mut()
+            .get_mut(&*OwnedRightsType::Inflation)
+            .unwrap();
+        let seals = assignments
+            .to_custom_state()
+            .iter()
+            .filter(|state| {
+                state.assigned_state().map(|data| data.u64()) != Some(Some(10))
+            })
+            .map(|state| state.seal_definition_confidential())
+            .collect::<Vec<_>>();
+        assert_eq!(seals.len(), 3);
+        assignments.conceal_seals(&seals);
+        assert_eq!(concealed_inflation(&genesis), (AtomicValue::MAX, true));
+
+        let asset = Asset::from_genesis(&genesis).unwrap();
+        assert_eq!(*asset.supply().issue_limit(), AtomicValue::MAX);
+        assert_eq!(asset.known_inflation(), &bmap! { outpoint(5) => 10 });
+        assert_eq!(asset.id(), &genesis.contract_id());
+        assert_eq!(*asset.primary_issue().unwrap().id(), genesis.node_id());
+        assert!(asset.known_allocations().iter().all(|allocation| {
+            *allocation.node_id()
+                == NodeId::from_inner(genesis.contract_id().into_inner())
+        }));
+        assert_eq!(asset.known_allocations().len(), 2);
+
+        // Single concealed amount does not overflow
+        let (_, mut genesis) = issue(bmap! { outpoint(2) => amount });
+        let assignments = genesis
+            .owned_rights_mut()
+            .get_mut(&*OwnedRightsType::Inflation)
+            .unwrap();
+        let seals = vec![
+            assignments.to_custom_state()[0].seal_definition_confidential()
+        ];
+        assignments.conceal_seals(&seals);
+        assert_eq!(concealed_inflation(&genesis), (amount, false));
+        let asset = Asset::from_genesis(&genesis).unwrap();
+        assert_eq!(*asset.supply().issue_limit(), amount);
+    }
+
+    #[test]
+    fn test_renomination_right() {
+        let issue = |renomination| {
+            rgb20::issue(
+                Chain::Testnet3,
+                s!("TST"),
+                s!("Test asset"),
+                None,
+                2,
+                vec![(outpoint(0), 1000)],
+                bmap! {},
+                renomination,
+                None,
+            )
+            .unwrap()
+            .1
+        };
+        assert_eq!(
+            renomination_right(&issue(Some(outpoint(3)))),
+            Some(outpoint(3))
+        );
+        assert_eq!(renomination_right(&issue(None)), None);
+    }
+
+    #[test]
+    fn test_pending_allocations() {
+        let (_, mut genesis) = issue(bmap! {});
+        assert!(pending_allocations(&genesis).is_empty());
+
+        let seal = allocation_seal(&genesis, 0);
+        genesis
+            .owned_rights_mut()
+            .get_mut(&*OwnedRightsType::Assets)
+            .unwrap()
+            .conceal_seals(&vec![seal]);
+
+        let asset = Asset::from_genesis(&genesis).unwrap();
+        let pending = pending_allocations(&genesis);
+        assert_eq!(asset.known_allocations().len(), 1);
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].node_id, genesis.node_id());
+        assert_eq!(pending[0].index, 0);
+        assert_eq!(pending[0].seal_confidential, seal);
+        assert_eq!(pending[0].outpoint, None);
+        assert_eq!(
+            pending[0].amount.unwrap() + asset.known_atomic_value(),
+            1500
+        );
+    }
+
+    #[test]
+    fn test_skipped_allocations() {
+        let (_, mut genesis) = issue(bmap! {});
+        let (_, skipped) = Asset::parse_genesis(&genesis).unwrap();
+        assert!(skipped.is_empty());
+
+        let mut assignments = genesis
+            .owned_rights_by_type(*OwnedRightsType::Assets)
+            .unwrap()
+            .to_discrete_state();
+        let assigned_state = *assignments[0].assigned_state().unwrap();
+        assignments.push(OwnedState::Revealed {
+            seal_definition: SealDefinition::WitnessVout {
+                vout: 1,
+                blinding: 0,
+            },
+            assigned_state,
+        });
+        genesis.owned_rights_mut().insert(
+            *OwnedRightsType::Assets,
+            Assignments::DiscreteFiniteField(assignments),
+        );
+
+        let (asset, skipped) = Asset::parse_genesis(&genesis).unwrap();
+        assert_eq!(asset.known_allocations().len(), 2);
+        assert_eq!(
+            skipped,
+            vec![SkippedAllocation {
+                node_id: genesis.node_id(),
+                index: 2,
+                vout: 1,
+                amount: Some(assigned_state.value),
+            }]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_json_roundtrip() {
+        let asset = asset();
+        let json = asset.to_json().unwrap();
+        assert!(json.starts_with("{\"version\":2,\"asset\":{"));
+        let parsed = Asset::from_json(&json).unwrap();
+        assert_eq!(parsed, asset);
+        assert_eq!(parsed.chain(), &Chain::Testnet3);
+
+        let json = json.replacen("\"version\":2", "\"version\":3", 1);
+        assert!(matches!(
+            Asset::from_json(&json),
+            Err(AssetJsonError::UnsupportedVersion(3))
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_rgb20_json() {
+        let asset = asset();
+        let json: serde_json::Value =
+            serde_json::from_str(&asset.to_rgb20_json().unwrap()).unwrap();
+        let fields = json
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect::<BTreeSet<_>>();
+        assert_eq!(
+            fields,
+            bset! {
+                "schema", "contractId", "ticker", "name", "description",
+                "precision", "issuedSupply", "allocations"
+            }
+        );
+        assert_eq!(json["schema"], "RGB20");
+        assert_eq!(json["contractId"], asset.id().to_string());
+        assert_eq!(json["ticker"], "TST");
+        assert_eq!(json["name"], "Test asset");
+        assert_eq!(json["precision"], 2);
+        assert_eq!(json["issuedSupply"], 1500);
+        assert_eq!(
+            json["allocations"],
+            serde_json::json!([
+                { "outpoint": outpoint(0).to_string(), "amount": 1000 },
+                { "outpoint": outpoint(1).to_string(), "amount": 500 },
+            ])
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_json_label() {
+        let asset = asset();
+        assert!(!asset.to_json().unwrap().contains("\"label\""));
+        assert_eq!(
+            Asset::from_json_labeled(&asset.to_json().unwrap())
+                .unwrap()
+                .1,
+            None
+        );
+
+        let json = asset.to_json_labeled(Some("company treasury")).unwrap();
+        let (parsed, label) = Asset::from_json_labeled(&json).unwrap();
+        assert_eq!(label, Some(s!("company treasury")));
+        assert_eq!(parsed, asset);
+        assert!(parsed.semantic_eq(&asset));
+        assert_eq!(parsed.fingerprint(), asset.fingerprint());
+        // Label is ignored when reading plain asset data
+        assert_eq!(Asset::from_json(&json).unwrap(), asset);
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_json_migration() {
+        let asset = asset();
+        let mut raw: serde_json::Value =
+            serde_json::from_str(&asset.to_json().unwrap()).unwrap();
+        assert_eq!(Asset::migrate(raw.clone()).unwrap(), asset);
+
+        // Version 1 data lacking later-added fields are upgraded
+        raw["version"] = serde_json::json!(1);
+        let fields = raw["asset"].as_object_mut().unwrap();
+        assert!(fields.remove("knownInflation").is_some());
+        assert!(fields.remove("description").is_some());
+        let migrated = Asset::migrate(raw.clone()).unwrap();
+        assert!(migrated.known_inflation().is_empty());
+        assert_eq!(migrated.description(), &None);
+        assert_eq!(migrated.known_allocations(), asset.known_allocations());
+        assert_eq!(migrated.id(), asset.id());
+
+        // Current version data must be complete
+        raw["version"] = serde_json::json!(ASSET_JSON_VERSION);
+        assert!(matches!(Asset::migrate(raw), Err(AssetJsonError::Json(_))));
+
+        let raw = serde_json::json!({ "version": 3, "asset": {} });
+        assert!(matches!(
+            Asset::migrate(raw),
+            Err(AssetJsonError::UnsupportedVersion(3))
+        ));
+    }
+
+    #[test]
+    fn test_strict_encoding() {
+        let asset = asset();
+        let data = strict_serialize(&asset).unwrap();
+        assert_eq!(strict_deserialize::<Asset>(&data).unwrap(), asset);
+
+        for len in [0, 1, data.len() / 2, data.len() - 1] {
+            assert!(strict_deserialize::<Asset>(&data[..len]).is_err());
+        }
+
+        let amount = AccountingAmount::from_asset_atomic_value(&asset, 1500);
+        let mut expected = 1500u64.to_le_bytes().to_vec();
+        expected.push(2);
+        assert_eq!(strict_serialize(&amount).unwrap(), expected);
+        assert_eq!(
+            strict_deserialize::<AccountingAmount>(&expected).unwrap(),
+            amount
+        );
+    }
+
+    #[test]
+    fn test_allocations_to_csv() {
+        let asset = asset();
+        let txid =
+            "fc63f797af718cc5a11988f69507701d5fe84e58cdd900e1b02856c0ea5a058a";
+        let node_id = asset.known_allocations()[0].node_id();
+        let index0 = *asset.allocations(outpoint(0))[0].index();
+        let index1 = *asset.allocations(outpoint(1))[0].index();
+        assert_eq!(
+            asset.allocations_to_csv(),
+            format!(
+                "outpoint,node_id,index,atomic_value,accounting_value\n\
+                {txid}:0,{node_id},{index0},1000,10.00\n\
+                {txid}:1,{node_id},{index1},500,5.00\n",
+                txid = txid,
+                node_id = node_id,
+                index0 = index0,
+                index1 = index1
+            )
+        );
+
+        assert_eq!(format_accounting_value(1, 8), "0.00000001");
+        assert_eq!(format_accounting_value(1500, 0), "1500");
+        assert_eq!(
+            format_accounting_value(u64::MAX, 19),
+            "1.8446744073709551615"
+        );
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let asset = asset();
+        assert_eq!(asset.fingerprint(), asset.fingerprint());
+        assert_eq!(asset.fingerprint(), contract_fingerprint(*asset.id()));
+        assert_eq!(asset.fingerprint().len(), 16);
+        assert!(asset.fingerprint().chars().all(|c| c.is_ascii_hexdigit()));
+
+        let other = issue(bmap! {}).0;
+        assert_ne!(asset.id(), other.id());
+        assert_ne!(asset.fingerprint(), other.fingerprint());
+    }
+
+    #[test]
+    fn test_cache_filename() {
+        let asset = asset();
+        let other = issue(bmap! {}).0;
+        assert_eq!(asset.ticker(), other.ticker());
+        assert_eq!(asset.cache_filename(), asset.cache_filename());
+        assert_ne!(asset.cache_filename(), other.cache_filename());
+        assert_eq!(asset.cache_filename().len(), 64);
+        assert!(asset
+            .cache_filename()
+            .chars()
+            .all(|c| c.is_ascii_hexdigit()));
+    }
+
+    #[test]
+    fn test_summary() {
+        assert_eq!(
+            asset().summary(),
+            "TST (Test asset): 15.00 TST known, 15.00/115.00 minted"
+        );
+
+        let fixed = issue(bmap! {}).0;
+        assert_eq!(
+            fixed.summary(),
+            "TST (Test asset): 15.00 TST known, 15.00/15.00 minted"
+        );
+    }
+
+    #[test]
+    fn test_issued_at() {
+        use chrono::TimeZone;
+
+        let (asset, genesis) = issue(bmap! {});
+        let timestamp = genesis.metadata().i64(*FieldType::Timestamp)[0];
+        assert_eq!(asset.issued_at().timestamp(), timestamp);
+        assert_eq!(asset.issued_at(), Utc.timestamp(timestamp, 0));
+        assert_eq!(asset.issued_at().naive_utc(), *asset.date());
+    }
+
+    #[test]
+    fn test_supply_by_ticker() {
+        let issue = |ticker: &str, precision: u8, vout: u32, amount| {
+            rgb20::issue(
+                Chain::Testnet3,
+                ticker.to_string(),
+                s!("Test asset"),
+                None,
+                precision,
+                vec![(outpoint(vout), amount)],
+                bmap! {},
+                None,
+                None,
+            )
+            .unwrap()
+            .0
+        };
+        let first = issue("TST", 2, 0, 1500);
+        let second = issue("TST", 2, 1, 500);
+        assert_ne!(first.id(), second.id());
+        let other = issue("OTH", 0, 2, 7);
+
+        let supply = supply_by_ticker(&[first.clone(), second, other]);
+        assert_eq!(supply.len(), 2);
+        assert_eq!(supply["TST"].atomic_value(), 2000);
+        assert_eq!(supply["TST"].decimal_precision(), 2);
+        assert_eq!(supply["OTH"].atomic_value(), 7);
+
+        // Colliding tickers with different precision are summed with the
+        // largest precision
+        let precise = issue("TST", 4, 3, 5);
+        let supply = supply_by_ticker(&[first, precise]);
+        assert_eq!(supply["TST"].atomic_value(), 150005);
+        assert_eq!(supply["TST"].decimal_precision(), 4);
+
+        assert!(supply_by_ticker(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_smallest_unit() {
+        let asset = asset();
+        assert_eq!(asset.smallest_unit(), "0.01");
+        let with_precision =
+            |precision| rebuild(&asset).decimal_precision(precision).build();
+        assert_eq!(with_precision(8).smallest_unit(), "0.00000001");
+        assert_eq!(with_precision(0).smallest_unit(), "1");
+    }
+
+    #[test]
+    fn test_zero_amount() {
+        let zero = AccountingAmount::zero(8);
+        assert_eq!(zero.decimal_precision(), 8);
+        assert_eq!(zero.atomic_value(), 0);
+        assert!(zero.is_zero());
+        assert!(!AccountingAmount::from_fractioned_atomic_value(8, 1).is_zero());
+        assert_eq!(
+            zero + AccountingAmount::from_fractioned_atomic_value(8, 150),
+            AccountingAmount::from_fractioned_atomic_value(8, 150)
+        );
+    }
+
+    #[test]
+    fn test_amount_ordering() {
+        let amount = |precision, value| {
+            AccountingAmount::from_fractioned_atomic_value(precision, value)
+        };
+        assert_eq!(amount(2, 100).cmp_value(&amount(2, 100)), Ordering::Equal);
+        assert_eq!(amount(2, 99).cmp_value(&amount(2, 100)), Ordering::Less);
+        assert_eq!(
+            amount(2, 101).cmp_value(&amount(2, 100)),
+            Ordering::Greater
+        );
+
+        // Mismatched precision: 1.5 > 1.25 even though 15 < 125
+        assert_eq!(amount(1, 15).cmp_value(&amount(2, 125)), Ordering::Greater);
+        assert_eq!(amount(2, 125).cmp_value(&amount(1, 15)), Ordering::Less);
+        // Same accounting value is ordered by precision
+        assert_eq!(amount(0, 1).cmp_value(&amount(2, 100)), Ordering::Less);
+        assert_eq!(amount(2, 100).cmp_value(&amount(0, 1)), Ordering::Greater);
+        // No overflow on extreme values
+        assert_eq!(
+            amount(0, u64::MAX).cmp_value(&amount(19, u64::MAX)),
+            Ordering::Greater
+        );
+        assert_eq!(
+            amount(0, 1).cmp_value(&amount(40, u64::MAX)),
+            Ordering::Greater
+        );
+        assert_eq!(
+            amount(255, u64::MAX).cmp_value(&amount(0, 1)),
+            Ordering::Less
+        );
+        assert_eq!(amount(255, 0).cmp_value(&amount(0, 0)), Ordering::Greater);
+
+        let mut amounts = vec![amount(2, 125), amount(0, 2), amount(1, 15)];
+        amounts.sort_by(AccountingAmountExt::cmp_value);
+        assert_eq!(amounts, vec![amount(2, 125), amount(1, 15), amount(0, 2)]);
+    }
+
+    #[test]
+    fn test_format_grouped() {
+        let amount = |precision, value| {
+            AccountingAmount::from_fractioned_atomic_value(precision, value)
+        };
+        assert_eq!(amount(2, 0).format_grouped("TST"), "0.00 TST");
+        assert_eq!(amount(2, 99950).format_grouped("TST"), "999.50 TST");
+        assert_eq!(amount(2, 100000).format_grouped("TST"), "1,000.00 TST");
+        assert_eq!(
+            amount(2, 123456700).format_grouped("TST"),
+            "1,234,567.00 TST"
+        );
+        assert_eq!(
+            amount(2, 123456700).format_grouped_with("TST", ' '),
+            "1 234 567.00 TST"
+        );
+        assert_eq!(amount(0, 999).format_grouped("TST"), "999 TST");
+        assert_eq!(amount(0, 1234567).format_grouped("TST"), "1,234,567 TST");
+        assert_eq!(amount(0, 123456).format_grouped("TST"), "123,456 TST");
+        // Precisions beyond `u64` range are rendered exactly
+        assert_eq!(
+            amount(20, u64::MAX).format_grouped("TST"),
+            "0.18446744073709551615 TST"
+        );
+        assert_eq!(
+            amount(25, 1).format_grouped("TST"),
+            "0.0000000000000000000000001 TST"
+        );
+    }
+
+    #[test]
+    fn test_amount() {
+        let asset = asset();
+        let amount = asset.amount(1500);
+        assert_eq!(amount.decimal_precision(), *asset.decimal_precision());
+        assert_eq!(amount.atomic_value(), 1500);
+        assert_eq!(
+            amount,
+            AccountingAmount::from_asset_atomic_value(&asset, 1500)
+        );
+    }
+
+    #[test]
+    fn test_allocation_precisions() {
+        let mut asset = asset();
+        assert_eq!(*asset.decimal_precision(), 2);
+        assert_eq!(asset.allocation_precisions(), bset![2]);
+
+        // Consistent asset reports its precision whatever amounts it holds
+        let transfer = NodeId::commit(b"transfer");
+        asset.add_allocations(vec![
+            (outpoint(4), transfer, 0, revealed(150)),
+            (outpoint(5), transfer, 1, revealed(125)),
+            (outpoint(6), transfer, 2, revealed(0)),
+        ]);
+        assert_eq!(asset.allocation_precisions(), bset![2]);
+
+        // Amount of 10.00 units scaled with precision 8 instead of 2 exceeds
+        // the maximum supply of 115.00 units
+        asset.add_allocation(outpoint(7), transfer, 3, revealed(1_000_000_000));
+        assert_eq!(asset.allocation_precisions(), bset![2, 7]);
+    }
+
+    #[test]
+    fn test_accounting_value_rounding() {
+        let amount = |precision, value| {
+            AccountingAmount::from_fractioned_atomic_value(precision, value)
+        };
+        let modes = [
+            RoundingMode::Truncate,
+            RoundingMode::Round,
+            RoundingMode::Ceil,
+            RoundingMode::Floor,
+        ];
+
+        // Exactly representable values are not rounded
+        for mode in modes {
+            assert_eq!(amount(2, 150).accounting_value_with(mode), 1.5);
+            assert_eq!(amount(2, 0).accounting_value_with(mode), 0.0);
+        }
+
+        // 0.1 is slightly less than the closest floating point value
+        let above = 0.1f64;
+        let below = f64::from_bits(above.to_bits() - 1);
+        let tenth = amount(1, 1);
+        assert_eq!(tenth.accounting_value_with(RoundingMode::Truncate), below);
+        assert_eq!(tenth.accounting_value_with(RoundingMode::Floor), below);
+        assert_eq!(tenth.accounting_value_with(RoundingMode::Ceil), above);
+        assert_eq!(tenth.accounting_value_with(RoundingMode::Round), above);
+
+        // 0.3 is slightly more than the closest floating point value
+        let below = 0.3f64;
+        let above = f64::from_bits(below.to_bits() + 1);
+        let three_tenth = amount(1, 3);
+        assert_eq!(
+            three_tenth.accounting_value_with(RoundingMode::Truncate),
+            below
+        );
+        assert_eq!(
+            three_tenth.accounting_value_with(RoundingMode::Floor),
+            below
+        );
+        assert_eq!(
+            three_tenth.accounting_value_with(RoundingMode::Ceil),
+            above
+        );
+        assert_eq!(
+            three_tenth.accounting_value_with(RoundingMode::Round),
+            below
+        );
+
+        // Integers above 2^53 are rounded to the multiple of 256 around 2^60
+        // with ties to even
+        let base = 1u64 << 60;
+        let large = |residue| amount(0, base + residue);
+        let (below, above) = (base as f64, (base + 256) as f64);
+        assert_eq!(
+            large(1).accounting_value_with(RoundingMode::Truncate),
+            below
+        );
+        assert_eq!(large(1).accounting_value_with(RoundingMode::Ceil), above);
+        assert_eq!(large(1).accounting_value_with(RoundingMode::Round), below);
+        assert_eq!(
+            large(129).accounting_value_with(RoundingMode::Round),
+            above
+        );
+        assert_eq!(
+            large(128).accounting_value_with(RoundingMode::Round),
+            below
+        );
+        assert_eq!(
+            large(384).accounting_value_with(RoundingMode::Round),
+            (base + 512) as f64
+        );
+        assert_eq!(
+            amount(0, u64::MAX).accounting_value_with(RoundingMode::Floor),
+            (u64::MAX - 2047) as f64
+        );
+        assert_eq!(
+            amount(0, u64::MAX).accounting_value_with(RoundingMode::Ceil),
+            18446744073709551616.0
+        );
+
+        // Large precisions do not overflow
+        assert_eq!(
+            amount(22, 3).accounting_value_with(RoundingMode::Ceil),
+            f64::from_bits((3e-22f64).to_bits() + 1)
+        );
+        for precision in [23, 39, 255] {
+            assert_eq!(
+                amount(precision, 1).accounting_value_with(RoundingMode::Ceil),
+                1.0 / 10f64.powi(precision as i32)
+            );
+        }
+    }
+
+    #[test]
+    fn test_indivisible_asset() {
+        assert!(asset().is_divisible());
+
+        // Not representable by the floating point accounting value
+        let supply = (1u64 << 60) + 1;
+        let asset = rgb20::issue(
+            Chain::Testnet3,
+            s!("NFT"),
+            s!("Indivisible asset"),
+            None,
+            0,
+            vec![(outpoint(0), supply - 1), (outpoint(1), 1)],
+            bmap! {},
+            None,
+            None,
+        )
+        .unwrap()
+        .0;
+        assert!(!asset.is_divisible());
+        assert_eq!(asset.smallest_unit(), "1");
+
+        let amount = asset.amount(asset.known_atomic_value());
+        assert_ne!(amount.accounting_value() as u64, supply);
+        assert_eq!(amount.indivisible_value(), Some(supply));
+        assert_eq!(
+            amount.format_grouped("NFT"),
+            "1,152,921,504,606,846,977 NFT"
+        );
+        assert_eq!(
+            AccountingAmount::from_fractioned_atomic_value(2, 100)
+                .indivisible_value(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_allocation_split() {
+        let node_id = NodeId::commit(b"transfer");
+        let allocation =
+            Allocation::with(node_id, 1, outpoint(4), revealed(200));
+
+        let (spent, change) = allocation.split(150).unwrap();
+        assert_eq!(spent.value(), 150);
+        assert_eq!(change.value(), 50);
+        for part in &[spent, change] {
+            assert_eq!(part.node_id(), allocation.node_id());
+            assert_eq!(part.index(), allocation.index());
+            assert_eq!(part.outpoint(), allocation.outpoint());
+        }
+
+        let (spent, change) = allocation.split(200).unwrap();
+        assert_eq!(spent.value(), 200);
+        assert_eq!(change.value(), 0);
+
+        assert_eq!(
+            allocation.split(201),
+            Err(AllocationError::InsufficientValue {
+                available: 200,
+                requested: 201,
+            })
+        );
+    }
+
+    #[test]
+    fn test_burn() {
+        let asset = asset();
+        assert_eq!(*asset.supply().known_circulating(), 1500);
+        assert_eq!(asset.known_atomic_value(), 1500);
+
+        let (burned_asset, burned) = asset.burn(&bset! {outpoint(1)});
+        assert_eq!(burned, 500);
+        assert!(burned_asset.allocations(outpoint(1)).is_empty());
+        assert_eq!(burned_asset.allocations(outpoint(0)).len(), 1);
+        assert_eq!(burned_asset.known_atomic_value(), 1000);
+        assert_eq!(*burned_asset.supply().known_circulating(), 1000);
+        assert_eq!(
+            burned_asset.supply().is_issued_known(),
+            asset.supply().is_issued_known()
+        );
+
+        // Outpoints without assets do not change anything
+        let (same_asset, burned) = burned_asset.burn(&bset! {outpoint(5)});
+        assert_eq!(burned, 0);
+        assert_eq!(same_asset.known_atomic_value(), 1000);
+        assert_eq!(*same_asset.supply().known_circulating(), 1000);
+    }
+
+    #[test]
+    fn test_history() {
+        let asset = asset();
+        let history = asset.history();
+        assert_eq!(
+            history[0],
+            AssetEvent::Genesis {
+                node_id: *asset.primary_issue().unwrap().id(),
+                timestamp: Some(*asset.date()),
+                amount: 1500,
+            }
+        );
+        assert_eq!(history.len(), 1);
+
+        let mut asset = asset;
+        let node_id = NodeId::commit(b"transfer");
+        let value = revealed(200);
+        asset.add_allocation(outpoint(4), node_id, 0, value);
+        let (asset, _) = asset.burn(&bset! {outpoint(1)});
+        let history = asset.history();
+        assert!(matches!(
+            history[0],
+            AssetEvent::Genesis { amount: 1500, .. }
+        ));
+        assert_eq!(
+            history[1..],
+            [
+                AssetEvent::Received {
+                    node_id,
+                    outpoint: outpoint(4),
+                    timestamp: None,
+                    amount: 200,
+                },
+                AssetEvent::Burned {
+                    timestamp: None,
+                    amount: 500,
+                },
+            ]
+        );
+
+        // Spent allocations are reported as sent
+        let mut asset = asset;
+        let spent = asset.allocations(outpoint(0))[0];
+        asset.remove_allocation(
+            *spent.outpoint(),
+            *spent.node_id(),
+            *spent.index(),
+            *spent.revealed_amount(),
+        );
+        let history = asset.history();
+        assert_eq!(history[0].amount(), 1500);
+        assert_eq!(
+            history[2],
+            AssetEvent::Sent {
+                timestamp: None,
+                amount: 1000,
+            }
+        );
+        assert_eq!(history[3].amount(), 500);
+    }
+
+    #[test]
+    fn test_allocation_conflict() {
+        let mut asset = asset();
+        let node_id = NodeId::commit(b"transfer");
+        let value = revealed(200);
+        assert_eq!(
+            asset.insert_allocation(outpoint(4), node_id, 0, value),
+            AllocationInsertion::Added
+        );
+        assert_eq!(
+            asset.insert_allocation(outpoint(4), node_id, 0, value),
+            AllocationInsertion::Known
+        );
+        let known = Allocation::with(node_id, 0, outpoint(4), value);
+        assert_eq!(
+            asset.insert_allocation(outpoint(5), node_id, 0, value),
+            AllocationInsertion::Conflict(known)
+        );
+        let other = revealed(300);
+        assert_eq!(
+            asset.insert_allocation(outpoint(4), node_id, 0, other),
+            AllocationInsertion::Conflict(known)
+        );
+        assert_eq!(asset.known_allocations().len(), 3);
+        assert_eq!(asset.allocations(outpoint(4)), vec![known]);
+        assert!(asset.allocations(outpoint(5)).is_empty());
+
+        // Different index of the same node is a different allocation
+        assert_eq!(
+            asset.insert_allocation(outpoint(4), node_id, 1, other),
+            AllocationInsertion::Added
+        );
+    }
+
+    #[test]
+    fn test_refresh_from_genesis() {
+        let (mut asset, genesis) = issue(bmap! { outpoint(2) => 10000 });
+        let node_id = NodeId::commit(b"transfer");
+        let value = revealed(200);
+        asset.insert_allocation(outpoint(4), node_id, 0, value);
+        let known = asset.clone();
+
+        asset.refresh_from_genesis(&genesis).unwrap();
+        assert_eq!(asset.known_allocations().len(), 3);
+        assert_eq!(
+            asset.allocations(outpoint(4)),
+            vec![Allocation::with(node_id, 0, outpoint(4), value)]
+        );
+        assert_eq!(asset.known_issues(), known.known_issues());
+        assert_eq!(asset.known_inflation(), known.known_inflation());
+        assert_eq!(asset.supply(), known.supply());
+
+        let (_, other) = issue(bmap! {});
+        assert_eq!(
+            asset.refresh_from_genesis(&other),
+            Err(GenesisError::ContractMismatch(other.contract_id()))
+        );
+        assert_eq!(asset.known_allocations().len(), 3);
+    }
+
+    #[test]
+    fn test_merge() {
+        let asset = issue(bmap! { outpoint(2) => 10000 }).0;
+        let secondary_id = NodeId::commit(b"secondary issue");
+        let with_issues = |issues: Vec<Issue>, circulating| {
+            issues
+                .into_iter()
+                .fold(rebuild(&asset), |rebuild, issue| rebuild.issue(issue))
+                .supply(Supply::with(
+                    circulating,
+                    None,
+                    *asset.supply().issue_limit(),
+                ))
+                .build()
+        };
+        let issued = Issue::with(secondary_id, 300, Some(outpoint(2)));
+        let unknown_origin = Issue::with(secondary_id, 300, None);
+
+        // Both snapshots know the same secondary issue; the first one also
+        // has a duplicated entry with unknown origin
+        let mut merged = with_issues(vec![unknown_origin, issued], 1800);
+        let mut other = with_issues(vec![issued], 1800);
+        let value = revealed(300);
+        other.insert_allocation(outpoint(5), secondary_id, 0, value);
+        merged.merge(&other).unwrap();
+
+        assert_eq!(
+            merged.secondary_issues().copied().collect::<Vec<_>>(),
+            vec![issued]
+        );
+        assert_eq!(merged.known_issues().len(), 2);
+        assert_eq!(*merged.supply().known_circulating(), 1800);
+        assert_eq!(merged.verify_supply_consistency(0), Ok(()));
+        assert_eq!(merged.known_allocations().len(), 3);
+        assert_eq!(merged.allocations(outpoint(5)).len(), 1);
+
+        let (other, _) = rgb20::issue(
+            Chain::Testnet3,
+            s!("OTH"),
+            s!("Other asset"),
+            None,
+            2,
+            vec![(outpoint(0), 1000)],
+            bmap! {},
+            None,
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            merged.merge(&other),
+            Err(GenesisError::ContractMismatch(*other.id()))
+        );
+    }
+
+    #[test]
+    fn test_issued_and_received_allocations() {
+        let (mut asset, genesis) = rgb20::issue(
+            Chain::Testnet3,
+            s!("TST"),
+            s!("Test asset"),
+            None,
+            2,
+            vec![(outpoint(0), 1000)],
+            bmap! {},
+            None,
+            None,
+        )
+        .unwrap();
+        let node_id = NodeId::commit(b"transfer");
+        let value = revealed(200);
+        asset.insert_allocation(outpoint(4), node_id, 0, value);
+
+        let issued = asset.issued_allocations();
+        assert_eq!(issued.len(), 1);
+        assert_eq!(*issued[0].node_id(), genesis.node_id());
+        assert_eq!(*issued[0].outpoint(), outpoint(0));
+        let received = asset.received_allocations();
+        assert_eq!(received.len(), 1);
+        assert_eq!(*received[0].node_id(), node_id);
+        assert_eq!(*received[0].outpoint(), outpoint(4));
+    }
+
+    #[test]
+    fn test_add_allocations() {
+        let mut asset = asset();
+        let known = asset.known_allocations()[0];
+        let transfer = NodeId::commit(b"transfer");
+        let value = revealed(200);
+
+        let added = asset.add_allocations(vec![
+            (
+                *known.outpoint(),
+                *known.node_id(),
+                *known.index(),
+                *known.revealed_amount(),
+            ),
+            (outpoint(4), transfer, 0, value),
+            (outpoint(5), transfer, 1, value),
+            (outpoint(4), transfer, 0, value),
+        ]);
+        assert_eq!(added, 2);
+        assert_eq!(asset.known_allocations().len(), 4);
+        assert_eq!(asset.allocations_by_node(transfer).len(), 2);
+
+        assert_eq!(
+            asset.add_allocations(vec![(outpoint(5), transfer, 1, value)]),
+            0
+        );
+        assert_eq!(asset.known_allocations().len(), 4);
+
+        // Allocations conflicting with the known ones are not added
+        assert_eq!(
+            asset.add_allocations(vec![(outpoint(6), transfer, 1, value)]),
+            0
+        );
+        assert_eq!(asset.known_allocations().len(), 4);
+        assert!(asset.detect_allocation_conflicts().is_empty());
+    }
+
+    #[test]
+    fn test_allocations_by_node() {
+        let mut asset = asset();
+        let transfer1 = NodeId::commit(b"transfer1");
+        let transfer2 = NodeId::commit(b"transfer2");
+        let value = revealed(200);
+        asset.insert_allocation(outpoint(4), transfer1, 0, value);
+        asset.insert_allocation(outpoint(5), transfer2, 0, value);
+        asset.insert_allocation(outpoint(6), transfer1, 1, value);
+
+        let allocations = asset.allocations_by_node(transfer1);
+        assert_eq!(
+            allocations
+                .iter()
+                .map(|allocation| (*allocation.outpoint(), *allocation.index()))
+                .collect::<Vec<_>>(),
+            vec![(outpoint(4), 0), (outpoint(6), 1)]
+        );
+        assert!(allocations
+            .iter()
+            .all(|allocation| *allocation.node_id() == transfer1));
+        assert_eq!(asset.allocations_by_node(transfer2).len(), 1);
+        assert!(asset
+            .allocations_by_node(NodeId::commit(b"unknown"))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_allocation_conflicts() {
+        let mut asset = asset();
+        assert!(asset.detect_allocation_conflicts().is_empty());
+
+        // Two received consignments assign the same transition output to
+        // different seals
+        let node_id = NodeId::commit(b"transfer");
+        let value = revealed(200);
+        asset.add_allocation(outpoint(4), node_id, 0, value);
+        asset.add_allocation(outpoint(5), node_id, 0, value);
+        asset.add_allocation(outpoint(6), node_id, 1, value);
+
+        assert_eq!(
+            asset.detect_allocation_conflicts(),
+            vec![(
+                Allocation::with(node_id, 0, outpoint(4), value),
+                Allocation::with(node_id, 0, outpoint(5), value)
+            )]
+        );
+    }
+
+    #[test]
+    fn test_semantic_eq() {
+        let asset = asset();
+        let mut reordered = asset.clone();
+        let first = reordered.known_allocations()[0];
+        reordered.remove_allocation(
+            *first.outpoint(),
+            *first.node_id(),
+            *first.index(),
+            *first.revealed_amount(),
+        );
+        reordered.add_allocation(
+            *first.outpoint(),
+            *first.node_id(),
+            *first.index(),
+            *first.revealed_amount(),
+        );
+        assert_ne!(asset, reordered);
+        assert!(asset.semantic_eq(&reordered));
+        assert!(asset.diff(&reordered).is_empty());
+
+        // Allocation-only difference
+        let mut received = asset.clone();
+        let value = revealed(200);
+        let node_id = NodeId::commit(b"transfer");
+        received.add_allocation(outpoint(4), node_id, 0, value);
+        assert!(!asset.semantic_eq(&received));
+        let allocation = Allocation::with(node_id, 0, outpoint(4), value);
+        assert_eq!(
+            asset.diff(&received),
+            AssetDiff {
+                added_allocations: vec![allocation],
+                ..AssetDiff::default()
+            }
+        );
+        assert_eq!(
+            received.diff(&asset),
+            AssetDiff {
+                removed_allocations: vec![allocation],
+                ..AssetDiff::default()
+            }
+        );
+
+        // Supply difference
+        let reconciled = asset.reconcile(&bset! { outpoint(2) });
+        assert_ne!(asset.supply(), reconciled.supply());
+        assert!(!asset.semantic_eq(&reconciled));
+        assert!(asset.diff(&reconciled).is_empty());
+    }
+
+    #[test]
+    fn test_rollback_to_height() {
+        let mut asset = issue(bmap! {outpoint(2) => 10000}).0;
+        let original = asset.clone();
+        let spendings = vec![
+            OutpointSpending {
+                outpoint: outpoint(0),
+                height: 100,
+                allocations: asset.on_outpoint_spent(&outpoint(0)),
+            },
+            OutpointSpending {
+                outpoint: outpoint(1),
+                height: 110,
+                allocations: asset.on_outpoint_spent(&outpoint(1)),
+            },
+            OutpointSpending {
+                outpoint: outpoint(2),
+                height: 120,
+                allocations: asset.on_outpoint_spent(&outpoint(2)),
+            },
+        ];
+        assert_eq!(asset.known_atomic_value(), 0);
+        assert_eq!(*asset.supply().is_issued_known(), Some(false));
+
+        // Reorg not reaching the spendings changes nothing
+        let spent = asset.clone();
+        assert!(asset.rollback_to_height(120, &spendings).is_empty());
+        assert_eq!(asset, spent);
+
+        // Reorg below the spending height restores the spent allocation
+        let restored = asset.rollback_to_height(105, &spendings);
+        assert_eq!(restored, original.allocations(outpoint(1)));
+        assert_eq!(asset.allocations(outpoint(1)), restored);
+        assert!(asset.allocations(outpoint(0)).is_empty());
+        assert_eq!(asset.known_atomic_value(), 500);
+        // Spending of the inflation right was orphaned as well
+        assert_eq!(*asset.supply().is_issued_known(), None);
+
+        // Repeated rollback does not duplicate allocations
+        let restored = asset.rollback_to_height(0, &spendings);
+        assert_eq!(restored, original.allocations(outpoint(0)));
+        assert_eq!(asset.known_atomic_value(), 1500);
+        assert!(asset.diff(&original).is_empty());
+    }
+
+    #[test]
+    fn test_outpoint_spent() {
+        let mut asset = issue(bmap! {outpoint(2) => 10000}).0;
+        let supply = *asset.supply();
+
+        let spent = asset.on_outpoint_spent(&outpoint(1));
+        assert_eq!(spent.len(), 1);
+        assert_eq!(spent[0].value(), 500);
+        assert!(asset.allocations(outpoint(1)).is_empty());
+        assert_eq!(asset.known_atomic_value(), 1000);
+        assert_eq!(asset.supply(), &supply);
+
+        // Spending outpoint without allocations changes nothing
+        assert!(asset.on_outpoint_spent(&outpoint(1)).is_empty());
+        assert!(asset.on_outpoint_spent(&outpoint(5)).is_empty());
+        assert_eq!(asset.known_atomic_value(), 1000);
+
+        // Spent inflation right may have produced unknown issue
+        assert!(asset.on_outpoint_spent(&outpoint(2)).is_empty());
+        assert_eq!(*asset.supply().is_issued_known(), Some(false));
+        assert_eq!(asset.supply().total_circulating(), None);
+        assert_eq!(
+            asset.supply().known_circulating(),
+            supply.known_circulating()
+        );
+        assert_eq!(asset.known_atomic_value(), 1000);
+    }
+
+    #[test]
+    fn test_inflation_rights() {
+        let asset = issue(bmap! {
+            outpoint(3) => 5000,
+            outpoint(2) => 10000
+        })
+        .0;
+        assert_eq!(
+            asset.inflation_rights(),
+            vec![(outpoint(2), 10000), (outpoint(3), 5000)]
+        );
+        assert!(issue(bmap! {}).0.inflation_rights().is_empty());
+    }
+
+    #[test]
+    fn test_genesis_seal_role_conflict() {
+        let genesis = issue(bmap! {
+            outpoint(1) => 100,
+            outpoint(2) => 10000
+        })
+        .1;
+        assert_eq!(
+            Asset::from_genesis(&genesis),
+            Err(GenesisError::SealRoleConflict(outpoint(1)))
+        );
+        assert!(
+            Asset::from_genesis(&issue(bmap! {outpoint(2) => 10000}).1).is_ok()
+        );
+    }
+
+    #[test]
+    fn test_outpoint_role() {
+        let asset = issue(bmap! {
+            outpoint(1) => 100,
+            outpoint(2) => 10000
+        })
+        .0;
+        assert_eq!(asset.outpoint_role(&outpoint(0)), OutpointRole::Allocation);
+        assert_eq!(asset.outpoint_role(&outpoint(1)), OutpointRole::Both);
+        assert_eq!(asset.outpoint_role(&outpoint(2)), OutpointRole::Inflation);
+        assert_eq!(asset.outpoint_role(&outpoint(3)), OutpointRole::None);
+    }
+
+    #[test]
+    fn test_allocations_digest() {
+        let asset = asset();
+        let allocations = asset.known_allocations().clone();
+        assert_eq!(allocations.len(), 2);
+        let with_allocations = |allocations: Vec<Allocation>| {
+            rebuild(&asset).allocations(allocations).build()
+        };
+
+        let digest = asset.allocations_digest();
+        let reordered =
+            with_allocations(allocations.iter().rev().cloned().collect());
+        assert_eq!(reordered.allocations_digest(), digest);
+        assert_ne!(
+            with_allocations(allocations[..1].to_vec()).allocations_digest(),
+            digest
+        );
+        assert_ne!(with_allocations(vec![]).allocations_digest(), digest);
+    }
+
+    #[test]
+    fn test_allocation_status() {
+        let asset = issue(bmap! {}).0;
+        let mut tx_heights = bmap! {};
+        let status = |tx_heights: &BTreeMap<Txid, u32>, tip_height| {
+            asset.allocation_status(&outpoint(0), tx_heights, tip_height)
+        };
+
+        assert_eq!(
+            status(&tx_heights, 100),
+            Some(AllocationStatus::Unconfirmed)
+        );
+        assert_eq!(AllocationStatus::Unconfirmed.to_string(), "pending");
+        assert_eq!(AllocationStatus::Unconfirmed.confirmations(100), 0);
+
+        // Transaction gets mined
+        tx_heights.insert(outpoint(0).txid, 100);
+        assert_eq!(
+            status(&tx_heights, 99),
+            Some(AllocationStatus::Unconfirmed)
+        );
+        assert_eq!(
+            status(&tx_heights, 100),
+            Some(AllocationStatus::Confirmed(100))
+        );
+        assert_eq!(AllocationStatus::Confirmed(100).confirmations(100), 1);
+        assert_eq!(AllocationStatus::Confirmed(100).confirmations(105), 6);
+
+        assert_eq!(
+            asset.allocation_status(&outpoint(3), &tx_heights, 100),
+            None
+        );
+    }
+
+    #[test]
+    fn test_select_allocations_min_inputs() {
+        let asset = |amounts: &[AtomicValue]| {
+            rgb20::issue(
+                Chain::Testnet3,
+                s!("TST"),
+                s!("Test asset"),
+                None,
+                0,
+                amounts
+                    .iter()
+                    .enumerate()
+                    .map(|(vout, amount)| (outpoint(vout as u32), *amount))
+                    .collect(),
+                bmap! {},
+                None,
+                None,
+            )
+            .unwrap()
+            .0
+        };
+
+        let asset1 = asset(&[100, 100, 100, 100, 100, 300, 300]);
+        assert_eq!(
+            asset1.select_allocations_min_inputs(500),
+            Some(vec![(outpoint(5), 300), (outpoint(6), 300)])
+        );
+        assert_eq!(
+            asset1.select_allocations_min_inputs(300),
+            Some(vec![(outpoint(5), 300)])
+        );
+        assert_eq!(asset1.select_allocations_min_inputs(1101), None);
+
+        // The last input is the smallest one covering the rest of the target
+        let asset2 = asset(&[1000, 600, 450]);
+        assert_eq!(
+            asset2.select_allocations_min_inputs(1400),
+            Some(vec![(outpoint(0), 1000), (outpoint(2), 450)])
+        );
+        assert_eq!(asset2.select_allocations_min_inputs(0), Some(vec![]));
+
+        // Allocations on the same outpoint are spent by a single input
+        let mut asset3 = asset(&[400]);
+        let value = revealed(200);
+        asset3.insert_allocation(
+            outpoint(0),
+            NodeId::commit(b"transfer"),
+            0,
+            value,
+        );
+        assert_eq!(
+            asset3.select_allocations_min_inputs(550),
+            Some(vec![(outpoint(0), 600)])
+        );
+    }
+
+    #[test]
+    fn test_dust_allocations() {
+        let asset = rgb20::issue(
+            Chain::Testnet3,
+            s!("TST"),
+            s!("Test asset"),
+            None,
+            0,
+            vec![
+                (outpoint(0), 1),
+                (outpoint(1), 5),
+                (outpoint(2), 99),
+                (outpoint(3), 100),
+                (outpoint(4), 5000),
+            ],
+            bmap! {},
+            None,
+            None,
+        )
+        .unwrap()
+        .0;
+
+        let dust = asset.dust_allocations(100);
+        let mut dust = dust
+            .iter()
+            .map(|allocation| (*allocation.outpoint(), allocation.value()))
+            .collect::<Vec<_>>();
+        dust.sort();
+        assert_eq!(
+            dust,
+            vec![(outpoint(0), 1), (outpoint(1), 5), (outpoint(2), 99)]
+        );
+        assert!(asset.dust_allocations(1).is_empty());
+    }
+
+    #[test]
+    fn test_ownership_fraction() {
+        let asset = rgb20::issue(
+            Chain::Testnet3,
+            s!("TST"),
+            s!("Test asset"),
+            None,
+            0,
+            vec![(outpoint(0), 750), (outpoint(1), 375), (outpoint(2), 375)],
+            bmap! {},
+            None,
+            None,
+        )
+        .unwrap()
+        .0;
+        assert_eq!(asset.ownership_fraction(&outpoint(0)), 0.5);
+        assert_eq!(asset.ownership_fraction(&outpoint(2)), 0.25);
+        assert_eq!(asset.ownership_fraction(&outpoint(3)), 0.0);
+
+        let (burned, _) =
+            asset.burn(&bset! {outpoint(0), outpoint(1), outpoint(2)});
+        assert_eq!(*burned.supply().known_circulating(), 0);
+        assert_eq!(burned.ownership_fraction(&outpoint(0)), 0.0);
+    }
+
+    #[test]
+    fn test_cached_asset_balance() {
+        let mut asset = CachedAsset::new(issue(bmap! {}).0);
+        assert_eq!(asset.cached_known_value(), 1500);
+        assert_eq!(asset.cached_known_value(), 1500);
+
+        let node_id = NodeId::commit(b"transfer");
+        let value = revealed(200);
+        assert!(asset.add_allocation(outpoint(4), node_id, 0, value));
+        assert_eq!(asset.cached_known_value(), 1700);
+
+        // Duplicated allocation is not added and keeps the balance
+        assert!(!asset.add_allocation(outpoint(4), node_id, 0, value));
+        assert_eq!(asset.cached_known_value(), 1700);
+
+        let spent = asset.allocations(outpoint(0))[0];
+        assert!(asset.remove_allocation(
+            *spent.outpoint(),
+            *spent.node_id(),
+            *spent.index(),
+            *spent.revealed_amount(),
+        ));
+        assert_eq!(asset.cached_known_value(), 700);
+        assert_eq!(
+            asset.cached_known_value(),
+            asset.into_asset().known_atomic_value()
+        );
+    }
+
+    #[test]
+    fn test_total_inflation_capacity() {
+        let asset = issue(bmap! {
+            outpoint(2) => 10000,
+            outpoint(3) => 5000
+        })
+        .0;
+        let supply = *asset.supply();
+        let asset = with_supply(
+            &asset,
+            Supply::with(*supply.known_circulating(), None, 700),
+        );
+        assert_eq!(asset.total_inflation_capacity(), 15700);
+
+        // Unknown amount of inflation on concealed seals
+        let asset = with_supply(
+            &asset,
+            Supply::with(*supply.known_circulating(), None, u64::MAX),
+        );
+        assert_eq!(asset.total_inflation_capacity(), u64::MAX);
+
+        // Revealed inflation rights summing past `u64::MAX`
+        let amount = AtomicValue::MAX / 2 + 1;
+        let (_, genesis) = issue(bmap! {
+            outpoint(2) => amount,
+            outpoint(3) => amount,
+            outpoint(4) => amount
+        });
+        let asset = Asset::from_genesis(&genesis).unwrap();
+        assert_eq!(asset.known_inflation().len(), 3);
+        assert_eq!(asset.total_inflation_capacity(), u64::MAX);
+    }
+
+    #[test]
+    fn test_allocations_within_supply() {
+        let (mut asset, _) = issue(bmap! {});
+        assert_eq!(asset.verify_allocations_within_supply(), Ok(()));
+
+        // Fabricated allocation assigning more than was ever issued
+        asset.add_allocation(
+            outpoint(5),
+            NodeId::commit(b"fabricated transfer"),
+            0,
+            revealed(1),
+        );
+        assert_eq!(
+            asset.verify_allocations_within_supply(),
+            Err(SupplyError::AllocationsExceedSupply {
+                allocated: 1501,
+                supply: 1500
+            })
+        );
+
+        // Inflation rights increase the maximum possible supply
+        let (asset, _) = issue(bmap! { outpoint(2) => 10000 });
+        assert_eq!(asset.verify_allocations_within_supply(), Ok(()));
+    }
+
+    #[test]
+    fn test_supply_consistency() {
+        let (asset, _) = issue(bmap! { outpoint(2) => 10000 });
+        assert_eq!(asset.verify_supply_consistency(0), Ok(()));
+
+        // Burned assets decrease circulating supply only
+        let (burned_asset, burned) = asset.burn(&bset! { outpoint(1) });
+        assert_eq!(burned_asset.verify_supply_consistency(burned), Ok(()));
+        assert_eq!(
+            burned_asset.verify_supply_consistency(0),
+            Err(SupplyError::CirculatingMismatch {
+                circulating: 1000,
+                expected: 1500
+            })
+        );
+        assert_eq!(
+            asset.verify_supply_consistency(1600),
+            Err(SupplyError::BurnedExceedsIssued {
+                burned: 1600,
+                issued: 1500
+            })
+        );
+
+        let supply = *asset.supply();
+        let corrupted = |circulating| {
+            with_supply(
+                &asset,
+                Supply::with(
+                    circulating,
+                    *supply.is_issued_known(),
+                    *supply.issue_limit(),
+                ),
+            )
+        };
+        assert_eq!(
+            corrupted(1600).verify_supply_consistency(0),
+            Err(SupplyError::CirculatingMismatch {
+                circulating: 1600,
+                expected: 1500
+            })
+        );
+        // Circulating supply lowered without a burn is detected as well
+        assert_eq!(
+            corrupted(1400).verify_supply_consistency(0),
+            Err(SupplyError::CirculatingMismatch {
+                circulating: 1400,
+                expected: 1500
+            })
+        );
+
+        let overissued = rebuild(&asset)
+            .issue(Issue::with(
+                NodeId::commit(b"secondary issue"),
+                20000,
+                Some(outpoint(2)),
+            ))
+            .supply(Supply::with(21500, None, 0))
+            .build();
+        assert_eq!(
+            overissued.verify_supply_consistency(0),
+            Err(SupplyError::ExceedsInflationCapacity {
+                issued: 20000,
+                capacity: 10000
+            })
+        );
+    }
+
+    #[test]
+    fn test_reconciliation() {
+        let asset = issue(bmap! {
+            outpoint(2) => 10000,
+            outpoint(3) => 5000
+        })
+        .0;
+        assert_eq!(
+            asset.unreconciled_inflation(),
+            vec![outpoint(2), outpoint(3)]
+        );
+        assert!(!asset.is_fully_reconciled());
+
+        // Partially reconciled: only one of inflation outpoints is spent
+        let asset = rebuild(&asset)
+            .issue(Issue::with(
+                NodeId::commit(b"secondary issue"),
+                300,
+                Some(outpoint(2)),
+            ))
+            .supply(Supply::with(1800, None, *asset.supply().issue_limit()))
+            .build();
+        assert_eq!(asset.unreconciled_inflation(), vec![outpoint(3)]);
+        assert!(!asset.is_fully_reconciled());
+        let partial = asset.reconcile(&bset! {});
+        assert_eq!(*partial.supply().is_issued_known(), None);
+        assert_eq!(partial.supply().total_circulating(), None);
+        assert!(!partial.is_fully_reconciled());
+
+        // Fully reconciled: the rest of inflation outpoints is confirmed
+        // unspent
+        let full = asset.reconcile(&bset! { outpoint(3) });
+        assert_eq!(*full.supply().is_issued_known(), Some(true));
+        assert_eq!(full.supply().total_circulating(), Some(1800));
+        assert!(full.is_fully_reconciled());
+
+        // Fixed-supply assets are always fully reconciled
+        let (_, genesis) = issue(bmap! {});
+        let asset = Asset::from_genesis(&genesis).unwrap();
+        assert!(asset.unreconciled_inflation().is_empty());
+        assert!(asset.is_fully_reconciled());
+    }
+}
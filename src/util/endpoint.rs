@@ -0,0 +1,99 @@
+// RGB standard library
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use std::net::ToSocketAddrs;
+
+use internet2::ZmqSocketAddr;
+
+/// Error parsing RPC endpoint
+#[derive(Clone, PartialEq, Eq, Debug, Display, Error)]
+#[display("`{endpoint}`: {reason}")]
+pub struct EndpointError {
+    /// Endpoint string which failed to parse
+    pub endpoint: String,
+
+    /// Description of the failure
+    pub reason: String,
+}
+
+/// Parses ZMQ socket address of an RPC endpoint. In addition to the native
+/// `lnpz:` forms, accepts standard ZMQ `tcp://host:port`, `ipc:///path` and
+/// `inproc://name` URLs. Malformed endpoints are reported before any socket
+/// is created.
+pub fn parse_endpoint(endpoint: &str) -> Result<ZmqSocketAddr, EndpointError> {
+    let invalid = |reason: &str| EndpointError {
+        endpoint: endpoint.to_owned(),
+        reason: reason.to_owned(),
+    };
+    if let Some(addr) = endpoint.strip_prefix("tcp://") {
+        addr.to_socket_addrs()
+            .map_err(|err| invalid(&err.to_string()))?
+            .next()
+            .map(ZmqSocketAddr::Tcp)
+            .ok_or_else(|| invalid("host does not resolve to any address"))
+    } else if let Some(path) = endpoint.strip_prefix("ipc://") {
+        if path.is_empty() {
+            return Err(invalid("socket file path is required"));
+        }
+        Ok(ZmqSocketAddr::Ipc(path.to_owned()))
+    } else if let Some(name) = endpoint.strip_prefix("inproc://") {
+        if name.is_empty() || name.contains('/') {
+            return Err(invalid("name must be non-empty and contain no `/`"));
+        }
+        Ok(ZmqSocketAddr::Inproc(name.to_owned()))
+    } else {
+        endpoint
+            .parse()
+            .map_err(|err: internet2::AddrError| invalid(&err.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_endpoint() {
+        assert_eq!(
+            parse_endpoint("tcp://127.0.0.1:61961").unwrap(),
+            ZmqSocketAddr::Tcp("127.0.0.1:61961".parse().unwrap())
+        );
+        assert_eq!(
+            parse_endpoint("ipc:///var/lib/rgb/fungibled.rpc").unwrap(),
+            ZmqSocketAddr::Ipc(s!("/var/lib/rgb/fungibled.rpc"))
+        );
+        assert_eq!(
+            parse_endpoint("inproc://fungibled").unwrap(),
+            ZmqSocketAddr::Inproc(s!("fungibled"))
+        );
+        assert_eq!(
+            parse_endpoint("lnpz:/var/lib/rgb/stashd.rpc").unwrap(),
+            ZmqSocketAddr::Ipc(s!("/var/lib/rgb/stashd.rpc"))
+        );
+
+        for malformed in &[
+            "tcp://127.0.0.1",
+            "ipc://",
+            "inproc://",
+            "udp://127.0.0.1:61961",
+        ] {
+            match parse_endpoint(malformed) {
+                Err(err) => {
+                    assert_eq!(err.endpoint, *malformed);
+                    assert!(err.to_string().contains(malformed))
+                }
+                other => panic!("{} must be invalid: {:?}", malformed, other),
+            }
+        }
+    }
+}
@@ -58,6 +58,10 @@ pub enum MagicNumber {
     /// Equals to first 4 bytes of SHA256("rgb:stash")
     /// = cd22a2cb85720d51f1616752cb85059a02f3d35f7dda30a4ca981b59b0924354
     Stash = 0xcd22a2cb,
+
+    /// Equals to first 4 bytes of SHA256("rgb:cache")
+    /// = cf28b28f3212536f705bc4386785e33cf1fe30946412450c98672deacfc4c125
+    Cache = 0xcf28b28f,
 }
 
 impl MagicNumber {
@@ -82,6 +86,7 @@ impl TryFrom<u32> for MagicNumber {
             n if n == Self::Consignment.to_u32() => Self::Consignment,
             n if n == Self::Disclosure.to_u32() => Self::Disclosure,
             n if n == Self::Stash.to_u32() => Self::Stash,
+            n if n == Self::Cache.to_u32() => Self::Cache,
             invalid => Err(invalid)?,
         })
     }
@@ -13,11 +13,30 @@
 
 #[macro_use]
 mod macros;
+#[cfg(feature = "fungibles")]
+mod asset;
 mod bech32data;
+#[cfg(feature = "_rpc")]
+mod endpoint;
 pub mod file;
 mod magic_numbers;
+mod scoped_id;
 mod seal_spec;
 
+#[cfg(all(feature = "fungibles", feature = "serde"))]
+pub use asset::AssetJsonError;
+#[cfg(feature = "fungibles")]
+pub use asset::{
+    contract_fingerprint, pending_allocations, renomination_right,
+    skipped_allocations, supply_by_ticker, AccountingAmountExt,
+    AllocationError, AllocationExt, AllocationInsertion, AllocationStatus,
+    AssetDiff, AssetEvent, AssetExt, CachedAsset, GenesisError, OutpointRole,
+    OutpointSpending, PendingAllocation, RoundingMode, SkippedAllocation,
+    SupplyError, ASSET_JSON_VERSION, RGB20_SCHEMA_ID,
+};
 pub use bech32data::{FromBech32Data, ToBech32Data};
+#[cfg(feature = "_rpc")]
+pub use endpoint::{parse_endpoint, EndpointError};
 pub use magic_numbers::MagicNumber;
+pub use scoped_id::ChainScopedContractId;
 pub use seal_spec::SealSpec;
@@ -0,0 +1,51 @@
+// RGB standard library
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use lnpbp::chain::Chain;
+use rgb::ContractId;
+#[cfg(feature = "fungibles")]
+use rgb20::Asset;
+
+/// Contract id together with the chain the contract is issued on. Unlike the
+/// bare contract id, it can't be used by mistake with the RGB node running on
+/// another chain: the integration API checks the chain before sending the
+/// request.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Display)]
+#[display("{contract_id}@{chain}")]
+pub struct ChainScopedContractId {
+    contract_id: ContractId,
+    chain: Chain,
+}
+
+impl ChainScopedContractId {
+    pub fn new(contract_id: ContractId, chain: Chain) -> Self {
+        Self { contract_id, chain }
+    }
+
+    #[inline]
+    pub fn contract_id(&self) -> ContractId {
+        self.contract_id
+    }
+
+    #[inline]
+    pub fn chain(&self) -> &Chain {
+        &self.chain
+    }
+}
+
+#[cfg(feature = "fungibles")]
+impl From<&Asset> for ChainScopedContractId {
+    fn from(asset: &Asset) -> Self {
+        Self::new(*asset.id(), asset.chain().clone())
+    }
+}